@@ -0,0 +1,155 @@
+use axum::{
+    body::{to_bytes, Body},
+    extract::{ConnectInfo, Query, Request, State},
+    http::{header, HeaderMap},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+use std::net::{IpAddr, SocketAddr};
+
+use crate::pairing::{bearer_token, client_id_header};
+use crate::server::API_PREFIX;
+use crate::error::AppError;
+use crate::AppState;
+
+/// 認証なしでアクセスできるパス（`API_PREFIX` 付きのパスも同様）
+///
+/// `/health` は接続確認、`/capabilities`・`/openapi.json` はペアリング前の互換性確認、
+/// `/auth` はペアリング（パスワード自体の検証）に使用します。
+pub const PUBLIC_PATHS: &[&str] = &["/health", "/capabilities", "/openapi.json", "/auth"];
+
+fn is_public_path(path: &str) -> bool {
+    let path = path.strip_prefix(API_PREFIX).unwrap_or(path);
+    PUBLIC_PATHS.contains(&path)
+}
+
+/// 認証情報を確認するためにバッファするリクエストボディの上限（axumのデフォルトと同じ2MB）
+const MAX_AUTH_BODY_BYTES: usize = 2 * 1024 * 1024;
+
+/// リクエストに含まれる認証情報
+///
+/// 以下の順で取得します。
+/// * ヘッダー: `x-client-id`、`Authorization: Bearer <token>`、`x-password`
+/// * クエリ: `client_id`、`token`、`password`（ヘッダーを付与できないWebSocket用）
+/// * JSONボディの `password`（従来の `/input` との互換用）
+///
+/// 認証に成功したリクエストには、検証した値をエクステンションとして付与します
+/// （WebSocketなどのハンドラーは、ミドルウェアと同じクライアントIDを使うためにこれを参照します）。
+#[derive(Clone, Default, Deserialize)]
+pub(crate) struct Credentials {
+    pub(crate) client_id: Option<String>,
+    pub(crate) token: Option<String>,
+    pub(crate) password: Option<String>,
+}
+
+impl Credentials {
+    fn from_headers(headers: &HeaderMap) -> Self {
+        Self {
+            client_id: client_id_header(headers).map(|id| id.to_string()),
+            token: bearer_token(headers).map(|token| token.to_string()),
+            password: headers
+                .get("x-password")
+                .and_then(|h| h.to_str().ok())
+                .map(|password| password.to_string()),
+        }
+    }
+
+    // 足りない項目を別の取得元で補う
+    fn or(self, other: Credentials) -> Self {
+        Self {
+            client_id: self.client_id.or(other.client_id),
+            token: self.token.or(other.token),
+            password: self.password.or(other.password),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct BodyPassword {
+    password: Option<String>,
+}
+
+/// 接続元のIPアドレスを取得する（`into_make_service_with_connect_info` で起動した場合のみ）
+pub fn client_ip(request: &Request) -> Option<IpAddr> {
+    request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip())
+}
+
+/// 全エンドポイント共通の認証ミドルウェア
+///
+/// 全てのリクエストにIPアドレスごとのレート制限（429）を適用します。
+/// `PUBLIC_PATHS` 以外へのリクエストは、デバイストークンまたは
+/// ワンタイムパスワードで認証されていない限り401を返し、
+/// 失敗が続いたクライアントID・IPアドレスは一定時間429を返します。
+/// デスクトップで接続を禁止したクライアントID・IPアドレスは全てのパスで403を返します。
+/// エラーのボディは `AppError` のJSON（`code` で理由を判別）です。
+pub async fn require_auth(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let ip = client_ip(&request);
+
+    let within_limit = match state.lock() {
+        Ok(mut state_guard) => state_guard.check_rate_limit(ip),
+        Err(_) => false,
+    };
+    if !within_limit {
+        return AppError::RateLimited.into_response();
+    }
+
+    let query = Query::<Credentials>::try_from_uri(request.uri())
+        .map(|Query(credentials)| credentials)
+        .unwrap_or_default();
+    let mut credentials = Credentials::from_headers(request.headers()).or(query);
+
+    // 接続を禁止したクライアントは公開パス（ヘルスチェック・ペアリング）も拒否する
+    let banned = match state.lock() {
+        Ok(state_guard) => state_guard.is_banned(credentials.client_id.as_deref(), ip),
+        Err(_) => false,
+    };
+    if banned {
+        return AppError::ClientBanned.into_response();
+    }
+
+    if is_public_path(request.uri().path()) {
+        return next.run(request).await;
+    }
+
+    // JSONボディにパスワードを含める従来形式（/input）の場合はボディも確認する
+    let is_json = request
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|h| h.to_str().ok())
+        .map(|content_type| content_type.starts_with("application/json"))
+        .unwrap_or(false);
+    let mut request = if is_json && credentials.token.is_none() && credentials.password.is_none() {
+        let (parts, body) = request.into_parts();
+        let bytes = match to_bytes(body, MAX_AUTH_BODY_BYTES).await {
+            Ok(bytes) => bytes,
+            Err(_) => return AppError::Unauthorized.into_response(),
+        };
+        credentials.password = serde_json::from_slice::<BodyPassword>(&bytes)
+            .ok()
+            .and_then(|body| body.password);
+        Request::from_parts(parts, Body::from(bytes))
+    } else {
+        request
+    };
+
+    match verify(&state, &credentials, ip) {
+        Ok(()) => {
+            request.extensions_mut().insert(credentials);
+            next.run(request).await
+        }
+        Err(e) => e.into_response(),
+    }
+}
+
+fn verify(state: &AppState, credentials: &Credentials, ip: Option<IpAddr>) -> Result<(), AppError> {
+    state.lock()?.verify_credentials(
+        credentials.client_id.as_deref(),
+        credentials.token.as_deref(),
+        credentials.password.as_deref(),
+        ip,
+    )
+}
@@ -0,0 +1,187 @@
+use lazy_static::lazy_static;
+use rdev::{Event, EventType, SimulateError};
+use std::sync::{Mutex, Once};
+use std::{thread, time};
+
+lazy_static! {
+    // OSの入力イベントから取得した実際のカーソル位置
+    static ref CURSOR_POSITION: Mutex<Option<(f64, f64)>> = Mutex::new(None);
+    // 監視したイベントを渡すコールバック（キー録画用）
+    static ref EVENT_HOOK: Mutex<Option<fn(Event)>> = Mutex::new(None);
+}
+
+static LISTENER: Once = Once::new();
+
+/// OSの入力イベントの監視を開始する関数（プロセス内で1回のみ起動）
+///
+/// rdevのリスナーは同時に複数起動できないため、カーソル位置の追跡とキー録画で共有します。
+/// 2回目以降の呼び出しではコールバックのみを差し替えます。
+///
+/// # Arguments
+///
+/// * `hook` - 監視した全てのイベントを受け取るコールバック
+pub fn start_event_listener(hook: fn(Event)) {
+    if let Ok(mut current) = EVENT_HOOK.lock() {
+        *current = Some(hook);
+    }
+
+    LISTENER.call_once(|| {
+        thread::spawn(|| {
+            if let Err(e) = rdev::listen(on_event) {
+                eprintln!("Failed to listen for input events: {:?}", e);
+            }
+        });
+    });
+}
+
+fn on_event(event: Event) {
+    if let EventType::MouseMove { x, y } = event.event_type {
+        if let Ok(mut position) = CURSOR_POSITION.lock() {
+            *position = Some((x, y));
+        }
+    }
+
+    let hook = EVENT_HOOK.lock().ok().and_then(|hook| *hook);
+    if let Some(hook) = hook {
+        hook(event);
+    }
+}
+
+/// 入力注入バックエンドのトレイト
+///
+/// キーボード・マウスイベントの送信とクリップボード操作を抽象化します。
+/// 全てのシミュレーションとカスタムアクション再生はこのトレイトを経由します。
+/// 使用するバックエンドは `ServerState::input_backend` で状態ごとに指定するため、
+/// テストごとに別の `RecordingBackend` を使用できます。
+pub trait InputBackend: Send + Sync + std::fmt::Debug {
+    /// 入力イベントを1件送信する
+    fn simulate(&self, event_type: &EventType) -> Result<(), SimulateError>;
+
+    /// イベント間の待機（テスト用バックエンドでは省略可能）
+    fn sleep(&self, duration: time::Duration) {
+        thread::sleep(duration);
+    }
+
+    /// メイン画面のサイズ（ピクセル）を取得する（取得できない場合はNone）
+    fn screen_size(&self) -> Option<(f64, f64)> {
+        None
+    }
+
+    /// 現在のカーソル位置（ピクセル）を取得する（取得できない場合はNone）
+    fn cursor_position(&self) -> Option<(f64, f64)> {
+        None
+    }
+
+    /// OSの入力イベントの監視を開始する（監視しないバックエンドでは何もしない）
+    fn start_listener(&self, _hook: fn(Event)) {}
+
+    /// クリップボードのテキストを取得する
+    fn get_clipboard_text(&self) -> Result<String, String>;
+
+    /// クリップボードにテキストを設定する
+    fn set_clipboard_text(&self, text: &str) -> Result<(), String>;
+}
+
+/// rdevとOSクリップボードを使用する標準バックエンド
+#[derive(Debug, Default)]
+pub struct RdevBackend;
+
+impl InputBackend for RdevBackend {
+    fn simulate(&self, event_type: &EventType) -> Result<(), SimulateError> {
+        rdev::simulate(event_type)
+    }
+
+    fn screen_size(&self) -> Option<(f64, f64)> {
+        rdev::display_size()
+            .ok()
+            .map(|(width, height)| (width as f64, height as f64))
+    }
+
+    fn cursor_position(&self) -> Option<(f64, f64)> {
+        CURSOR_POSITION.lock().ok().and_then(|position| *position)
+    }
+
+    fn start_listener(&self, hook: fn(Event)) {
+        start_event_listener(hook);
+    }
+
+    fn get_clipboard_text(&self) -> Result<String, String> {
+        arboard::Clipboard::new()
+            .map_err(|e| format!("Clipboard unavailable: {}", e))?
+            .get_text()
+            .map_err(|e| format!("Failed to read clipboard: {}", e))
+    }
+
+    fn set_clipboard_text(&self, text: &str) -> Result<(), String> {
+        arboard::Clipboard::new()
+            .map_err(|e| format!("Clipboard unavailable: {}", e))?
+            .set_text(text.to_string())
+            .map_err(|e| format!("Failed to set clipboard: {}", e))
+    }
+}
+
+/// 送信されたイベントをメモリ上に記録するバックエンド
+///
+/// 実際の入力は行わず、待機も省略します。ヘッドレス環境での結合テストで
+/// `/input` が生成するキーシーケンスを検証するために使用します。
+/// カーソル位置は最後に送信した `MouseMove` の位置です。
+#[derive(Debug, Default)]
+pub struct RecordingBackend {
+    events: Mutex<Vec<EventType>>,
+    clipboard: Mutex<String>,
+    cursor: Mutex<Option<(f64, f64)>>,
+}
+
+impl RecordingBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// これまでに記録されたイベントを取得する
+    pub fn events(&self) -> Vec<EventType> {
+        self.events.lock().map(|events| events.clone()).unwrap_or_default()
+    }
+
+    /// 記録されたイベントを取り出してクリアする
+    pub fn take_events(&self) -> Vec<EventType> {
+        self.events.lock().map(|mut events| std::mem::take(&mut *events)).unwrap_or_default()
+    }
+
+    /// ユーザーがマウスを動かした場合のカーソル位置を設定する
+    pub fn set_cursor_position(&self, x: f64, y: f64) {
+        if let Ok(mut cursor) = self.cursor.lock() {
+            *cursor = Some((x, y));
+        }
+    }
+}
+
+impl InputBackend for RecordingBackend {
+    fn simulate(&self, event_type: &EventType) -> Result<(), SimulateError> {
+        if let Ok(mut events) = self.events.lock() {
+            events.push(*event_type);
+        }
+        if let EventType::MouseMove { x, y } = event_type {
+            self.set_cursor_position(*x, *y);
+        }
+        Ok(())
+    }
+
+    fn sleep(&self, _duration: time::Duration) {}
+
+    fn cursor_position(&self) -> Option<(f64, f64)> {
+        self.cursor.lock().ok().and_then(|cursor| *cursor)
+    }
+
+    fn get_clipboard_text(&self) -> Result<String, String> {
+        self.clipboard
+            .lock()
+            .map(|clipboard| clipboard.clone())
+            .map_err(|e| format!("Failed to lock clipboard: {}", e))
+    }
+
+    fn set_clipboard_text(&self, text: &str) -> Result<(), String> {
+        let mut clipboard = self.clipboard.lock().map_err(|e| format!("Failed to lock clipboard: {}", e))?;
+        *clipboard = text.to_string();
+        Ok(())
+    }
+}
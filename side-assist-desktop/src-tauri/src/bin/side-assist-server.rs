@@ -0,0 +1,123 @@
+//! GUIを起動せずに入力サーバーだけを実行するヘッドレス版
+//!
+//! ログイン時の自動起動やキオスク端末での利用を想定しています。
+//! 起動するとペアリング用のQRコードをターミナルに表示し、Ctrl+Cで停止します。
+//! ワンタイムパスワードの期限が切れる（または失敗回数の上限で無効化される）と新しいパスワードを生成して
+//! QRコードを表示し直します。Enterキーでいつでも再生成できます。
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use clap::Parser;
+use side_assist_desktop_lib::server::{self, DEFAULT_PASSWORD_TTL_SECS};
+use side_assist_desktop_lib::{qr, AppState, ServerState};
+use tokio::io::{AsyncBufReadExt, BufReader};
+
+/// ワンタイムパスワードの期限を確認する間隔
+const OTP_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// コマンドライン引数
+#[derive(Parser)]
+#[command(name = "side-assist-server", version, about = "Run the Side Assist input server without the desktop UI")]
+struct Options {
+    /// Port to listen on (default: 8080)
+    #[arg(short, long)]
+    port: Option<u16>,
+
+    /// Local IP address to listen on and advertise (default: all interfaces)
+    #[arg(short, long, value_name = "ADDRESS")]
+    bind: Option<String>,
+
+    /// Use this 5-digit one-time password instead of a random one (renewed with the same value on expiry)
+    #[arg(long, value_name = "PASSWORD")]
+    otp: Option<String>,
+
+    /// Lifetime of the one-time password
+    #[arg(long, value_name = "SECONDS", default_value_t = DEFAULT_PASSWORD_TTL_SECS)]
+    otp_ttl: u64,
+
+    /// Directory for settings, custom actions, paired devices and certificate
+    #[arg(long, value_name = "PATH")]
+    data_dir: Option<PathBuf>,
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    match run(Options::parse()).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run(options: Options) -> Result<(), String> {
+    if let Some(data_dir) = &options.data_dir {
+        server::set_app_data_dir(data_dir.clone());
+    }
+
+    let state: AppState = Arc::new(Mutex::new(ServerState::default()));
+    server::load_persistent_state(&state).await;
+
+    if let Some(port) = options.port {
+        server::set_port(&state, port)?;
+    }
+    if options.bind.is_some() {
+        server::set_bind_address(&state, options.bind.as_deref())?;
+    }
+
+    let bind_addr = server::start_server(&state).await?;
+    println!("Side Assist server listening on {}", bind_addr);
+    issue_password(&state, &options)?;
+
+    // 標準入力が閉じている場合（自動起動など）は期限切れでの再生成のみ行う
+    let mut stdin = BufReader::new(tokio::io::stdin()).lines();
+    let mut stdin_open = true;
+    let mut otp_check = tokio::time::interval(OTP_CHECK_INTERVAL);
+
+    loop {
+        tokio::select! {
+            result = tokio::signal::ctrl_c() => {
+                result.map_err(|e| format!("Failed to wait for Ctrl+C: {}", e))?;
+                break;
+            }
+            line = stdin.next_line(), if stdin_open => match line {
+                Ok(Some(_)) => issue_password(&state, &options)?,
+                _ => stdin_open = false,
+            },
+            _ = otp_check.tick() => {
+                if server::current_password(&state)?.is_none() {
+                    println!("One-time password expired.");
+                    issue_password(&state, &options)?;
+                }
+            }
+        }
+    }
+
+    server::stop_server(&state).await?;
+    println!("Server stopped");
+
+    Ok(())
+}
+
+// ワンタイムパスワードを発行し、ペアリング用のQRコードと接続情報を表示する
+fn issue_password(state: &AppState, options: &Options) -> Result<(), String> {
+    let password = match &options.otp {
+        Some(password) => {
+            server::set_one_time_password(state, password, options.otp_ttl)?;
+            password.clone()
+        }
+        None => server::generate_one_time_password(state, options.otp_ttl)?,
+    };
+
+    let url = server::connection_url(state)?;
+    println!("{}", qr::render_terminal(&url)?);
+    println!("One-time password: {} (valid for {} seconds)", password, options.otp_ttl);
+    println!("Connection URL: {}", url);
+    println!("Press Enter to generate a new one-time password, or Ctrl+C to stop.");
+
+    Ok(())
+}
@@ -0,0 +1,55 @@
+use axum::response::Json as JsonResponse;
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::discovery::PROTOCOL_VERSION;
+use crate::keyboard::supported_key_names;
+use crate::macros::MACRO_STEP_TYPES;
+use crate::snippets::BUILTIN_SNIPPET_VARIABLES;
+use crate::server::API_PREFIX;
+use crate::{cached_accessibility_permission, ACTION_TYPES, GESTURE_ACTIONS};
+
+/// このサーバーが対応しているAPIバージョン（`/api/<version>` のプレフィックス）
+pub const API_VERSIONS: &[&str] = &["v1"];
+
+/// サーバーの機能情報
+///
+/// モバイルアプリは接続時にこの情報を取得し、対応していないアクションを
+/// 送信しないようにします（古いデスクトップと新しいモバイルアプリの組み合わせなど）。
+#[derive(Clone, Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Capabilities {
+    pub server_version: &'static str,
+    pub protocol_version: &'static str,
+    pub api_versions: &'static [&'static str],
+    pub api_prefix: &'static str,
+    pub platform: &'static str,
+    pub actions: &'static [&'static str],           // ActionTypeの `type` の値
+    pub gesture_actions: &'static [&'static str],   // gestureアクションの `action` の値
+    pub macro_steps: &'static [&'static str],       // カスタムアクションのステップの `type` の値
+    pub snippet_variables: &'static [&'static str], // スニペットの組み込み変数
+    pub keys: Vec<&'static str>,                    // カスタムアクションで使用できるキー名
+    pub accessibility_permission: Option<bool>,     // 確認できない場合は `None`
+}
+
+/// 現在のサーバーの機能情報を取得する関数
+pub async fn current_capabilities() -> Capabilities {
+    Capabilities {
+        server_version: env!("CARGO_PKG_VERSION"),
+        protocol_version: PROTOCOL_VERSION,
+        api_versions: API_VERSIONS,
+        api_prefix: API_PREFIX,
+        platform: std::env::consts::OS,
+        actions: ACTION_TYPES,
+        gesture_actions: GESTURE_ACTIONS,
+        macro_steps: MACRO_STEP_TYPES,
+        snippet_variables: BUILTIN_SNIPPET_VARIABLES,
+        keys: supported_key_names(),
+        accessibility_permission: cached_accessibility_permission().await.ok(),
+    }
+}
+
+/// `/capabilities` エンドポイント（認証不要、ペアリング前の互換性確認に使用）
+pub async fn get_capabilities() -> JsonResponse<Capabilities> {
+    JsonResponse(current_capabilities().await)
+}
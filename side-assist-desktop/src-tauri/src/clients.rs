@@ -0,0 +1,154 @@
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use axum::http::HeaderMap;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tokio_util::sync::CancellationToken;
+
+// `ClientInfo::session_id` に割り当てる値
+static NEXT_SESSION_ID: AtomicU64 = AtomicU64::new(0);
+
+/// 接続中のクライアントの情報
+///
+/// `/health` またはWebSocket接続で登録され、一定時間アクセスがないと削除されます。
+#[derive(Clone, Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ClientInfo {
+    #[serde(rename = "clientID")]
+    pub id: String,
+    pub device_name: Option<String>,
+    pub platform: Option<String>, // "ios"・"android" など
+    pub app_version: Option<String>,
+    pub ip: Option<String>,
+    pub connected_at: u64,
+    pub last_health_check: u64,
+    pub last_action: Option<String>, // 最後に実行したアクションの種類（`ActionType` の `type`）
+    pub last_action_at: Option<u64>,
+    #[serde(skip)]
+    pub disconnect: CancellationToken, // デスクトップから切断する時にWebSocket接続を閉じる
+    #[serde(skip)]
+    pub session_id: u64, // 登録ごとに異なる値（同じクライアントIDの以前の登録と区別する）
+}
+
+impl ClientInfo {
+    pub fn new(id: &str, timestamp: u64) -> Self {
+        Self {
+            id: id.to_string(),
+            device_name: None,
+            platform: None,
+            app_version: None,
+            ip: None,
+            connected_at: timestamp,
+            last_health_check: timestamp,
+            last_action: None,
+            last_action_at: None,
+            disconnect: CancellationToken::new(),
+            session_id: NEXT_SESSION_ID.fetch_add(1, Ordering::Relaxed),
+        }
+    }
+
+    /// リクエストから取得した情報で更新する（取得できなかった項目は以前の値を残す）
+    ///
+    /// # Returns
+    ///
+    /// * `true` - いずれかの項目が変わった場合
+    pub fn update_metadata(&mut self, metadata: &ClientMetadata) -> bool {
+        let before = (
+            self.device_name.clone(),
+            self.platform.clone(),
+            self.app_version.clone(),
+            self.ip.clone(),
+        );
+
+        if metadata.device_name.is_some() {
+            self.device_name = metadata.device_name.clone();
+        }
+        if metadata.platform.is_some() {
+            self.platform = metadata.platform.clone();
+        }
+        if metadata.app_version.is_some() {
+            self.app_version = metadata.app_version.clone();
+        }
+        if let Some(ip) = metadata.ip {
+            self.ip = Some(ip.to_string());
+        }
+
+        before
+            != (
+                self.device_name.clone(),
+                self.platform.clone(),
+                self.app_version.clone(),
+                self.ip.clone(),
+            )
+    }
+}
+
+/// リクエストに含まれるクライアントの情報
+///
+/// ヘッダー `x-device-name`・`x-platform`・`x-app-version`、または同名のクエリ
+/// （`device_name`・`platform`・`app_version`、ヘッダーを付与できないWebSocket用）から取得します。
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct ClientMetadata {
+    pub device_name: Option<String>,
+    pub platform: Option<String>,
+    pub app_version: Option<String>,
+    #[serde(skip)]
+    pub ip: Option<IpAddr>,
+}
+
+impl ClientMetadata {
+    pub fn from_headers(headers: &HeaderMap, ip: Option<IpAddr>) -> Self {
+        let header = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|h| h.to_str().ok())
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+                .map(|value| value.to_string())
+        };
+
+        Self {
+            device_name: header("x-device-name"),
+            platform: header("x-platform"),
+            app_version: header("x-app-version"),
+            ip,
+        }
+    }
+
+    // 足りない項目を別の取得元で補う
+    pub fn or(self, other: ClientMetadata) -> Self {
+        Self {
+            device_name: self.device_name.or(other.device_name),
+            platform: self.platform.or(other.platform),
+            app_version: self.app_version.or(other.app_version),
+            ip: self.ip.or(other.ip),
+        }
+    }
+}
+
+/// デスクトップで接続を拒否したクライアント
+///
+/// クライアントIDと、禁止した時点のIPアドレスの両方からの接続を拒否します
+/// （アプリを再インストールしてクライアントIDが変わった場合にも対応するため）。
+/// ペアリング済みデバイスと同じファイルに保存し、アプリを再起動しても解除されません。
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BannedClient {
+    #[serde(rename = "clientID")]
+    pub client_id: String,
+    pub device_name: Option<String>,
+    pub ip: Option<String>,
+    pub banned_at: u64,
+}
+
+impl BannedClient {
+    pub fn matches(&self, client_id: Option<&str>, ip: Option<IpAddr>) -> bool {
+        let id_matches = client_id == Some(self.client_id.as_str());
+        let ip_matches = match (&self.ip, ip) {
+            (Some(banned_ip), Some(ip)) => *banned_ip == ip.to_string(),
+            _ => false,
+        };
+        id_matches || ip_matches
+    }
+}
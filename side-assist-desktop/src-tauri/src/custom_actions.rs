@@ -0,0 +1,306 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use axum::{
+    extract::{Json, Path, State},
+    response::Json as JsonResponse,
+};
+use lazy_static::lazy_static;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::error::AppError;
+use crate::key_sequence::KeySequenceEdit;
+use crate::macros::MacroStep;
+use crate::storage::save_custom_actions;
+use crate::{ApiResponse, AppState, CustomAction, RecordedKey, ServerState};
+
+lazy_static! {
+    // カスタムアクションの変更と保存を1件ずつ行うためのロック
+    static ref PERSIST_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::new(());
+}
+
+/// カスタムアクションの更新リクエスト（指定した項目のみ変更）
+#[derive(Deserialize, JsonSchema)]
+pub(crate) struct UpdateCustomActionRequest {
+    name: Option<String>,
+    icon: Option<String>, // 空文字列の場合はアイコンを削除
+}
+
+/// カスタムアクションの並び替えリクエスト
+#[derive(Deserialize, JsonSchema)]
+pub(crate) struct ReorderCustomActionsRequest {
+    ids: Vec<String>, // 先頭から順に並べるアクションのID（省略したアクションは後ろに並ぶ）
+}
+
+/// キーシーケンスの置き換えリクエスト
+#[derive(Deserialize, JsonSchema)]
+pub(crate) struct ReplaceKeySequenceRequest {
+    key_sequence: Vec<RecordedKey>,
+}
+
+/// キーシーケンスの編集リクエスト（`edits` を順に適用）
+#[derive(Deserialize, JsonSchema)]
+pub(crate) struct EditKeySequenceRequest {
+    edits: Vec<KeySequenceEdit>,
+}
+
+/// ステップで構成するカスタムアクションの作成リクエスト
+#[derive(Deserialize, JsonSchema)]
+pub(crate) struct CreateCustomActionRequest {
+    name: String,
+    icon: Option<String>,
+    steps: Vec<MacroStep>,
+}
+
+/// ステップの置き換えリクエスト
+#[derive(Deserialize, JsonSchema)]
+pub(crate) struct SetMacroStepsRequest {
+    steps: Vec<MacroStep>,
+}
+
+/// カスタムアクションを変更し、ファイルに保存してから反映する関数
+///
+/// `modify` は現在の一覧を変更しますが、変更後の一覧はいったん元に戻し、
+/// 保存に成功した場合のみ置き換えて通知します（保存に失敗した場合は何も変更しません）。
+/// 変更と保存は1件ずつ行うため、同時に変更しても古い一覧で上書きされることはありません。
+///
+/// # Arguments
+///
+/// * `state` - アプリケーション状態
+/// * `modify` - カスタムアクションを変更する処理（`ServerState` のカスタムアクション操作）
+///
+/// # Returns
+///
+/// * `Ok(T)` - `modify` の戻り値
+/// * `Err(AppError)` - 変更または保存に失敗した場合
+pub(crate) async fn modify_and_persist<T>(
+    state: &AppState,
+    modify: impl FnOnce(&mut ServerState) -> Result<T, AppError>,
+) -> Result<T, AppError> {
+    let _persist_guard = PERSIST_LOCK.lock().await;
+    
+    let (result, actions_to_save) = {
+        let mut state_guard = state.lock()?;
+        let previous = state_guard.custom_actions.clone();
+        let result = modify(&mut state_guard);
+        let updated = std::mem::replace(&mut state_guard.custom_actions, previous);
+        (result?, updated)
+    };
+    
+    save_custom_actions(&actions_to_save).await.map_err(AppError::Storage)?;
+    
+    let mut state_guard = state.lock()?;
+    state_guard.custom_actions = actions_to_save;
+    state_guard.publish_custom_actions();
+    Ok(result)
+}
+
+fn now_secs() -> Result<u64, AppError> {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .map_err(|e| AppError::Internal(format!("Failed to get system time: {}", e)))
+}
+
+#[tauri::command]
+pub async fn update_custom_action_icon(
+    state: tauri::State<'_, AppState>,
+    action_id: String,
+    icon: Option<String>,
+) -> Result<CustomAction, AppError> {
+    // `None` の場合はアイコンを削除する
+    let icon = icon.unwrap_or_default();
+    let action = modify_and_persist(&state, |state| {
+        state.update_custom_action(&action_id, None, Some(&icon))
+    })
+    .await?;
+
+    Ok(action)
+}
+
+#[tauri::command]
+pub async fn delete_custom_action(
+    state: tauri::State<'_, AppState>,
+    action_id: String,
+) -> Result<String, AppError> {
+    let action = modify_and_persist(&state, |state| state.delete_custom_action(&action_id)).await?;
+
+    Ok(format!("Custom action deleted: {}", action.name))
+}
+
+#[tauri::command]
+pub async fn duplicate_custom_action(
+    state: tauri::State<'_, AppState>,
+    action_id: String,
+) -> Result<CustomAction, AppError> {
+    let now = now_secs()?;
+    let action = modify_and_persist(&state, |state| state.duplicate_custom_action(&action_id, now)).await?;
+
+    Ok(action)
+}
+
+#[tauri::command]
+pub async fn reorder_custom_actions(
+    state: tauri::State<'_, AppState>,
+    action_ids: Vec<String>,
+) -> Result<Vec<CustomAction>, AppError> {
+    let actions = modify_and_persist(&state, |state| state.reorder_custom_actions(&action_ids)).await?;
+
+    Ok(actions)
+}
+
+#[tauri::command]
+pub async fn replace_key_sequence(
+    state: tauri::State<'_, AppState>,
+    action_id: String,
+    key_sequence: Vec<RecordedKey>,
+) -> Result<CustomAction, AppError> {
+    let action = modify_and_persist(&state, |state| {
+        state.replace_key_sequence(&action_id, key_sequence)
+    })
+    .await?;
+
+    Ok(action)
+}
+
+#[tauri::command]
+pub async fn edit_key_sequence(
+    state: tauri::State<'_, AppState>,
+    action_id: String,
+    edits: Vec<KeySequenceEdit>,
+) -> Result<CustomAction, AppError> {
+    let action = modify_and_persist(&state, |state| {
+        state.edit_key_sequence(&action_id, &edits)
+    })
+    .await?;
+
+    Ok(action)
+}
+
+#[tauri::command]
+pub async fn create_custom_action(
+    state: tauri::State<'_, AppState>,
+    name: String,
+    icon: Option<String>,
+    steps: Vec<MacroStep>,
+) -> Result<CustomAction, AppError> {
+    let now = now_secs()?;
+    let action = modify_and_persist(&state, |state| {
+        state.create_custom_action(&name, icon, steps, now)
+    })
+    .await?;
+
+    Ok(action)
+}
+
+#[tauri::command]
+pub async fn set_macro_steps(
+    state: tauri::State<'_, AppState>,
+    action_id: String,
+    steps: Vec<MacroStep>,
+) -> Result<CustomAction, AppError> {
+    let action = modify_and_persist(&state, |state| state.set_macro_steps(&action_id, steps)).await?;
+
+    Ok(action)
+}
+
+/// `POST /custom_actions` エンドポイント（ステップで構成するアクションの作成）
+pub async fn handle_create_custom_action(
+    State(state): State<AppState>,
+    Json(request): Json<CreateCustomActionRequest>,
+) -> Result<JsonResponse<CustomAction>, AppError> {
+    let now = now_secs()?;
+    let action = modify_and_persist(&state, |state| {
+        state.create_custom_action(&request.name, request.icon, request.steps, now)
+    })
+    .await?;
+
+    Ok(JsonResponse(action))
+}
+
+/// `PATCH /custom_actions/:id` エンドポイント（名前・アイコンの変更）
+pub async fn handle_update_custom_action(
+    State(state): State<AppState>,
+    Path(action_id): Path<String>,
+    Json(request): Json<UpdateCustomActionRequest>,
+) -> Result<JsonResponse<CustomAction>, AppError> {
+    let action = modify_and_persist(&state, |state| {
+        state.update_custom_action(&action_id, request.name.as_deref(), request.icon.as_deref())
+    })
+    .await?;
+
+    Ok(JsonResponse(action))
+}
+
+/// `DELETE /custom_actions/:id` エンドポイント
+pub async fn handle_delete_custom_action(
+    State(state): State<AppState>,
+    Path(action_id): Path<String>,
+) -> Result<JsonResponse<ApiResponse>, AppError> {
+    let action = modify_and_persist(&state, |state| state.delete_custom_action(&action_id)).await?;
+
+    Ok(JsonResponse(ApiResponse {
+        success: true,
+        message: format!("Custom action deleted: {}", action.name),
+    }))
+}
+
+/// `POST /custom_actions/:id/duplicate` エンドポイント
+pub async fn handle_duplicate_custom_action(
+    State(state): State<AppState>,
+    Path(action_id): Path<String>,
+) -> Result<JsonResponse<CustomAction>, AppError> {
+    let now = now_secs()?;
+    let action = modify_and_persist(&state, |state| state.duplicate_custom_action(&action_id, now)).await?;
+
+    Ok(JsonResponse(action))
+}
+
+/// `PUT /custom_actions/order` エンドポイント
+pub async fn handle_reorder_custom_actions(
+    State(state): State<AppState>,
+    Json(request): Json<ReorderCustomActionsRequest>,
+) -> Result<JsonResponse<Vec<CustomAction>>, AppError> {
+    let actions = modify_and_persist(&state, |state| state.reorder_custom_actions(&request.ids)).await?;
+
+    Ok(JsonResponse(actions))
+}
+
+/// `PUT /custom_actions/:id/key_sequence` エンドポイント（キーシーケンス全体の置き換え）
+pub async fn handle_replace_key_sequence(
+    State(state): State<AppState>,
+    Path(action_id): Path<String>,
+    Json(request): Json<ReplaceKeySequenceRequest>,
+) -> Result<JsonResponse<CustomAction>, AppError> {
+    let action = modify_and_persist(&state, |state| {
+        state.replace_key_sequence(&action_id, request.key_sequence)
+    })
+    .await?;
+
+    Ok(JsonResponse(action))
+}
+
+/// `PATCH /custom_actions/:id/key_sequence` エンドポイント（ステップの挿入・削除・移動・変更）
+pub async fn handle_edit_key_sequence(
+    State(state): State<AppState>,
+    Path(action_id): Path<String>,
+    Json(request): Json<EditKeySequenceRequest>,
+) -> Result<JsonResponse<CustomAction>, AppError> {
+    let action = modify_and_persist(&state, |state| {
+        state.edit_key_sequence(&action_id, &request.edits)
+    })
+    .await?;
+
+    Ok(JsonResponse(action))
+}
+
+/// `PUT /custom_actions/:id/steps` エンドポイント（ステップの置き換え）
+pub async fn handle_set_macro_steps(
+    State(state): State<AppState>,
+    Path(action_id): Path<String>,
+    Json(request): Json<SetMacroStepsRequest>,
+) -> Result<JsonResponse<CustomAction>, AppError> {
+    let action = modify_and_persist(&state, |state| state.set_macro_steps(&action_id, request.steps)).await?;
+
+    Ok(JsonResponse(action))
+}
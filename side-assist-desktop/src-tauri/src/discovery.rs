@@ -0,0 +1,98 @@
+use lazy_static::lazy_static;
+use mdns_sd::{ServiceDaemon, ServiceInfo};
+use std::net::SocketAddr;
+use std::sync::Mutex;
+
+/// DNS-SDのサービスタイプ
+pub const SERVICE_TYPE: &str = "_sideassist._tcp.local.";
+
+/// TXTレコードで公開するプロトコルバージョン（モバイルアプリとの互換性確認用）
+pub const PROTOCOL_VERSION: &str = "1";
+
+/// 登録中のサービス（mDNSデーモンと登録名）
+struct Advertisement {
+    daemon: ServiceDaemon,
+    fullname: String,
+}
+
+// 現在公開中のサービス（サーバー停止時に取り消す）
+lazy_static! {
+    static ref ADVERTISEMENT: Mutex<Option<Advertisement>> = Mutex::new(None);
+}
+
+/// このデスクトップの表示名を取得する関数（ホスト名、取得できない場合は固定名）
+pub fn get_device_name() -> String {
+    hostname::get()
+        .ok()
+        .and_then(|name| name.into_string().ok())
+        .map(|name| name.trim_end_matches(".local").to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "side-assist-desktop".to_string())
+}
+
+/// `_sideassist._tcp` サービスをmDNSで公開する関数
+///
+/// 既に公開中の場合は取り消してから公開し直します。
+/// 全インターフェースで待ち受けている場合、IPアドレスはmDNSデーモンが
+/// ネットワークインターフェースから自動で設定します。
+///
+/// # Arguments
+///
+/// * `bind_addr` - サーバーの待ち受けアドレス
+/// * `fingerprint` - TLS証明書のフィンガープリント（QRコードと同じ値）
+///
+/// # Returns
+///
+/// * `Ok(())` - 公開に成功した場合
+/// * `Err(String)` - mDNSデーモンの起動またはサービス登録に失敗した場合のエラーメッセージ
+pub fn start_advertising(bind_addr: SocketAddr, fingerprint: &str) -> Result<(), String> {
+    stop_advertising();
+
+    let device_name = get_device_name();
+    let host_name = format!("{}.local.", device_name.replace(' ', "-"));
+    let port = bind_addr.port();
+    let port_string = port.to_string();
+    let properties = [
+        ("name", device_name.as_str()),
+        ("port", port_string.as_str()),
+        ("version", PROTOCOL_VERSION),
+        ("fingerprint", fingerprint),
+    ];
+
+    let service_info = if bind_addr.ip().is_unspecified() {
+        ServiceInfo::new(SERVICE_TYPE, &device_name, &host_name, "", port, &properties[..])
+            .map(|info| info.enable_addr_auto())
+    } else {
+        ServiceInfo::new(SERVICE_TYPE, &device_name, &host_name, bind_addr.ip(), port, &properties[..])
+    }
+    .map_err(|e| format!("Failed to create service info: {}", e))?;
+    let fullname = service_info.get_fullname().to_string();
+
+    let daemon = ServiceDaemon::new().map_err(|e| format!("Failed to start mDNS daemon: {}", e))?;
+    if let Err(e) = daemon.register(service_info) {
+        let _ = daemon.shutdown();
+        return Err(format!("Failed to register mDNS service: {}", e));
+    }
+
+    if let Ok(mut advertisement) = ADVERTISEMENT.lock() {
+        *advertisement = Some(Advertisement { daemon, fullname });
+    }
+
+    Ok(())
+}
+
+/// 公開中のサービスを取り消す関数（公開していない場合は何もしない）
+pub fn stop_advertising() {
+    let advertisement = match ADVERTISEMENT.lock() {
+        Ok(mut advertisement) => advertisement.take(),
+        Err(_) => None,
+    };
+
+    if let Some(Advertisement { daemon, fullname }) = advertisement {
+        // 取り消し（goodbyeパケットの送信）後にデーモンを停止する
+        if let Ok(receiver) = daemon.unregister(&fullname) {
+            let _ = receiver.recv_timeout(std::time::Duration::from_secs(1));
+        }
+        let _ = daemon.shutdown();
+    }
+}
@@ -0,0 +1,285 @@
+use std::sync::PoisonError;
+use std::time::Duration;
+
+use axum::{
+    http::{header, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use schemars::{gen::SchemaGenerator, schema::Schema, JsonSchema};
+use serde::{ser::SerializeStruct, Serialize, Serializer};
+
+use crate::simulation::UndeliveredChar;
+
+/// HTTP API・WebSocket・Tauriコマンド共通のエラー
+///
+/// クライアントは `code()` の値（レスポンスの `code` フィールド）でエラーの種類を判別します。
+/// コードは互換性のために変更しないでください。メッセージは表示用で、変更される場合があります。
+#[derive(Clone, Debug)]
+pub enum AppError {
+    /// 認証情報がない、またはデバイストークンが無効
+    Unauthorized,
+    /// ワンタイムパスワードが一致しない
+    InvalidPassword,
+    /// ワンタイムパスワードの有効期限切れ
+    PasswordExpired,
+    /// ワンタイムパスワードが未発行（または失敗が続いたため無効化済み）
+    PasswordNotSet,
+    /// 認証失敗が続いたためロックアウト中
+    LockedOut { retry_after: Duration },
+    /// IPアドレスごとのリクエスト数の上限を超えた
+    RateLimited,
+    /// デスクトップで接続を禁止されたクライアント
+    ClientBanned,
+    /// デスクトップでのみ変更できる設定をHTTPで変更しようとした（設定名を保持）
+    DesktopOnlySetting(String),
+    /// `x-client-id` ヘッダーがない
+    MissingClientId,
+    /// リクエストの内容が不正
+    InvalidRequest(String),
+    /// 指定されたカスタムアクションが存在しない
+    ActionNotFound(String),
+    /// `invoke_action` でカスタムアクションが互いに呼び出し合う（循環するアクションのIDを呼び出し順に保持）
+    ActionCycle(Vec<String>),
+    /// `invoke_action` の呼び出しが `MAX_INVOKE_DEPTH` より深い
+    ActionNestingTooDeep { max_depth: usize },
+    /// 削除しようとしたカスタムアクションが他のアクションから呼び出されている（`used_by` は呼び出し元の名前）
+    ActionInUse { action_id: String, used_by: Vec<String> },
+    /// 指定されたペアリング済みデバイスが存在しない
+    DeviceNotFound(String),
+    /// 指定されたクライアントが接続中・禁止中の一覧に存在しない
+    ClientNotFound(String),
+    /// キーボード・マウス・クリップボードの操作に失敗した
+    InjectionFailed {
+        message: String,
+        undelivered: Option<Vec<UndeliveredChar>>, // テキスト入力で入力できなかった文字
+    },
+    /// 録画セッションがない
+    NoRecording,
+    /// 録画が完了していない
+    RecordingNotCompleted,
+    /// サーバーの起動・停止処理が実行中
+    ServerBusy,
+    /// サーバーが実行中のため操作できない
+    ServerRunning,
+    /// 待ち受けポートが使用できない
+    PortUnavailable { port: u16, reason: String },
+    /// ファイルの読み書きに失敗した
+    Storage(String),
+    /// その他の内部エラー
+    Internal(String),
+}
+
+impl AppError {
+    /// 機械判読用のエラーコード
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::Unauthorized => "unauthorized",
+            AppError::InvalidPassword => "invalid_password",
+            AppError::PasswordExpired => "password_expired",
+            AppError::PasswordNotSet => "password_not_set",
+            AppError::LockedOut { .. } => "locked_out",
+            AppError::RateLimited => "rate_limited",
+            AppError::ClientBanned => "client_banned",
+            AppError::DesktopOnlySetting(_) => "desktop_only_setting",
+            AppError::MissingClientId => "missing_client_id",
+            AppError::InvalidRequest(_) => "invalid_request",
+            AppError::ActionNotFound(_) => "action_not_found",
+            AppError::ActionCycle(_) => "action_cycle",
+            AppError::ActionNestingTooDeep { .. } => "action_nesting_too_deep",
+            AppError::ActionInUse { .. } => "action_in_use",
+            AppError::DeviceNotFound(_) => "device_not_found",
+            AppError::ClientNotFound(_) => "client_not_found",
+            AppError::InjectionFailed { .. } => "injection_failed",
+            AppError::NoRecording => "no_recording",
+            AppError::RecordingNotCompleted => "recording_not_completed",
+            AppError::ServerBusy => "server_busy",
+            AppError::ServerRunning => "server_running",
+            AppError::PortUnavailable { .. } => "port_unavailable",
+            AppError::Storage(_) => "storage_error",
+            AppError::Internal(_) => "internal_error",
+        }
+    }
+
+    /// HTTPレスポンスのステータスコード
+    pub fn status(&self) -> StatusCode {
+        match self {
+            AppError::Unauthorized
+            | AppError::InvalidPassword
+            | AppError::PasswordExpired
+            | AppError::PasswordNotSet => StatusCode::UNAUTHORIZED,
+            AppError::LockedOut { .. } | AppError::RateLimited => StatusCode::TOO_MANY_REQUESTS,
+            AppError::ClientBanned | AppError::DesktopOnlySetting(_) => StatusCode::FORBIDDEN,
+            AppError::MissingClientId
+            | AppError::InvalidRequest(_)
+            | AppError::ActionCycle(_)
+            | AppError::ActionNestingTooDeep { .. } => StatusCode::BAD_REQUEST,
+            AppError::ActionNotFound(_) | AppError::DeviceNotFound(_) | AppError::ClientNotFound(_) => {
+                StatusCode::NOT_FOUND
+            }
+            AppError::ActionInUse { .. }
+            | AppError::NoRecording
+            | AppError::RecordingNotCompleted
+            | AppError::ServerBusy
+            | AppError::ServerRunning
+            | AppError::PortUnavailable { .. } => StatusCode::CONFLICT,
+            AppError::InjectionFailed { .. } | AppError::Storage(_) | AppError::Internal(_) => {
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        }
+    }
+
+    /// 表示用のメッセージ
+    pub fn message(&self) -> String {
+        match self {
+            AppError::Unauthorized => "Unauthorized".to_string(),
+            AppError::InvalidPassword => "Invalid password".to_string(),
+            AppError::PasswordExpired => {
+                "One-time password has expired. Generate a new one on the desktop".to_string()
+            }
+            AppError::PasswordNotSet => {
+                "No one-time password has been generated. Generate one on the desktop".to_string()
+            }
+            AppError::LockedOut { retry_after } => format!(
+                "Too many failed attempts. Retry after {} seconds",
+                retry_after_secs(*retry_after)
+            ),
+            AppError::RateLimited => "Too many requests".to_string(),
+            AppError::ClientBanned => "This device has been blocked on the desktop".to_string(),
+            AppError::DesktopOnlySetting(name) => format!("Setting '{}' can only be changed on the desktop", name),
+            AppError::MissingClientId => "Missing x-client-id header".to_string(),
+            AppError::InvalidRequest(message) => message.clone(),
+            AppError::ActionNotFound(action_id) => format!("Custom action '{}' not found", action_id),
+            AppError::ActionCycle(cycle) => {
+                format!("Custom actions would invoke each other in a loop: {}", cycle.join(" → "))
+            }
+            AppError::ActionNestingTooDeep { max_depth } => {
+                format!("Custom actions are nested more than {} levels deep", max_depth)
+            }
+            AppError::ActionInUse { action_id, used_by } => format!(
+                "Custom action '{}' is invoked by other actions: {}",
+                action_id,
+                used_by.join(", ")
+            ),
+            AppError::DeviceNotFound(client_id) => format!("Paired device with client ID '{}' not found", client_id),
+            AppError::ClientNotFound(client_id) => format!("Client '{}' not found", client_id),
+            AppError::InjectionFailed { message, .. } => message.clone(),
+            AppError::NoRecording => "No recording session active".to_string(),
+            AppError::RecordingNotCompleted => "No completed recording to acknowledge".to_string(),
+            AppError::ServerBusy => "サーバー操作が実行中です。しばらくお待ちください。".to_string(),
+            AppError::ServerRunning => "サーバーが実行中です。まずサーバーを停止してください。".to_string(),
+            AppError::PortUnavailable { port, reason } => format!("ポート{}は使用できません: {}", port, reason),
+            AppError::Storage(message) | AppError::Internal(message) => message.clone(),
+        }
+    }
+
+    /// 操作失敗のエラーを生成する（テキスト入力以外）
+    pub fn injection(message: impl Into<String>) -> Self {
+        AppError::InjectionFailed {
+            message: message.into(),
+            undelivered: None,
+        }
+    }
+}
+
+// Retry-Afterは1秒未満を切り上げる
+fn retry_after_secs(retry_after: Duration) -> u64 {
+    retry_after.as_secs().max(1)
+}
+
+impl std::fmt::Display for AppError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message())
+    }
+}
+
+impl std::error::Error for AppError {}
+
+// 既存の `Result<_, String>` を返す関数のエラーは内部エラーとして扱う
+impl From<String> for AppError {
+    fn from(message: String) -> Self {
+        AppError::Internal(message)
+    }
+}
+
+// `Result<_, String>` を返す関数（ヘッドレスサーバーなど）からも `?` で使えるようにする
+impl From<AppError> for String {
+    fn from(e: AppError) -> Self {
+        e.message()
+    }
+}
+
+impl<T> From<PoisonError<T>> for AppError {
+    fn from(e: PoisonError<T>) -> Self {
+        AppError::Internal(format!("Failed to lock state: {}", e))
+    }
+}
+
+/// JSONでの表現（HTTPレスポンスのボディ、Tauriコマンドのエラー）
+///
+/// `{ "success": false, "code": "...", "message": "..." }` に、エラーに応じて
+/// `retryAfterSecs`・`undelivered`・`cycle`・`usedBy` を加えます。
+impl Serialize for AppError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut body = serializer.serialize_struct("AppError", 5)?;
+        body.serialize_field("success", &false)?;
+        body.serialize_field("code", self.code())?;
+        body.serialize_field("message", &self.message())?;
+        match self {
+            AppError::LockedOut { retry_after } => {
+                body.serialize_field("retryAfterSecs", &retry_after_secs(*retry_after))?;
+            }
+            AppError::InjectionFailed {
+                undelivered: Some(undelivered),
+                ..
+            } => {
+                body.serialize_field("undelivered", undelivered)?;
+            }
+            AppError::ActionCycle(cycle) => {
+                body.serialize_field("cycle", cycle)?;
+            }
+            AppError::ActionInUse { used_by, .. } => {
+                body.serialize_field("usedBy", used_by)?;
+            }
+            _ => {}
+        }
+        body.end()
+    }
+}
+
+// OpenAPIのスキーマ用（`Serialize` の出力と同じ形）
+#[derive(JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[allow(dead_code)]
+struct ErrorBody {
+    success: bool,
+    code: String,
+    message: String,
+    retry_after_secs: Option<u64>,
+    undelivered: Option<Vec<UndeliveredChar>>,
+    cycle: Option<Vec<String>>,
+    used_by: Option<Vec<String>>,
+}
+
+impl JsonSchema for AppError {
+    fn schema_name() -> String {
+        "AppError".to_string()
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        ErrorBody::json_schema(gen)
+    }
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        let mut response = (self.status(), Json(&self)).into_response();
+        if let AppError::LockedOut { retry_after } = self {
+            response.headers_mut().insert(
+                header::RETRY_AFTER,
+                HeaderValue::from(retry_after_secs(retry_after)),
+            );
+        }
+        response
+    }
+}
@@ -0,0 +1,102 @@
+use schemars::JsonSchema;
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+use crate::clients::ClientInfo;
+use crate::settings::AppSettings;
+use crate::{CustomAction, RecordedKey, RecordingStatusResponse};
+
+/// イベントチャネルのバッファ数（受信が遅れたクライアントは古いイベントを取りこぼす）
+///
+/// 録画中はキーごと（`RecordedKey`）にイベントが発生するため、素早い入力でも取りこぼさない程度に大きくします。
+const EVENT_CHANNEL_CAPACITY: usize = 1024;
+
+/// サーバーからクライアントへプッシュするイベント
+///
+/// WebSocketでは `type` フィールドでイベントの種類を判別します。
+#[derive(Clone, Debug, Serialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerEvent {
+    /// 録画状態の変化（準備・録画開始・完了・キャンセル）
+    RecordingStatus(RecordingStatusResponse),
+    /// 録画中に記録されたキー（1キーごとに送信）
+    RecordedKey {
+        #[serde(rename = "actionId")]
+        action_id: String,
+        index: usize, // 録画中のキーシーケンス内の位置（0始まり）
+        key: RecordedKey,
+    },
+    /// 録画中に押されたが、再生できないため記録しなかったキー
+    RecordedKeyWarning {
+        #[serde(rename = "actionId")]
+        action_id: String,
+        key: String, // rdevでのキー名（表示用、キー名としては使用できない）
+        message: String,
+    },
+    /// カスタムアクション一覧の変化
+    CustomActionsChanged { actions: Vec<CustomAction> },
+    /// 設定の変化
+    SettingsChanged { settings: AppSettings },
+    /// クライアントの接続
+    ClientConnected {
+        #[serde(rename = "clientID")]
+        client_id: String,
+    },
+    /// クライアントの切断
+    ClientDisconnected {
+        #[serde(rename = "clientID")]
+        client_id: String,
+    },
+    /// ハートビートが途絶えたクライアントの削除
+    ClientTimedOut {
+        #[serde(rename = "clientID")]
+        client_id: String,
+    },
+    /// 接続中のクライアント一覧の変化（デスクトップにのみ通知する）
+    ClientsChanged { clients: Vec<ClientInfo> },
+    /// 総当たり攻撃の疑い（デスクトップにのみ通知し、WebSocketクライアントには送信しない）
+    SecurityAlert(SecurityAlert),
+    /// 受信が遅れてイベントを取りこぼした（クライアントは一覧や録画状態を取得し直す）
+    ///
+    /// チャネルには送信せず、取りこぼした受信側にのみ送信します。
+    Resync {
+        skipped: u64, // 取りこぼしたイベント数
+    },
+}
+
+impl ServerEvent {
+    /// デスクトップUIにのみ通知するイベントかどうか
+    pub fn is_desktop_only(&self) -> bool {
+        matches!(self, ServerEvent::SecurityAlert(_) | ServerEvent::ClientsChanged { .. })
+    }
+}
+
+/// セキュリティ警告の種類
+#[derive(Clone, Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SecurityAlertKind {
+    /// 認証失敗が続いたためクライアントID・IPアドレスをロックアウトした
+    Lockout,
+    /// 失敗回数が上限に達したためワンタイムパスワードを無効化した
+    OtpInvalidated,
+    /// リクエスト数が上限を超えた
+    RateLimited,
+}
+
+/// セキュリティ警告の内容
+#[derive(Clone, Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SecurityAlert {
+    pub kind: SecurityAlertKind,
+    #[serde(rename = "clientID")]
+    pub client_id: Option<String>,
+    pub ip: Option<String>,
+    pub failed_attempts: u32,
+    pub retry_after_secs: Option<u64>,
+}
+
+/// サーバーイベントの送信用チャネルを作成する
+pub fn event_channel() -> broadcast::Sender<ServerEvent> {
+    let (sender, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
+    sender
+}
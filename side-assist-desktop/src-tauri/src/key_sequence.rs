@@ -0,0 +1,149 @@
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::error::AppError;
+use crate::keyboard::string_to_key;
+use crate::{KeyModifiers, RecordedKey};
+
+/// 1つのカスタムアクションに保存できるキーイベントの上限
+pub const MAX_KEY_SEQUENCE_LEN: usize = 1000;
+
+/// キーシーケンスの編集操作
+///
+/// 複数の操作はまとめて先頭から順に適用し、最後に全体を検証します（途中の状態は検証しません）。
+/// そのため、ステップの移動とタイムスタンプの調整を同じリクエストで行えます。
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum KeySequenceEdit {
+    /// `index` の位置にステップを挿入する（`index` がステップ数と同じ場合は末尾に追加）
+    Insert { index: usize, step: RecordedKey },
+    /// `index` のステップを削除する
+    Remove { index: usize },
+    /// `from` のステップを取り出して `to` の位置に移動する
+    Move { from: usize, to: usize },
+    /// `index` のステップのキー名・イベント種類・修飾キーを変更する（省略した項目は変更しない）
+    SetKey {
+        index: usize,
+        key: Option<String>,
+        event_type: Option<String>,
+        modifiers: Option<KeyModifiers>,
+    },
+    /// `index` のステップのタイムスタンプ（ミリ秒）を変更する
+    SetTimestamp { index: usize, timestamp: u64 },
+}
+
+fn step_out_of_range(index: usize, len: usize) -> AppError {
+    AppError::InvalidRequest(format!("Step {} is out of range (the sequence has {} steps)", index, len))
+}
+
+/// キーシーケンスに編集操作を適用する関数
+///
+/// # Arguments
+///
+/// * `key_sequence` - 編集前のキーシーケンス
+/// * `edits` - 適用する編集操作
+///
+/// # Returns
+///
+/// * `Ok(Vec<RecordedKey>)` - 編集後のキーシーケンス（`validate_key_sequence` で検証済み）
+/// * `Err(AppError::InvalidRequest)` - 位置が範囲外、または編集後のシーケンスが不正な場合
+pub fn apply_edits(key_sequence: &[RecordedKey], edits: &[KeySequenceEdit]) -> Result<Vec<RecordedKey>, AppError> {
+    let mut steps = key_sequence.to_vec();
+
+    for edit in edits {
+        match edit {
+            KeySequenceEdit::Insert { index, step } => {
+                if *index > steps.len() {
+                    return Err(step_out_of_range(*index, steps.len()));
+                }
+                steps.insert(*index, step.clone());
+            }
+            KeySequenceEdit::Remove { index } => {
+                if *index >= steps.len() {
+                    return Err(step_out_of_range(*index, steps.len()));
+                }
+                steps.remove(*index);
+            }
+            KeySequenceEdit::Move { from, to } => {
+                if *from >= steps.len() {
+                    return Err(step_out_of_range(*from, steps.len()));
+                }
+                if *to >= steps.len() {
+                    return Err(step_out_of_range(*to, steps.len()));
+                }
+                let step = steps.remove(*from);
+                steps.insert(*to, step);
+            }
+            KeySequenceEdit::SetKey {
+                index,
+                key,
+                event_type,
+                modifiers,
+            } => {
+                let len = steps.len();
+                let step = steps.get_mut(*index).ok_or_else(|| step_out_of_range(*index, len))?;
+                if let Some(key) = key {
+                    step.key = key.clone();
+                }
+                if let Some(event_type) = event_type {
+                    step.event_type = event_type.clone();
+                }
+                if let Some(modifiers) = modifiers {
+                    step.modifiers = modifiers.clone();
+                }
+            }
+            KeySequenceEdit::SetTimestamp { index, timestamp } => {
+                let len = steps.len();
+                let step = steps.get_mut(*index).ok_or_else(|| step_out_of_range(*index, len))?;
+                step.timestamp = *timestamp;
+            }
+        }
+    }
+
+    validate_key_sequence(&steps)?;
+    Ok(steps)
+}
+
+/// キーシーケンスを検証する関数
+///
+/// 全てのキー名が `string_to_key` で解釈できること、イベント種類が `press` / `release` であること、
+/// タイムスタンプが減少しないことを確認します。
+///
+/// # Arguments
+///
+/// * `key_sequence` - 検証するキーシーケンス
+///
+/// # Returns
+///
+/// * `Ok(())` - 実行できるキーシーケンスの場合
+/// * `Err(AppError::InvalidRequest)` - 不正なステップがある場合（最初の1つを報告）
+pub fn validate_key_sequence(key_sequence: &[RecordedKey]) -> Result<(), AppError> {
+    if key_sequence.len() > MAX_KEY_SEQUENCE_LEN {
+        return Err(AppError::InvalidRequest(format!(
+            "A key sequence can have at most {} steps",
+            MAX_KEY_SEQUENCE_LEN
+        )));
+    }
+
+    let mut previous_timestamp = 0;
+    for (index, step) in key_sequence.iter().enumerate() {
+        if string_to_key(&step.key).is_none() {
+            return Err(AppError::InvalidRequest(format!("Step {}: unknown key '{}'", index, step.key)));
+        }
+        if step.event_type != "press" && step.event_type != "release" {
+            return Err(AppError::InvalidRequest(format!(
+                "Step {}: event_type must be 'press' or 'release', got '{}'",
+                index, step.event_type
+            )));
+        }
+        if step.timestamp < previous_timestamp {
+            return Err(AppError::InvalidRequest(format!(
+                "Step {}: timestamp {} is earlier than the previous step ({})",
+                index, step.timestamp, previous_timestamp
+            )));
+        }
+        previous_timestamp = step.timestamp;
+    }
+
+    Ok(())
+}
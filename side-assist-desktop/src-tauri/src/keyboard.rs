@@ -1,8 +1,175 @@
 use rdev::Key;
 
+/// キー名とrdev::Keyの対応表
+///
+/// `string_to_key` と `key_to_string` の両方がこの表を参照するため、
+/// 表に載っているキーは必ず名前から復元（再生）できます。
+/// 新しいキー名はrdev::Keyのバリアント名をそのまま使用します
+/// （以前のバージョンが`{:?}`で保存したキー名とも互換になります）。
+const KEY_NAMES: &[(&str, Key)] = &[
+    // アルファベット
+    ("KeyA", Key::KeyA),
+    ("KeyB", Key::KeyB),
+    ("KeyC", Key::KeyC),
+    ("KeyD", Key::KeyD),
+    ("KeyE", Key::KeyE),
+    ("KeyF", Key::KeyF),
+    ("KeyG", Key::KeyG),
+    ("KeyH", Key::KeyH),
+    ("KeyI", Key::KeyI),
+    ("KeyJ", Key::KeyJ),
+    ("KeyK", Key::KeyK),
+    ("KeyL", Key::KeyL),
+    ("KeyM", Key::KeyM),
+    ("KeyN", Key::KeyN),
+    ("KeyO", Key::KeyO),
+    ("KeyP", Key::KeyP),
+    ("KeyQ", Key::KeyQ),
+    ("KeyR", Key::KeyR),
+    ("KeyS", Key::KeyS),
+    ("KeyT", Key::KeyT),
+    ("KeyU", Key::KeyU),
+    ("KeyV", Key::KeyV),
+    ("KeyW", Key::KeyW),
+    ("KeyX", Key::KeyX),
+    ("KeyY", Key::KeyY),
+    ("KeyZ", Key::KeyZ),
+    // 数字（メインキーボード）
+    ("Num0", Key::Num0),
+    ("Num1", Key::Num1),
+    ("Num2", Key::Num2),
+    ("Num3", Key::Num3),
+    ("Num4", Key::Num4),
+    ("Num5", Key::Num5),
+    ("Num6", Key::Num6),
+    ("Num7", Key::Num7),
+    ("Num8", Key::Num8),
+    ("Num9", Key::Num9),
+    // 修飾キー
+    ("MetaLeft", Key::MetaLeft),
+    ("MetaRight", Key::MetaRight),
+    ("ControlLeft", Key::ControlLeft),
+    ("ControlRight", Key::ControlRight),
+    ("ShiftLeft", Key::ShiftLeft),
+    ("ShiftRight", Key::ShiftRight),
+    ("Alt", Key::Alt),
+    ("AltRight", Key::AltGr),
+    ("Function", Key::Function),
+    // 編集・制御キー
+    ("Space", Key::Space),
+    ("Enter", Key::Return),
+    ("Escape", Key::Escape),
+    ("Backspace", Key::Backspace),
+    ("Tab", Key::Tab),
+    ("CapsLock", Key::CapsLock),
+    ("Insert", Key::Insert),
+    ("Delete", Key::Delete),
+    // ナビゲーションキー
+    ("UpArrow", Key::UpArrow),
+    ("DownArrow", Key::DownArrow),
+    ("LeftArrow", Key::LeftArrow),
+    ("RightArrow", Key::RightArrow),
+    ("Home", Key::Home),
+    ("End", Key::End),
+    ("PageUp", Key::PageUp),
+    ("PageDown", Key::PageDown),
+    // ファンクションキー
+    ("F1", Key::F1),
+    ("F2", Key::F2),
+    ("F3", Key::F3),
+    ("F4", Key::F4),
+    ("F5", Key::F5),
+    ("F6", Key::F6),
+    ("F7", Key::F7),
+    ("F8", Key::F8),
+    ("F9", Key::F9),
+    ("F10", Key::F10),
+    ("F11", Key::F11),
+    ("F12", Key::F12),
+    ("F13", Key::F13),
+    ("F14", Key::F14),
+    ("F15", Key::F15),
+    ("F16", Key::F16),
+    ("F17", Key::F17),
+    ("F18", Key::F18),
+    ("F19", Key::F19),
+    ("F20", Key::F20),
+    ("F21", Key::F21),
+    ("F22", Key::F22),
+    ("F23", Key::F23),
+    ("F24", Key::F24),
+    // 記号キー
+    ("BackQuote", Key::BackQuote),
+    ("Minus", Key::Minus),
+    ("Equal", Key::Equal),
+    ("LeftBracket", Key::LeftBracket),
+    ("RightBracket", Key::RightBracket),
+    ("BackSlash", Key::BackSlash),
+    ("IntlBackslash", Key::IntlBackslash),
+    ("SemiColon", Key::SemiColon),
+    ("Quote", Key::Quote),
+    ("Comma", Key::Comma),
+    ("Dot", Key::Dot),
+    ("Slash", Key::Slash),
+    // テンキー
+    ("Kp0", Key::Kp0),
+    ("Kp1", Key::Kp1),
+    ("Kp2", Key::Kp2),
+    ("Kp3", Key::Kp3),
+    ("Kp4", Key::Kp4),
+    ("Kp5", Key::Kp5),
+    ("Kp6", Key::Kp6),
+    ("Kp7", Key::Kp7),
+    ("Kp8", Key::Kp8),
+    ("Kp9", Key::Kp9),
+    ("KpReturn", Key::KpReturn),
+    ("KpMinus", Key::KpMinus),
+    ("KpPlus", Key::KpPlus),
+    ("KpMultiply", Key::KpMultiply),
+    ("KpDivide", Key::KpDivide),
+    ("KpDelete", Key::KpDelete),
+    ("NumLock", Key::NumLock),
+    // 日本語・韓国語キーボードのキー
+    ("IntlYen", Key::IntlYen),   // JISの ¥ キー
+    ("IntlRo", Key::IntlRo),     // JISの ろ（_）キー
+    ("KanaMode", Key::KanaMode), // Windowsの カタカナ/ひらがな キー
+    ("Lang1", Key::Lang1),       // Apple JISの かな キー（韓国語の 한/영 キー）
+    ("Lang2", Key::Lang2),       // Apple JISの 英数 キー（韓国語の 漢字 キー）
+    ("Lang3", Key::Lang3),       // カタカナ キー
+    ("Lang4", Key::Lang4),       // ひらがな キー
+    ("Lang5", Key::Lang5),       // 半角/全角 キー
+    // システムキー
+    ("PrintScreen", Key::PrintScreen),
+    ("ScrollLock", Key::ScrollLock),
+    ("Pause", Key::Pause),
+    // メディアキー
+    ("VolumeUp", Key::VolumeUp),
+    ("VolumeDown", Key::VolumeDown),
+    ("VolumeMute", Key::VolumeMute),
+];
+
+/// 旧バージョンとの互換用・入力補助用のキー名の別名
+const KEY_ALIASES: &[(&str, Key)] = &[
+    ("AltLeft", Key::Alt),    // Left Alt key
+    ("AltGr", Key::AltGr),    // Right Alt key (AltGr)
+    ("Return", Key::Return),
+    ("ArrowUp", Key::UpArrow),
+    ("ArrowDown", Key::DownArrow),
+    ("ArrowLeft", Key::LeftArrow),
+    ("ArrowRight", Key::RightArrow),
+    ("Kana", Key::Lang1),
+    ("Eisu", Key::Lang2),
+];
+
+/// キーシーケンスで使用できるキー名の一覧を取得する関数（別名と `Unknown(コード)` 形式は含まない）
+pub fn supported_key_names() -> Vec<&'static str> {
+    KEY_NAMES.iter().map(|(name, _)| *name).collect()
+}
+
 /// 文字列からrdev::Keyに変換する関数
 ///
 /// 保存されたキー名文字列をrdev::Key列挙型に変換します。
+/// `key_to_string` が生成する全ての名前（`Unknown(コード)` 形式を含む）を解釈できます。
 ///
 /// # Arguments
 ///
@@ -13,64 +180,30 @@ use rdev::Key;
 /// * `Some(Key)` - 変換成功時のrdev::Key
 /// * `None` - サポートされていないキー文字列の場合
 pub fn string_to_key(key_str: &str) -> Option<Key> {
-    match key_str {
-        "KeyA" => Some(Key::KeyA),
-        "KeyB" => Some(Key::KeyB),
-        "KeyC" => Some(Key::KeyC),
-        "KeyD" => Some(Key::KeyD),
-        "KeyE" => Some(Key::KeyE),
-        "KeyF" => Some(Key::KeyF),
-        "KeyG" => Some(Key::KeyG),
-        "KeyH" => Some(Key::KeyH),
-        "KeyI" => Some(Key::KeyI),
-        "KeyJ" => Some(Key::KeyJ),
-        "KeyK" => Some(Key::KeyK),
-        "KeyL" => Some(Key::KeyL),
-        "KeyM" => Some(Key::KeyM),
-        "KeyN" => Some(Key::KeyN),
-        "KeyO" => Some(Key::KeyO),
-        "KeyP" => Some(Key::KeyP),
-        "KeyQ" => Some(Key::KeyQ),
-        "KeyR" => Some(Key::KeyR),
-        "KeyS" => Some(Key::KeyS),
-        "KeyT" => Some(Key::KeyT),
-        "KeyU" => Some(Key::KeyU),
-        "KeyV" => Some(Key::KeyV),
-        "KeyW" => Some(Key::KeyW),
-        "KeyX" => Some(Key::KeyX),
-        "KeyY" => Some(Key::KeyY),
-        "KeyZ" => Some(Key::KeyZ),
-        "Num0" => Some(Key::Num0),
-        "Num1" => Some(Key::Num1),
-        "Num2" => Some(Key::Num2),
-        "Num3" => Some(Key::Num3),
-        "Num4" => Some(Key::Num4),
-        "Num5" => Some(Key::Num5),
-        "Num6" => Some(Key::Num6),
-        "Num7" => Some(Key::Num7),
-        "Num8" => Some(Key::Num8),
-        "Num9" => Some(Key::Num9),
-        "Space" => Some(Key::Space),
-        "MetaLeft" => Some(Key::MetaLeft),
-        "MetaRight" => Some(Key::MetaRight),
-        "ControlLeft" => Some(Key::ControlLeft),
-        "ControlRight" => Some(Key::ControlRight),
-        "ShiftLeft" => Some(Key::ShiftLeft),
-        "ShiftRight" => Some(Key::ShiftRight),
-        "Alt" => Some(Key::Alt),
-        "AltLeft" => Some(Key::Alt),    // Left Alt key
-        "AltRight" => Some(Key::AltGr), // Right Alt key (AltGr)
-        "Enter" => Some(Key::Return),
-        "Escape" => Some(Key::Escape),
-        "Backspace" => Some(Key::Backspace),
-        "Tab" => Some(Key::Tab),
-        _ => None,
+    if let Some((_, key)) = KEY_NAMES
+        .iter()
+        .chain(KEY_ALIASES.iter())
+        .find(|(name, _)| *name == key_str)
+    {
+        return Some(*key);
     }
+
+    // 対応表にないキーはプラットフォーム固有のキーコードとして保存されている
+    key_str
+        .strip_prefix("Unknown(")
+        .and_then(|rest| rest.strip_suffix(')'))
+        .and_then(|code| code.parse::<u32>().ok())
+        .map(Key::Unknown)
 }
 
-/// 文字をrdev::Keyに変換する関数
+/// 文字をrdev::Keyとシフト要否の組に変換する関数
 ///
-/// 入力文字をrdev::Key列挙型に変換します。大文字小文字は区別しません。
+/// 入力文字を物理キーのキーストロークに変換します。OSのキーボード配列によって
+/// 入力される文字が変わらないよう、US配列とJIS配列で同じキーに割り当てられている文字
+/// （英数字、空白、改行、タブと一部の記号）のみを変換します。
+/// `@`、`"`、`:` など配列によって位置が異なる記号は `None` を返すため、
+/// 呼び出し元でクリップボード経由の入力に切り替えてください。
+/// 大文字や記号など、Shiftキーの同時押しが必要な文字はシフトフラグがtrueになります。
 ///
 /// # Arguments
 ///
@@ -78,54 +211,77 @@ pub fn string_to_key(key_str: &str) -> Option<Key> {
 ///
 /// # Returns
 ///
-/// * `Some(Key)` - 変換成功時のrdev::Key
-/// * `None` - サポートされていない文字の場合
-pub fn char_to_key(ch: char) -> Option<Key> {
-    match ch.to_ascii_lowercase() {
-        'a' => Some(Key::KeyA),
-        'b' => Some(Key::KeyB),
-        'c' => Some(Key::KeyC),
-        'd' => Some(Key::KeyD),
-        'e' => Some(Key::KeyE),
-        'f' => Some(Key::KeyF),
-        'g' => Some(Key::KeyG),
-        'h' => Some(Key::KeyH),
-        'i' => Some(Key::KeyI),
-        'j' => Some(Key::KeyJ),
-        'k' => Some(Key::KeyK),
-        'l' => Some(Key::KeyL),
-        'm' => Some(Key::KeyM),
-        'n' => Some(Key::KeyN),
-        'o' => Some(Key::KeyO),
-        'p' => Some(Key::KeyP),
-        'q' => Some(Key::KeyQ),
-        'r' => Some(Key::KeyR),
-        's' => Some(Key::KeyS),
-        't' => Some(Key::KeyT),
-        'u' => Some(Key::KeyU),
-        'v' => Some(Key::KeyV),
-        'w' => Some(Key::KeyW),
-        'x' => Some(Key::KeyX),
-        'y' => Some(Key::KeyY),
-        'z' => Some(Key::KeyZ),
-        '0' => Some(Key::Num0),
-        '1' => Some(Key::Num1),
-        '2' => Some(Key::Num2),
-        '3' => Some(Key::Num3),
-        '4' => Some(Key::Num4),
-        '5' => Some(Key::Num5),
-        '6' => Some(Key::Num6),
-        '7' => Some(Key::Num7),
-        '8' => Some(Key::Num8),
-        '9' => Some(Key::Num9),
-        ' ' => Some(Key::Space),
-        _ => None, // サポートされていない文字
+/// * `Some((Key, bool))` - 変換成功時のrdev::KeyとShiftキーが必要かどうか
+/// * `None` - キーストロークで確実に入力できない文字の場合（日本語、絵文字、配列依存の記号など）
+pub fn char_to_keystroke(ch: char) -> Option<(Key, bool)> {
+    if ch.is_ascii_alphabetic() {
+        let key = match ch.to_ascii_lowercase() {
+            'a' => Key::KeyA,
+            'b' => Key::KeyB,
+            'c' => Key::KeyC,
+            'd' => Key::KeyD,
+            'e' => Key::KeyE,
+            'f' => Key::KeyF,
+            'g' => Key::KeyG,
+            'h' => Key::KeyH,
+            'i' => Key::KeyI,
+            'j' => Key::KeyJ,
+            'k' => Key::KeyK,
+            'l' => Key::KeyL,
+            'm' => Key::KeyM,
+            'n' => Key::KeyN,
+            'o' => Key::KeyO,
+            'p' => Key::KeyP,
+            'q' => Key::KeyQ,
+            'r' => Key::KeyR,
+            's' => Key::KeyS,
+            't' => Key::KeyT,
+            'u' => Key::KeyU,
+            'v' => Key::KeyV,
+            'w' => Key::KeyW,
+            'x' => Key::KeyX,
+            'y' => Key::KeyY,
+            _ => Key::KeyZ,
+        };
+        return Some((key, ch.is_ascii_uppercase()));
+    }
+
+    match ch {
+        '0' => Some((Key::Num0, false)),
+        '1' => Some((Key::Num1, false)),
+        '2' => Some((Key::Num2, false)),
+        '3' => Some((Key::Num3, false)),
+        '4' => Some((Key::Num4, false)),
+        '5' => Some((Key::Num5, false)),
+        '6' => Some((Key::Num6, false)),
+        '7' => Some((Key::Num7, false)),
+        '8' => Some((Key::Num8, false)),
+        '9' => Some((Key::Num9, false)),
+        ' ' => Some((Key::Space, false)),
+        '\n' => Some((Key::Return, false)),
+        '\t' => Some((Key::Tab, false)),
+        // Shiftなしの記号（US配列・JIS配列で共通）
+        '-' => Some((Key::Minus, false)),
+        ';' => Some((Key::SemiColon, false)),
+        ',' => Some((Key::Comma, false)),
+        '.' => Some((Key::Dot, false)),
+        '/' => Some((Key::Slash, false)),
+        // Shiftが必要な記号（US配列・JIS配列で共通）
+        '!' => Some((Key::Num1, true)),
+        '#' => Some((Key::Num3, true)),
+        '$' => Some((Key::Num4, true)),
+        '%' => Some((Key::Num5, true)),
+        '<' => Some((Key::Comma, true)),
+        '>' => Some((Key::Dot, true)),
+        '?' => Some((Key::Slash, true)),
+        _ => None, // キーストロークで表現できない文字・配列によって位置が異なる記号
     }
 }
 
 /// rdev::Keyを文字列に変換する関数
 ///
 /// rdev::Key列挙型を対応する文字列に変換します。
+/// 生成された名前は必ず `string_to_key` で元のキーに戻せます。
 ///
 /// # Arguments
 ///
@@ -133,59 +289,16 @@ pub fn char_to_key(ch: char) -> Option<Key> {
 ///
 /// # Returns
 ///
-/// * `String` - 対応する文字列名
-pub fn key_to_string(key: Key) -> String {
+/// * `Some(String)` - 対応する文字列名
+/// * `None` - 名前で表現できないキーの場合（記録対象外）
+pub fn key_to_string(key: Key) -> Option<String> {
+    if let Some((name, _)) = KEY_NAMES.iter().find(|(_, k)| *k == key) {
+        return Some(name.to_string());
+    }
+
     match key {
-        Key::KeyA => "KeyA".to_string(),
-        Key::KeyB => "KeyB".to_string(),
-        Key::KeyC => "KeyC".to_string(),
-        Key::KeyD => "KeyD".to_string(),
-        Key::KeyE => "KeyE".to_string(),
-        Key::KeyF => "KeyF".to_string(),
-        Key::KeyG => "KeyG".to_string(),
-        Key::KeyH => "KeyH".to_string(),
-        Key::KeyI => "KeyI".to_string(),
-        Key::KeyJ => "KeyJ".to_string(),
-        Key::KeyK => "KeyK".to_string(),
-        Key::KeyL => "KeyL".to_string(),
-        Key::KeyM => "KeyM".to_string(),
-        Key::KeyN => "KeyN".to_string(),
-        Key::KeyO => "KeyO".to_string(),
-        Key::KeyP => "KeyP".to_string(),
-        Key::KeyQ => "KeyQ".to_string(),
-        Key::KeyR => "KeyR".to_string(),
-        Key::KeyS => "KeyS".to_string(),
-        Key::KeyT => "KeyT".to_string(),
-        Key::KeyU => "KeyU".to_string(),
-        Key::KeyV => "KeyV".to_string(),
-        Key::KeyW => "KeyW".to_string(),
-        Key::KeyX => "KeyX".to_string(),
-        Key::KeyY => "KeyY".to_string(),
-        Key::KeyZ => "KeyZ".to_string(),
-        Key::Num0 => "Num0".to_string(),
-        Key::Num1 => "Num1".to_string(),
-        Key::Num2 => "Num2".to_string(),
-        Key::Num3 => "Num3".to_string(),
-        Key::Num4 => "Num4".to_string(),
-        Key::Num5 => "Num5".to_string(),
-        Key::Num6 => "Num6".to_string(),
-        Key::Num7 => "Num7".to_string(),
-        Key::Num8 => "Num8".to_string(),
-        Key::Num9 => "Num9".to_string(),
-        Key::Space => "Space".to_string(),
-        Key::MetaLeft => "MetaLeft".to_string(),
-        Key::MetaRight => "MetaRight".to_string(),
-        Key::ControlLeft => "ControlLeft".to_string(),
-        Key::ControlRight => "ControlRight".to_string(),
-        Key::ShiftLeft => "ShiftLeft".to_string(),
-        Key::ShiftRight => "ShiftRight".to_string(),
-        Key::Alt => "Alt".to_string(),
-        Key::AltGr => "AltLeft".to_string(),
-        Key::Return => "Enter".to_string(),
-        Key::Escape => "Escape".to_string(),
-        Key::Backspace => "Backspace".to_string(),
-        Key::Tab => "Tab".to_string(),
-        _ => format!("{:?}", key), // Fallback for unsupported keys
+        Key::Unknown(code) => Some(format!("Unknown({})", code)),
+        _ => None,
     }
 }
 
@@ -234,3 +347,72 @@ pub fn get_modifier_type(key: Key) -> Option<&'static str> {
         _ => None,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn letters_and_digits_are_typed_as_keystrokes() {
+        assert_eq!(char_to_keystroke('a'), Some((Key::KeyA, false)));
+        assert_eq!(char_to_keystroke('Z'), Some((Key::KeyZ, true)));
+        assert_eq!(char_to_keystroke('0'), Some((Key::Num0, false)));
+        assert_eq!(char_to_keystroke('9'), Some((Key::Num9, false)));
+        assert_eq!(char_to_keystroke(' '), Some((Key::Space, false)));
+        assert_eq!(char_to_keystroke('\n'), Some((Key::Return, false)));
+        assert_eq!(char_to_keystroke('\t'), Some((Key::Tab, false)));
+    }
+
+    #[test]
+    fn symbols_shared_by_us_and_jis_layouts_are_typed_as_keystrokes() {
+        let expected = [
+            ('-', Key::Minus, false),
+            (';', Key::SemiColon, false),
+            (',', Key::Comma, false),
+            ('.', Key::Dot, false),
+            ('/', Key::Slash, false),
+            ('!', Key::Num1, true),
+            ('#', Key::Num3, true),
+            ('$', Key::Num4, true),
+            ('%', Key::Num5, true),
+            ('<', Key::Comma, true),
+            ('>', Key::Dot, true),
+            ('?', Key::Slash, true),
+        ];
+        for (ch, key, shift) in expected {
+            assert_eq!(char_to_keystroke(ch), Some((key, shift)), "{:?}", ch);
+        }
+    }
+
+    #[test]
+    fn layout_dependent_symbols_are_not_typed_as_keystrokes() {
+        // JIS配列ではUS配列と異なるキー・Shiftの組み合わせになる記号
+        for ch in "@\"'^&*()_=+[]{}\\|:`~".chars() {
+            assert_eq!(char_to_keystroke(ch), None, "{:?}", ch);
+        }
+    }
+
+    #[test]
+    fn non_ascii_characters_are_not_typed_as_keystrokes() {
+        for ch in ['あ', '漢', 'é', '😀', '\u{3000}'] {
+            assert_eq!(char_to_keystroke(ch), None, "{:?}", ch);
+        }
+    }
+
+    #[test]
+    fn key_names_round_trip() {
+        for (name, key) in KEY_NAMES {
+            assert_eq!(key_to_string(*key).as_deref(), Some(*name), "{:?}", key);
+            assert_eq!(string_to_key(&key_to_string(*key).unwrap()), Some(*key), "{}", name);
+        }
+        assert_eq!(string_to_key(&key_to_string(Key::Unknown(42)).unwrap()), Some(Key::Unknown(42)));
+    }
+
+    #[test]
+    fn key_aliases_resolve_to_a_named_key() {
+        for (alias, key) in KEY_ALIASES {
+            assert_eq!(string_to_key(alias), Some(*key), "{}", alias);
+            assert!(key_to_string(*key).is_some(), "{}", alias);
+        }
+    }
+}
@@ -1,36 +1,72 @@
 use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
 use std::sync::{Arc, Mutex, atomic::{AtomicBool, Ordering}};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use serde::{Deserialize, Serialize};
-use rand::Rng;
+use schemars::JsonSchema;
 use lazy_static::lazy_static;
-use tauri::Manager;
+use tauri::{Emitter, Manager};
 use axum::{
-    extract::{Json, State},
-    http::{HeaderMap, StatusCode},
+    extract::{ConnectInfo, Json, State},
+    http::HeaderMap,
     response::Json as JsonResponse,
-    routing::{get, post},
-    Router,
 };
-use tower_http::cors::CorsLayer;
 // ENIGOは完全に削除し、rdevを使用
 use rdev::{EventType, Event};
-use qrcode::QrCode;
-use qrcode::render::svg;
 
 // モジュール宣言
+mod auth;
+pub mod backend;
+use backend::{InputBackend, RdevBackend};
+mod capabilities;
+mod clients;
+mod custom_actions;
+mod discovery;
+pub mod error;
+mod events;
 mod network;
+pub mod openapi;
+mod pairing;
+pub mod qr;
+mod security;
+pub mod server;
 mod storage;
+mod key_sequence;
 mod keyboard;
+mod macros;
 mod simulation;
 mod settings;
+mod snippets;
+mod sse;
+mod tls;
+mod websocket;
 
 // モジュールからのインポート  
-use network::get_local_ip_address;
-use storage::{save_custom_actions, load_custom_actions};
-use keyboard::{string_to_key, key_to_string, is_modifier_key, get_modifier_type};
-use simulation::{simulate_typing, simulate_copy, simulate_paste};
-use settings::{get_current_settings, update_settings_persistent, load_settings_persistent};
+use network::{list_network_interfaces, NetworkInterface};
+use storage::load_custom_actions;
+use keyboard::{key_to_string, is_modifier_key, get_modifier_type};
+use simulation::{simulate_typing, simulate_copy, simulate_paste, send_copy, send_paste, type_text};
+use simulation::{
+    parse_mouse_button, simulate_mouse_move, simulate_mouse_move_to, simulate_mouse_click,
+    simulate_mouse_drag, simulate_mouse_scroll,
+};
+use settings::{find_desktop_only_setting, get_current_settings, update_settings_persistent};
+use clients::{BannedClient, ClientInfo, ClientMetadata};
+use custom_actions::{
+    create_custom_action, delete_custom_action, duplicate_custom_action, edit_key_sequence,
+    reorder_custom_actions, replace_key_sequence, set_macro_steps, update_custom_action_icon,
+};
+use error::AppError;
+use key_sequence::{apply_edits, validate_key_sequence, KeySequenceEdit};
+use macros::{run_custom_action, validate_invocations, validate_steps, MacroStep};
+use snippets::expand_snippet;
+use events::{event_channel, SecurityAlert, SecurityAlertKind, ServerEvent};
+use security::{CredentialKind, RateLimitOutcome, SecurityState};
+use tls::{load_or_create_identity, TlsIdentity};
+use pairing::{
+    generate_device_token, hash_token, client_id_header, save_paired_devices,
+    PairedDevice, PairedDeviceInfo, PairingData,
+};
 
 // グローバル録画状態（rdevコールバック用）
 lazy_static! {
@@ -56,15 +92,10 @@ pub struct ServerStatus {
     pub running: bool,
     pub connected_clients: usize,
     pub port: u16,
+    pub bind_address: Option<String>, // Noneの場合は全インターフェースで待ち受け
 }
 
-#[derive(Clone, Debug)]
-pub struct ClientInfo {
-    pub id: String,
-    pub last_health_check: u64,
-}
-
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
 pub enum ShortcutType {
     Normal,      // 通常のキーシーケンス
     Sequential,  // シーケンシャルショートカット（Alt → H → B → A）
@@ -76,7 +107,7 @@ impl Default for ShortcutType {
     }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
 pub struct CustomAction {
     pub id: String,
     pub name: String,
@@ -85,6 +116,10 @@ pub struct CustomAction {
     pub created_at: u64,
     #[serde(default)] // 既存データとの互換性を保つ
     pub shortcut_type: ShortcutType, // ショートカットのタイプ
+    #[serde(default)] // 既存データは作成日時順に並ぶ
+    pub order: u32, // 一覧での表示順（小さいほど先頭、同じ場合は作成日時順）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")] // 空の場合は `key_sequence` を再生する
+    pub steps: Vec<MacroStep>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -94,7 +129,7 @@ pub struct CustomActionsStorage {
     pub last_updated: u64,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
 pub struct KeyModifiers {
     pub alt: bool,
     pub ctrl: bool,
@@ -113,7 +148,7 @@ impl Default for KeyModifiers {
     }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
 pub struct RecordedKey {
     pub key: String, // Key名を文字列として保存
     pub event_type: String, // "press" or "release"
@@ -123,7 +158,9 @@ pub struct RecordedKey {
 }
 
 
-type AppState = Arc<Mutex<ServerState>>;
+pub type AppState = Arc<Mutex<ServerState>>;
+
+pub use server::create_router;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct RecordingModalInfo {
@@ -143,39 +180,738 @@ pub struct ServerState {
     pub running: bool,
     pub connected_clients: HashMap<String, ClientInfo>,
     pub port: u16,
+    pub bind_address: Option<IpAddr>, // 待ち受け・QRコードに使用するアドレス（Noneの場合は全インターフェース）
     pub one_time_password: Option<String>,
     pub password_expiry: Option<u64>,
     pub operation_in_progress: bool,
     pub custom_actions: HashMap<String, CustomAction>,
     pub recording_modal_info: Option<RecordingModalInfo>,
+    pub events: tokio::sync::broadcast::Sender<ServerEvent>, // WebSocketクライアントへのプッシュ用
+    pub paired_devices: HashMap<String, PairedDevice>, // キーはクライアントID
+    pub security: SecurityState, // 認証失敗・リクエスト数の記録
+    pub tls_identity: Option<TlsIdentity>, // HTTPS/WSS用の自己署名証明書（初回使用時に読み込み）
+    pub server_handle: Option<server::ServerHandle>, // 実行中のサーバーの停止用
+    pub banned_clients: HashMap<String, BannedClient>, // キーはクライアントID
+    pub input_backend: Arc<dyn InputBackend>, // 入力注入に使用するバックエンド（テストでは `RecordingBackend`）
+    pub client_activity_published_at: Option<Instant>, // アクションの記録でクライアント一覧を最後に送信した時刻
+    pub client_activity_pending: bool, // 送信を見送ったアクションの記録がある
 }
 
+/// アクションの記録によるクライアント一覧の送信を間引く間隔
+///
+/// 入力のたびに一覧を送信するとイベントチャンネルがあふれるため、この間隔に1回までまとめて送信します。
+pub(crate) const CLIENT_ACTIVITY_PUBLISH_INTERVAL: Duration = Duration::from_secs(1);
+
 impl Default for ServerState {
     fn default() -> Self {
         Self {
             running: false,
             connected_clients: HashMap::new(),
             port: 8080,
+            bind_address: None,
             one_time_password: None,
             password_expiry: None,
             operation_in_progress: false,
             custom_actions: HashMap::new(), // Will be loaded asynchronously during startup
             recording_modal_info: None,
+            events: event_channel(),
+            paired_devices: HashMap::new(), // Will be loaded asynchronously during startup
+            security: SecurityState::default(),
+            tls_identity: None,
+            server_handle: None,
+            banned_clients: HashMap::new(),
+            input_backend: Arc::new(RdevBackend),
+            client_activity_published_at: None,
+            client_activity_pending: false,
         }
     }
 }
 
-#[derive(Deserialize)]
+impl ServerState {
+    /// サーバーイベントを購読中の全クライアントに送信する
+    pub(crate) fn publish(&self, event: ServerEvent) {
+        // 購読者がいない場合のエラーは無視する
+        let _ = self.events.send(event);
+    }
+
+    /// 現在の録画状態を送信する
+    pub(crate) fn publish_recording_status(&self) {
+        self.publish(ServerEvent::RecordingStatus(recording_status_response(
+            self.recording_modal_info.as_ref(),
+        )));
+    }
+
+    /// 現在のカスタムアクション一覧を送信する
+    pub(crate) fn publish_custom_actions(&self) {
+        self.publish(ServerEvent::CustomActionsChanged {
+            actions: self.sorted_custom_actions(),
+        });
+    }
+
+    /// カスタムアクション一覧（表示順）
+    pub(crate) fn sorted_custom_actions(&self) -> Vec<CustomAction> {
+        let mut actions: Vec<CustomAction> = self.custom_actions.values().cloned().collect();
+        actions.sort_by(|a, b| {
+            (a.order, a.created_at, &a.id).cmp(&(b.order, b.created_at, &b.id))
+        });
+        actions
+    }
+
+    /// 一覧の末尾に追加する場合の表示順
+    pub(crate) fn next_custom_action_order(&self) -> u32 {
+        self.custom_actions
+            .values()
+            .map(|action| action.order.saturating_add(1))
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// カスタムアクションの名前・アイコンを変更する
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - 新しい名前（`None` の場合は変更しない）
+    /// * `icon` - 新しいアイコン（`None` の場合は変更しない、空文字列の場合は削除）
+    pub(crate) fn update_custom_action(
+        &mut self,
+        action_id: &str,
+        name: Option<&str>,
+        icon: Option<&str>,
+    ) -> Result<CustomAction, AppError> {
+        let name = name
+            .map(|name| {
+                let name = name.trim();
+                if name.is_empty() {
+                    Err(AppError::InvalidRequest("Custom action name must not be empty".to_string()))
+                } else {
+                    Ok(name.to_string())
+                }
+            })
+            .transpose()?;
+        
+        let action = self
+            .custom_actions
+            .get_mut(action_id)
+            .ok_or_else(|| AppError::ActionNotFound(action_id.to_string()))?;
+        if let Some(name) = name {
+            action.name = name;
+        }
+        if let Some(icon) = icon {
+            action.icon = Some(icon.trim().to_string()).filter(|icon| !icon.is_empty());
+        }
+        let action = action.clone();
+        
+        Ok(action)
+    }
+
+    /// カスタムアクションを削除する
+    ///
+    /// 他のアクションから `invoke_action` で呼び出されているアクションは削除できません。
+    pub(crate) fn delete_custom_action(&mut self, action_id: &str) -> Result<CustomAction, AppError> {
+        let mut used_by: Vec<String> = self
+            .custom_actions
+            .values()
+            .filter(|action| action.id != action_id && action.invokes(action_id))
+            .map(|action| action.name.clone())
+            .collect();
+        if !used_by.is_empty() {
+            used_by.sort();
+            return Err(AppError::ActionInUse {
+                action_id: action_id.to_string(),
+                used_by,
+            });
+        }
+        
+        let action = self
+            .custom_actions
+            .remove(action_id)
+            .ok_or_else(|| AppError::ActionNotFound(action_id.to_string()))?;
+        
+        Ok(action)
+    }
+
+    /// カスタムアクションを複製し、一覧の末尾に追加する
+    pub(crate) fn duplicate_custom_action(&mut self, action_id: &str, timestamp: u64) -> Result<CustomAction, AppError> {
+        let source = self
+            .custom_actions
+            .get(action_id)
+            .ok_or_else(|| AppError::ActionNotFound(action_id.to_string()))?;
+        
+        let duplicate = CustomAction {
+            id: uuid::Uuid::new_v4().to_string(),
+            name: format!("{} (copy)", source.name),
+            created_at: timestamp,
+            order: self.next_custom_action_order(),
+            ..source.clone()
+        };
+        self.custom_actions.insert(duplicate.id.clone(), duplicate.clone());
+        
+        Ok(duplicate)
+    }
+
+    /// カスタムアクションの表示順を設定する
+    ///
+    /// 指定されたIDの順に並べ、指定されなかったアクションは現在の順序のまま後ろに並べます。
+    pub(crate) fn reorder_custom_actions(&mut self, action_ids: &[String]) -> Result<Vec<CustomAction>, AppError> {
+        if let Some(unknown) = action_ids.iter().find(|id| !self.custom_actions.contains_key(*id)) {
+            return Err(AppError::ActionNotFound(unknown.clone()));
+        }
+        
+        let mut ordered_ids: Vec<String> = Vec::with_capacity(self.custom_actions.len());
+        for id in action_ids {
+            if !ordered_ids.contains(id) {
+                ordered_ids.push(id.clone());
+            }
+        }
+        for action in self.sorted_custom_actions() {
+            if !ordered_ids.contains(&action.id) {
+                ordered_ids.push(action.id);
+            }
+        }
+        
+        for (order, id) in ordered_ids.iter().enumerate() {
+            if let Some(action) = self.custom_actions.get_mut(id) {
+                action.order = order as u32;
+            }
+        }
+        
+        Ok(self.sorted_custom_actions())
+    }
+
+    /// カスタムアクションのキーシーケンスを置き換える（全てのステップを検証してから保存）
+    pub(crate) fn replace_key_sequence(
+        &mut self,
+        action_id: &str,
+        key_sequence: Vec<RecordedKey>,
+    ) -> Result<CustomAction, AppError> {
+        validate_key_sequence(&key_sequence)?;
+        self.check_key_sequence_editable(action_id)?;
+        
+        let action = self
+            .custom_actions
+            .get_mut(action_id)
+            .ok_or_else(|| AppError::ActionNotFound(action_id.to_string()))?;
+        action.key_sequence = key_sequence;
+        let action = action.clone();
+        
+        Ok(action)
+    }
+
+    /// キーシーケンスを置き換え・録画し直せるか確認する
+    ///
+    /// ステップで構成するアクションはキーシーケンスを実行しないため、
+    /// 置き換えるとステップが失われるか無視されます。存在しないアクション（新規の録画）は確認しません。
+    pub(crate) fn check_key_sequence_editable(&self, action_id: &str) -> Result<(), AppError> {
+        match self.custom_actions.get(action_id) {
+            Some(action) if !action.steps.is_empty() => Err(AppError::InvalidRequest(format!(
+                "Custom action '{}' runs steps, not a key sequence. Edit its steps instead",
+                action_id
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// `action_id` のアクションに設定するステップを検証する
+    /// （`invoke_action` の呼び出し先が存在し、呼び出しが循環せず深すぎないことも確認する）
+    pub(crate) fn validate_macro_steps(&self, action_id: &str, steps: &[MacroStep]) -> Result<(), AppError> {
+        validate_steps(steps)?;
+        
+        for step in steps {
+            if let MacroStep::InvokeAction { action_id } = step {
+                if !self.custom_actions.contains_key(action_id) {
+                    return Err(AppError::ActionNotFound(action_id.clone()));
+                }
+            }
+        }
+        validate_invocations(&self.custom_actions, action_id, steps)
+    }
+
+    /// カスタムアクションのステップを置き換える
+    ///
+    /// ステップを設定したアクションは `key_sequence` の代わりにステップを実行するため、
+    /// 録画したキーシーケンスは削除します。空のステップは設定できません。
+    pub(crate) fn set_macro_steps(&mut self, action_id: &str, steps: Vec<MacroStep>) -> Result<CustomAction, AppError> {
+        if steps.is_empty() {
+            return Err(AppError::InvalidRequest("A custom action needs at least one step".to_string()));
+        }
+        if !self.custom_actions.contains_key(action_id) {
+            return Err(AppError::ActionNotFound(action_id.to_string()));
+        }
+        self.validate_macro_steps(action_id, &steps)?;
+        
+        let action = self
+            .custom_actions
+            .get_mut(action_id)
+            .ok_or_else(|| AppError::ActionNotFound(action_id.to_string()))?;
+        action.steps = steps;
+        action.key_sequence.clear();
+        let action = action.clone();
+        
+        Ok(action)
+    }
+
+    /// ステップで構成するカスタムアクションを作成し、一覧の末尾に追加する
+    pub(crate) fn create_custom_action(
+        &mut self,
+        name: &str,
+        icon: Option<String>,
+        steps: Vec<MacroStep>,
+        timestamp: u64,
+    ) -> Result<CustomAction, AppError> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(AppError::InvalidRequest("Custom action name must not be empty".to_string()));
+        }
+        if steps.is_empty() {
+            return Err(AppError::InvalidRequest("A custom action needs at least one step".to_string()));
+        }
+        let id = uuid::Uuid::new_v4().to_string();
+        self.validate_macro_steps(&id, &steps)?;
+        
+        let action = CustomAction {
+            id,
+            name: name.to_string(),
+            icon: icon.filter(|icon| !icon.trim().is_empty()),
+            key_sequence: Vec::new(),
+            created_at: timestamp,
+            shortcut_type: ShortcutType::default(),
+            order: self.next_custom_action_order(),
+            steps,
+        };
+        self.custom_actions.insert(action.id.clone(), action.clone());
+        
+        Ok(action)
+    }
+
+    /// カスタムアクションのキーシーケンスを編集する（いずれかの操作が不正な場合は何も変更しない）
+    pub(crate) fn edit_key_sequence(
+        &mut self,
+        action_id: &str,
+        edits: &[KeySequenceEdit],
+    ) -> Result<CustomAction, AppError> {
+        let action = self
+            .custom_actions
+            .get(action_id)
+            .ok_or_else(|| AppError::ActionNotFound(action_id.to_string()))?;
+        let key_sequence = apply_edits(&action.key_sequence, edits)?;
+        
+        self.replace_key_sequence(action_id, key_sequence)
+    }
+
+    /// 保存されているワンタイムパスワードと照合する（期限切れの場合は無効）
+    pub(crate) fn is_password_valid(&self, provided_password: &str) -> bool {
+        if let (Some(stored_password), Some(expiry)) = (&self.one_time_password, self.password_expiry) {
+            let now = match SystemTime::now().duration_since(UNIX_EPOCH) {
+                Ok(duration) => duration.as_secs(),
+                Err(_) => return false,
+            };
+            
+            provided_password == stored_password && now < expiry
+        } else {
+            false
+        }
+    }
+
+    /// ペアリング済みデバイスのトークンと照合する（成功時は最終使用時刻を更新）
+    pub(crate) fn is_device_token_valid(&mut self, client_id: &str, token: &str) -> bool {
+        let token_hash = hash_token(token);
+        
+        match self.paired_devices.get_mut(client_id) {
+            Some(device) if device.token_hash == token_hash => {
+                device.last_used_at = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .ok()
+                    .map(|duration| duration.as_secs());
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// リクエストを認証する
+    ///
+    /// クライアントIDに紐づくデバイストークンを優先し、なければワンタイムパスワードで認証します。
+    pub(crate) fn authorize(
+        &mut self,
+        client_id: Option<&str>,
+        token: Option<&str>,
+        password: Option<&str>,
+    ) -> bool {
+        if let (Some(client_id), Some(token)) = (client_id, token) {
+            if self.is_device_token_valid(client_id, token) {
+                return true;
+            }
+        }
+        
+        password.map(|password| self.is_password_valid(password)).unwrap_or(false)
+    }
+
+    /// 接続中のセッション（WebSocket）の認証がまだ有効か確認する
+    ///
+    /// 失敗回数は記録しません。デバイストークンは失効していないこと、ワンタイムパスワードは
+    /// 再生成・無効化されていないことを確認します（接続後にパスワードの期限が切れただけの場合は有効）。
+    pub(crate) fn is_session_authorized(
+        &self,
+        client_id: &str,
+        token: Option<&str>,
+        password: Option<&str>,
+        ip: Option<IpAddr>,
+    ) -> bool {
+        if self.is_banned(Some(client_id), ip) {
+            return false;
+        }
+
+        let token_hash = token.map(hash_token);
+        let token_valid = matches!(
+            (self.paired_devices.get(client_id), token_hash),
+            (Some(device), Some(token_hash)) if device.token_hash == token_hash
+        );
+        token_valid || (password.is_some() && self.one_time_password.as_deref() == password)
+    }
+
+    /// 失敗回数の制限付きでリクエストを認証する
+    ///
+    /// ロックアウト中のクライアントID・IPアドレスは検証せずに拒否します。
+    /// 認証情報を含むリクエストが失敗した場合は失敗回数を記録し、上限に達すると
+    /// ロックアウトやワンタイムパスワードの無効化を行ってデスクトップに警告します。
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - 認証に成功した場合
+    /// * `Err(AppError)` - `Unauthorized`・`InvalidPassword`・`PasswordExpired`・`PasswordNotSet`・`LockedOut` のいずれか
+    pub(crate) fn verify_credentials(
+        &mut self,
+        client_id: Option<&str>,
+        token: Option<&str>,
+        password: Option<&str>,
+        ip: Option<IpAddr>,
+    ) -> Result<(), AppError> {
+        if let Some(retry_after) = self.security.lockout_remaining(client_id, ip) {
+            return Err(AppError::LockedOut { retry_after });
+        }
+        
+        if self.authorize(client_id, token, password) {
+            self.security.record_success(client_id, ip);
+            return Ok(());
+        }
+        
+        // 認証情報を含まないリクエストは試行として数えない
+        if token.is_none() && password.is_none() {
+            return Err(AppError::Unauthorized);
+        }
+        
+        // 無効化される前のパスワードで失敗の種類を判定する（パスワードがない場合はデバイストークンの失敗）
+        let (error, credential) = match password {
+            Some(password) => (self.password_error(password), CredentialKind::Password),
+            None => (AppError::Unauthorized, CredentialKind::DeviceToken),
+        };
+        
+        let outcome = self.security.record_failure(client_id, ip, credential);
+        let alert = |kind, retry_after: Option<std::time::Duration>| SecurityAlert {
+            kind,
+            client_id: client_id.map(|id| id.to_string()),
+            ip: ip.map(|ip| ip.to_string()),
+            failed_attempts: match credential {
+                CredentialKind::Password => outcome.otp_failures,
+                CredentialKind::DeviceToken => outcome.token_failures,
+            },
+            retry_after_secs: retry_after.map(|duration| duration.as_secs()),
+        };
+        
+        if outcome.invalidate_otp && self.one_time_password.is_some() {
+            self.one_time_password = None;
+            self.password_expiry = None;
+            self.publish(ServerEvent::SecurityAlert(alert(SecurityAlertKind::OtpInvalidated, None)));
+        }
+        
+        match outcome.locked_out {
+            Some(retry_after) => {
+                self.publish(ServerEvent::SecurityAlert(alert(SecurityAlertKind::Lockout, Some(retry_after))));
+                Err(AppError::LockedOut { retry_after })
+            }
+            None => Err(error),
+        }
+    }
+
+    // ワンタイムパスワードが一致しなかった理由を返す（一致したが期限切れの場合のみ期限切れとして扱う）
+    fn password_error(&self, provided_password: &str) -> AppError {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(u64::MAX);
+        
+        match (&self.one_time_password, self.password_expiry) {
+            (Some(_), Some(expiry)) if now < expiry => AppError::InvalidPassword,
+            (Some(stored_password), _) if stored_password != provided_password => AppError::InvalidPassword,
+            (Some(_), _) => AppError::PasswordExpired,
+            (None, _) => AppError::PasswordNotSet,
+        }
+    }
+
+    /// IPアドレスごとのリクエスト数を確認する（上限は設定の `rateLimitPerMinute`）
+    ///
+    /// # Returns
+    ///
+    /// * `true` - リクエストを受け付ける場合
+    /// * `false` - 上限を超えている場合
+    pub(crate) fn check_rate_limit(&mut self, ip: Option<IpAddr>) -> bool {
+        let limit = get_current_settings().rate_limit_per_minute;
+        
+        match self.security.check_rate_limit(ip, limit) {
+            RateLimitOutcome::Allowed => true,
+            RateLimitOutcome::Exceeded => {
+                // 警告は上限を超えた最初のリクエストでのみ送信する
+                self.publish(ServerEvent::SecurityAlert(SecurityAlert {
+                    kind: SecurityAlertKind::RateLimited,
+                    client_id: None,
+                    ip: ip.map(|ip| ip.to_string()),
+                    failed_attempts: 0,
+                    retry_after_secs: None,
+                }));
+                false
+            }
+            RateLimitOutcome::StillExceeded => false,
+        }
+    }
+
+    /// 待ち受けるソケットアドレス（アドレス未指定の場合は全IPv4インターフェース）
+    pub(crate) fn bind_socket_addr(&self) -> SocketAddr {
+        SocketAddr::new(
+            self.bind_address.unwrap_or(IpAddr::V4(std::net::Ipv4Addr::UNSPECIFIED)),
+            self.port,
+        )
+    }
+
+    /// HTTPS/WSS用の証明書を取得する（未生成の場合は生成して保存する）
+    pub(crate) fn tls_identity(&mut self) -> Result<TlsIdentity, String> {
+        if let Some(identity) = &self.tls_identity {
+            return Ok(identity.clone());
+        }
+        
+        let identity = load_or_create_identity()?;
+        self.tls_identity = Some(identity.clone());
+        Ok(identity)
+    }
+
+    /// デバイスをペアリングし、新しいトークンを発行する（同じクライアントIDの既存トークンは無効になる）
+    pub(crate) fn pair_device(&mut self, client_id: &str, device_name: Option<String>) -> Result<String, String> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|e| format!("システム時刻の取得に失敗しました: {}", e))?
+            .as_secs();
+        let token = generate_device_token();
+        
+        self.paired_devices.insert(
+            client_id.to_string(),
+            PairedDevice {
+                client_id: client_id.to_string(),
+                device_name,
+                token_hash: hash_token(&token),
+                paired_at: now,
+                last_used_at: Some(now),
+            },
+        );
+        
+        Ok(token)
+    }
+
+    /// クライアントの最終アクセス時刻と端末情報を更新し、新規接続の場合は通知する
+    pub(crate) fn touch_client(&mut self, client_id: &str, timestamp: u64, metadata: &ClientMetadata) {
+        let is_new = !self.connected_clients.contains_key(client_id);
+        let client = self
+            .connected_clients
+            .entry(client_id.to_string())
+            .or_insert_with(|| ClientInfo::new(client_id, timestamp));
+        client.last_health_check = timestamp;
+        let changed = client.update_metadata(metadata);
+        
+        // 端末名が送られてこない場合はペアリング時の名前を使う
+        if client.device_name.is_none() {
+            client.device_name = self
+                .paired_devices
+                .get(client_id)
+                .and_then(|device| device.device_name.clone());
+        }
+        
+        if is_new {
+            self.publish(ServerEvent::ClientConnected {
+                client_id: client_id.to_string(),
+            });
+        }
+        if is_new || changed {
+            self.publish_clients();
+        }
+    }
+
+    /// クライアントを削除し、切断を通知する
+    pub(crate) fn remove_client(&mut self, client_id: &str) {
+        if self.connected_clients.remove(client_id).is_some() {
+            self.publish(ServerEvent::ClientDisconnected {
+                client_id: client_id.to_string(),
+            });
+            self.publish_clients();
+        }
+    }
+
+    /// `session_id` の登録が残っている場合のみクライアントを削除し、切断を通知する
+    ///
+    /// WebSocket接続の終了時に使用し、同じクライアントIDで後から登録された接続は削除しません。
+    pub(crate) fn remove_client_session(&mut self, client_id: &str, session_id: u64) {
+        let registered = self
+            .connected_clients
+            .get(client_id)
+            .is_some_and(|client| client.session_id == session_id);
+        if registered {
+            self.remove_client(client_id);
+        }
+    }
+
+    /// ハートビートが途絶えたクライアントを削除し、タイムアウトを通知する
+    pub(crate) fn time_out_client(&mut self, client_id: &str) {
+        if self.connected_clients.remove(client_id).is_some() {
+            self.publish(ServerEvent::ClientTimedOut {
+                client_id: client_id.to_string(),
+            });
+            self.publish_clients();
+        }
+    }
+
+    /// クライアントが最後に実行したアクションを記録する（未登録のクライアントは無視）
+    ///
+    /// クライアント一覧の送信は `CLIENT_ACTIVITY_PUBLISH_INTERVAL` に1回までとし、
+    /// 間隔内の記録は `flush_client_activity` でまとめて送信します。
+    pub(crate) fn record_client_action(&mut self, client_id: &str, action: &str, timestamp: u64) {
+        let Some(client) = self.connected_clients.get_mut(client_id) else {
+            return;
+        };
+        client.last_action = Some(action.to_string());
+        client.last_action_at = Some(timestamp);
+        
+        let recently_published = self
+            .client_activity_published_at
+            .is_some_and(|published_at| published_at.elapsed() < CLIENT_ACTIVITY_PUBLISH_INTERVAL);
+        if recently_published || self.client_activity_pending {
+            self.client_activity_pending = true;
+        } else {
+            self.client_activity_published_at = Some(Instant::now());
+            self.publish_clients();
+        }
+    }
+
+    /// 送信を見送ったアクションの記録があれば、クライアント一覧を送信する
+    pub(crate) fn flush_client_activity(&mut self) {
+        if std::mem::take(&mut self.client_activity_pending) {
+            self.client_activity_published_at = Some(Instant::now());
+            self.publish_clients();
+        }
+    }
+
+    /// 接続中のクライアント一覧（接続順）
+    pub(crate) fn client_list(&self) -> Vec<ClientInfo> {
+        let mut clients: Vec<ClientInfo> = self.connected_clients.values().cloned().collect();
+        clients.sort_by(|a, b| a.connected_at.cmp(&b.connected_at).then_with(|| a.id.cmp(&b.id)));
+        clients
+    }
+
+    /// 接続中のクライアント一覧をデスクトップUIに送信する
+    pub(crate) fn publish_clients(&self) {
+        self.publish(ServerEvent::ClientsChanged {
+            clients: self.client_list(),
+        });
+    }
+
+    /// クライアントを切断する（WebSocket接続を閉じ、一覧から削除する）
+    ///
+    /// 再接続は拒否しないため、接続を禁止する場合は `ban_client` を使用します。
+    pub(crate) fn disconnect_client(&mut self, client_id: &str) -> Result<ClientInfo, AppError> {
+        let client = self
+            .connected_clients
+            .get(client_id)
+            .cloned()
+            .ok_or_else(|| AppError::ClientNotFound(client_id.to_string()))?;
+        
+        client.disconnect.cancel();
+        self.remove_client(client_id);
+        
+        Ok(client)
+    }
+
+    /// クライアントからの接続を禁止する
+    ///
+    /// 接続中の場合は切断し、ペアリングも解除します（再ペアリングも拒否されます）。
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(true)` - ペアリングを解除した場合
+    /// * `Ok(false)` - ペアリングされていなかった場合
+    ///
+    /// いずれの場合も呼び出し元で `pairing_data` を保存する必要があります。
+    pub(crate) fn ban_client(&mut self, client_id: &str, timestamp: u64) -> Result<bool, AppError> {
+        let client = self.disconnect_client(client_id).ok();
+        let paired_device = self.paired_devices.remove(client_id);
+        
+        if client.is_none() && paired_device.is_none() {
+            return Err(AppError::ClientNotFound(client_id.to_string()));
+        }
+        
+        let device_name = client
+            .as_ref()
+            .and_then(|client| client.device_name.clone())
+            .or_else(|| paired_device.as_ref().and_then(|device| device.device_name.clone()));
+        self.banned_clients.insert(
+            client_id.to_string(),
+            BannedClient {
+                client_id: client_id.to_string(),
+                device_name,
+                ip: client.and_then(|client| client.ip),
+                banned_at: timestamp,
+            },
+        );
+        
+        Ok(paired_device.is_some())
+    }
+
+    /// ファイルに保存するペアリング情報（ペアリング済みデバイスと接続を禁止したクライアント）
+    pub(crate) fn pairing_data(&self) -> PairingData {
+        PairingData {
+            devices: self.paired_devices.clone(),
+            banned_clients: self.banned_clients.clone(),
+        }
+    }
+
+    /// 接続の禁止を解除する
+    pub(crate) fn unban_client(&mut self, client_id: &str) -> Result<(), AppError> {
+        self.banned_clients
+            .remove(client_id)
+            .map(|_| ())
+            .ok_or_else(|| AppError::ClientNotFound(client_id.to_string()))
+    }
+
+    /// クライアントID・IPアドレスが接続を禁止されているか確認する
+    pub(crate) fn is_banned(&self, client_id: Option<&str>, ip: Option<IpAddr>) -> bool {
+        self.banned_clients
+            .values()
+            .any(|banned| banned.matches(client_id, ip))
+    }
+}
+
+// パスワード（従来形式）は認証ミドルウェアがボディから直接読み取る
+#[derive(Deserialize, JsonSchema)]
 struct InputRequest {
     pub action: ActionType,
-    pub password: Option<String>,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, JsonSchema)]
 #[serde(tag = "type")]
 pub enum ActionType {
     #[serde(rename = "text")]
     Text { text: String },
+    #[serde(rename = "snippet")]
+    Snippet {
+        template: String, // `{date}`, `{time}`, `{clipboard}` などの変数を含むテキスト
+        #[serde(default)]
+        variables: HashMap<String, String>, // この実行でのみ使用する変数（ユーザー定義変数より優先）
+    },
     #[serde(rename = "copy")]
     Copy,
     #[serde(rename = "paste")]
@@ -196,14 +932,85 @@ pub enum ActionType {
         action: String,
         action_data: Option<String>,
     },
+    #[serde(rename = "mouse_move")]
+    MouseMove { dx: f64, dy: f64 }, // 相対移動（ピクセル）
+    #[serde(rename = "mouse_move_to")]
+    MouseMoveTo { x: f64, y: f64 }, // 絶対移動（ピクセル）
+    #[serde(rename = "mouse_click")]
+    MouseClick {
+        button: Option<String>, // "left" (default), "right", "middle"
+        clicks: Option<u8>,     // 1 (default), 2 = ダブルクリック
+    },
+    #[serde(rename = "mouse_drag")]
+    MouseDrag {
+        button: Option<String>,
+        dx: f64,
+        dy: f64,
+    },
+    #[serde(rename = "mouse_scroll")]
+    MouseScroll {
+        #[serde(default)]
+        dx: i64, // 横スクロール
+        #[serde(default)]
+        dy: i64, // 縦スクロール
+    },
 }
 
-#[derive(Deserialize)]  
+impl ActionType {
+    /// `type` の値（`ACTION_TYPES` のいずれか）
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            ActionType::Text { .. } => "text",
+            ActionType::Snippet { .. } => "snippet",
+            ActionType::Copy => "copy",
+            ActionType::Paste => "paste",
+            ActionType::Custom { .. } => "custom",
+            ActionType::PrepareRecording { .. } => "prepare_recording",
+            ActionType::Gesture { .. } => "gesture",
+            ActionType::MouseMove { .. } => "mouse_move",
+            ActionType::MouseMoveTo { .. } => "mouse_move_to",
+            ActionType::MouseClick { .. } => "mouse_click",
+            ActionType::MouseDrag { .. } => "mouse_drag",
+            ActionType::MouseScroll { .. } => "mouse_scroll",
+        }
+    }
+}
+
+/// `ActionType` の `type` の値の一覧（`/capabilities` で公開、バリアントを追加したらここにも追加する。漏れはテストで検出する）
+pub(crate) const ACTION_TYPES: &[&str] = &[
+    "text",
+    "snippet",
+    "copy",
+    "paste",
+    "custom",
+    "prepare_recording",
+    "gesture",
+    "mouse_move",
+    "mouse_move_to",
+    "mouse_click",
+    "mouse_drag",
+    "mouse_scroll",
+];
+
+/// `ActionType::Gesture` の `action` に指定できる値の一覧
+pub(crate) const GESTURE_ACTIONS: &[&str] = &["copy", "paste", "text_input", "custom_action"];
+
+#[derive(Deserialize, JsonSchema)]
 struct AuthRequest {
     password: String,
+    #[serde(rename = "deviceName")]
+    device_name: Option<String>, // ペアリング一覧に表示するデバイス名
+}
+
+#[derive(Serialize, JsonSchema)]
+struct AuthResponse {
+    success: bool,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    token: Option<String>, // x-client-id 付きで認証した場合に発行されるデバイストークン
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, JsonSchema)]
 struct HealthResponse {
     status: String,
     service: String,
@@ -214,15 +1021,16 @@ struct HealthResponse {
     connected_clients: usize,
 }
 
-#[derive(Serialize)]
+// 成功時のレスポンス（失敗時は `AppError` のJSON）
+#[derive(Serialize, JsonSchema)]
 struct ApiResponse {
     success: bool,
     message: String,
 }
 
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
-struct RecordingStatusResponse {
+pub struct RecordingStatusResponse {
     status: String, // "idle", "preparing", "recording", "completed"
     action_id: Option<String>,
     name: Option<String>,
@@ -231,30 +1039,30 @@ struct RecordingStatusResponse {
 }
 
 #[tauri::command]
-async fn load_custom_actions_on_startup(state: tauri::State<'_, AppState>) -> Result<String, String> {
+async fn load_custom_actions_on_startup(state: tauri::State<'_, AppState>) -> Result<String, AppError> {
     
     
     match load_custom_actions().await {
         Ok(loaded_actions) => {
-            let mut state_guard = state.lock().map_err(|e| format!("Failed to lock state: {}", e))?;
+            let mut state_guard = state.lock()?;
             state_guard.custom_actions = loaded_actions;
             let count = state_guard.custom_actions.len();
+            state_guard.publish_custom_actions();
             
             
             Ok(format!("Loaded {} custom actions", count))
         }
         Err(e) => {
-            Err(format!("Failed to load custom actions: {}", e))
+            Err(AppError::Storage(format!("Failed to load custom actions: {}", e)))
         }
     }
 }
 
 #[tauri::command]
-async fn get_all_custom_actions(state: tauri::State<'_, AppState>) -> Result<Vec<CustomAction>, String> {
-    let state_guard = state.lock().map_err(|e| format!("Failed to lock state: {}", e))?;
-    let actions: Vec<CustomAction> = state_guard.custom_actions.values().cloned().collect();
+async fn get_all_custom_actions(state: tauri::State<'_, AppState>) -> Result<Vec<CustomAction>, AppError> {
+    let state_guard = state.lock()?;
     
-    Ok(actions)
+    Ok(state_guard.sorted_custom_actions())
 }
 
 #[tauri::command]
@@ -262,430 +1070,234 @@ async fn update_custom_action_name(
     state: tauri::State<'_, AppState>,
     action_id: String,
     new_name: String
-) -> Result<String, String> {
-    let mut state_guard = state.lock().map_err(|e| format!("Failed to lock state: {}", e))?;
+) -> Result<String, AppError> {
+    // ファイルに保存してから反映する
+    let action = custom_actions::modify_and_persist(&state, |state| {
+        state.update_custom_action(&action_id, Some(&new_name), None)
+    })
+    .await?;
     
-    if let Some(action) = state_guard.custom_actions.get_mut(&action_id) {
-        action.name = new_name.clone();
-        
-        // ファイルに永続化保存
-        let actions_to_save = state_guard.custom_actions.clone();
-        drop(state_guard); // ロックを早期解放
-        
-        tokio::spawn(async move {
-            if let Err(_e) = save_custom_actions(&actions_to_save).await {
-                // Error handling for save_custom_actions
-            }
-        });
-        
-        Ok(format!("Custom action name updated to: {}", new_name))
-    } else {
-        Err(format!("Custom action with ID '{}' not found", action_id))
-    }
+    Ok(format!("Custom action name updated to: {}", action.name))
 }
 
 #[tauri::command]
-async fn get_server_status(state: tauri::State<'_, AppState>) -> Result<ServerStatus, String> {
-    let state = state.lock().map_err(|e| format!("Failed to lock state: {}", e))?;
-    Ok(ServerStatus {
-        running: state.running,
-        connected_clients: state.connected_clients.len(),
-        port: state.port,
-    })
+async fn get_paired_devices(state: tauri::State<'_, AppState>) -> Result<Vec<PairedDeviceInfo>, AppError> {
+    let state_guard = state.lock()?;
+    let mut devices: Vec<PairedDeviceInfo> = state_guard.paired_devices.values().map(PairedDeviceInfo::from).collect();
+    devices.sort_by_key(|device| device.paired_at);
+    
+    Ok(devices)
 }
 
 #[tauri::command]
-async fn generate_one_time_password(state: tauri::State<'_, AppState>) -> Result<String, String> {
-    let mut rng = rand::thread_rng();
-    let password: String = (0..5).map(|_| rng.gen_range(0..10).to_string()).collect();
-    
-    let now = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .map_err(|e| format!("システム時刻の取得に失敗しました: {}", e))?
-        .as_secs();
-    
-    let expiry = now + 300; // 5分間有効
-    
-    let mut state = state.lock().map_err(|e| format!("Failed to lock state: {}", e))?;
-    state.one_time_password = Some(password.clone());
-    state.password_expiry = Some(expiry);
+async fn revoke_paired_device(
+    state: tauri::State<'_, AppState>,
+    client_id: String
+) -> Result<String, AppError> {
+    let devices_to_save = {
+        let mut state_guard = state.lock()?;
+        
+        if state_guard.paired_devices.remove(&client_id).is_none() {
+            return Err(AppError::DeviceNotFound(client_id));
+        }
+        // 接続中の場合はWebSocketを閉じて一覧から削除する（接続していなければ何もしない）
+        let _ = state_guard.disconnect_client(&client_id);
+        state_guard.pairing_data()
+    };
     
+    // ファイルに永続化保存
+    save_paired_devices(&devices_to_save).await.map_err(AppError::Storage)?;
     
-    Ok(password)
+    Ok(format!("Paired device revoked: {}", client_id))
 }
 
 #[tauri::command]
-async fn get_current_password(state: tauri::State<'_, AppState>) -> Result<Option<String>, String> {
-    let state = state.lock().map_err(|e| format!("Failed to lock state: {}", e))?;
-    
-    if let (Some(password), Some(expiry)) = (&state.one_time_password, state.password_expiry) {
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .map_err(|e| format!("システム時刻の取得に失敗しました: {}", e))?
-            .as_secs();
-        
-        if now < expiry {
-            Ok(Some(password.clone()))
-        } else {
-            Ok(None)
-        }
-    } else {
-        Ok(None)
-    }
+async fn get_connected_clients(state: tauri::State<'_, AppState>) -> Result<Vec<ClientInfo>, AppError> {
+    Ok(state.lock()?.client_list())
 }
 
 #[tauri::command]
-async fn generate_qr_code(state: tauri::State<'_, AppState>) -> Result<String, String> {
-    let state = state.lock().map_err(|e| format!("Failed to lock state: {}", e))?;
+async fn disconnect_client(
+    state: tauri::State<'_, AppState>,
+    client_id: String
+) -> Result<String, AppError> {
+    state.lock()?.disconnect_client(&client_id)?;
     
-    // パスワードが存在するかチェック
-    let password = if let (Some(password), Some(expiry)) = (&state.one_time_password, state.password_expiry) {
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .map_err(|e| format!("システム時刻の取得に失敗しました: {}", e))?
-            .as_secs();
-        
-        if now < expiry {
-            password.clone()
-        } else {
-            return Err("パスワードが期限切れです。新しいパスワードを生成してください。".to_string());
-        }
-    } else {
-        return Err("パスワードが生成されていません。まずパスワードを生成してください。".to_string());
-    };
+    Ok(format!("Client disconnected: {}", client_id))
+}
 
-    // 本当のIPアドレスを取得
+/// クライアントを切断し、以降の接続を拒否する（ペアリング情報と一緒に保存され、`unban_client` で解除）
+#[tauri::command]
+async fn ban_client(
+    state: tauri::State<'_, AppState>,
+    client_id: String
+) -> Result<String, AppError> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|e| AppError::Internal(format!("Failed to get system time: {}", e)))?
+        .as_secs();
     
-    let local_ip = match get_local_ip_address() {
-        Some(ip) => {
-            
-            ip
-        },
-        None => {
-            
-            return Err("ローカルIPアドレスを取得できませんでした。ネットワーク接続を確認してください。".to_string());
-        }
+    let data_to_save = {
+        let mut state_guard = state.lock()?;
+        state_guard.ban_client(&client_id, timestamp)?;
+        state_guard.pairing_data()
     };
-    let port = state.port;
-    
-    // URLスキーム形式でデータを作成（改行や空白を確実に除去）
-    let url_scheme = format!("sideassist://connect?ip={}&port={}&password={}", local_ip, port, password)
-        .trim()
-        .replace('\n', "")
-        .replace('\r', "")
-        .replace(' ', "");
-    
-    // デバッグ用詳細ログ
-    
-    
-    
-    
-    
     
+    // 再起動後も接続を拒否するためファイルに永続化保存
+    save_paired_devices(&data_to_save).await.map_err(AppError::Storage)?;
     
+    Ok(format!("Client banned: {}", client_id))
+}
 
-    // URLの各文字をチェック
-    for (_i, ch) in url_scheme.chars().enumerate() {
-        if ch.is_control() || ch == '\n' || ch == '\r' || ch == '\t' {
-            
-        }
-    }
-    
-    // QRコードを生成
-    let code = QrCode::new(&url_scheme)
-        .map_err(|e| format!("QRコード生成エラー: {}", e))?;
+#[tauri::command]
+async fn unban_client(
+    state: tauri::State<'_, AppState>,
+    client_id: String
+) -> Result<String, AppError> {
+    let data_to_save = {
+        let mut state_guard = state.lock()?;
+        state_guard.unban_client(&client_id)?;
+        state_guard.pairing_data()
+    };
     
-    // SVG形式で生成（正しいColor型を使用）
-    let svg_string = code.render::<svg::Color>()
-        .min_dimensions(200, 200)
-        .dark_color(svg::Color("#000000"))
-        .light_color(svg::Color("#ffffff"))
-        .build();
+    // ファイルに永続化保存
+    save_paired_devices(&data_to_save).await.map_err(AppError::Storage)?;
     
+    Ok(format!("Client unbanned: {}", client_id))
+}
+
+#[tauri::command]
+async fn get_banned_clients(state: tauri::State<'_, AppState>) -> Result<Vec<BannedClient>, AppError> {
+    let state_guard = state.lock()?;
+    let mut clients: Vec<BannedClient> = state_guard.banned_clients.values().cloned().collect();
+    clients.sort_by_key(|client| client.banned_at);
     
-    Ok(svg_string)
+    Ok(clients)
 }
 
+#[tauri::command]
+async fn get_app_settings() -> Result<settings::AppSettings, AppError> {
+    Ok(get_current_settings())
+}
 
+#[tauri::command]
+async fn update_app_settings(
+    state: tauri::State<'_, AppState>,
+    settings: serde_json::Value
+) -> Result<settings::AppSettings, AppError> {
+    server::update_settings(&state, settings)
+}
 
+#[tauri::command]
+async fn get_server_status(state: tauri::State<'_, AppState>) -> Result<ServerStatus, AppError> {
+    let state = state.lock()?;
+    Ok(ServerStatus {
+        running: state.running,
+        connected_clients: state.connected_clients.len(),
+        port: state.port,
+        bind_address: state.bind_address.map(|address| address.to_string()),
+    })
+}
 
+#[tauri::command]
+async fn generate_one_time_password(state: tauri::State<'_, AppState>) -> Result<String, AppError> {
+    server::generate_one_time_password(&state, server::DEFAULT_PASSWORD_TTL_SECS)
+}
 
-// 保存されたキーシーケンスを再生する関数
-async fn execute_custom_action(action: &CustomAction) -> Result<String, String> {
-    
-    
-    tokio::task::spawn_blocking({
-        let key_sequence = action.key_sequence.clone();
-        let action_name = action.name.clone();
-        let shortcut_type = action.shortcut_type.clone();
-        
-        move || {
-            use std::{thread, time};
-            
-            // 再生開始前に少し待機
-            thread::sleep(time::Duration::from_millis(200));
-            
-            match shortcut_type {
-                ShortcutType::Sequential => {
-                    execute_sequential_shortcut(&key_sequence, &action_name)
-                }
-                ShortcutType::Normal => {
-                    execute_normal_shortcut(&key_sequence, &action_name)
-                }
-            }
-        }
-    }).await.map_err(|e| format!("Task error: {}", e))?
+#[tauri::command]
+async fn get_current_password(state: tauri::State<'_, AppState>) -> Result<Option<String>, AppError> {
+    server::current_password(&state)
 }
 
-// 通常のショートカット実行（従来の方式）
-fn execute_normal_shortcut(key_sequence: &[RecordedKey], action_name: &str) -> Result<String, String> {
-    use rdev::{simulate, EventType, SimulateError};
-    use std::{thread, time};
-    
-    fn send(event_type: &EventType) -> Result<(), SimulateError> {
-        let delay = time::Duration::from_millis(50);
-        let result = simulate(event_type);
-        thread::sleep(delay);
-        result
-    }
-    
-    let mut executed_keys = 0;
-    for recorded_key in key_sequence {
-        if let Some(key) = string_to_key(&recorded_key.key) {
-            if recorded_key.event_type == "press" {
-                // キープレス
-                send(&EventType::KeyPress(key))
-                    .map_err(|e| format!("Failed to press key {}: {:?}", recorded_key.key, e))?;
-                
-                // キーリリース（セット実行）
-                send(&EventType::KeyRelease(key))
-                    .map_err(|e| format!("Failed to release key {}: {:?}", recorded_key.key, e))?;
-                
-                executed_keys += 1;
-                
-                
-                thread::sleep(time::Duration::from_millis(50));
-            }
-        } else {
-            
-        }
-    }
-    
+#[tauri::command]
+async fn generate_qr_code(state: tauri::State<'_, AppState>) -> Result<String, AppError> {
+    let url_scheme = server::connection_url(&state)?;
     
-    Ok(format!("Successfully executed normal shortcut '{}' with {} keys", action_name, executed_keys))
+    qr::render_svg(&url_scheme).map_err(AppError::Internal)
 }
 
-// シーケンシャルショートカット実行（Alt → H → B → A 形式）
-// 記録されたpress/releaseイベントを忠実に再現
-fn execute_sequential_shortcut(key_sequence: &[RecordedKey], action_name: &str) -> Result<String, String> {
-    use rdev::{simulate, EventType, SimulateError};
-    use std::{thread, time, collections::HashSet};
-    
-    fn send_key_event(event_type: &EventType) -> Result<(), SimulateError> {
-        let delay = time::Duration::from_millis(20); // 高速化
-        let result = simulate(event_type);
-        thread::sleep(delay);
-        result
-    }
-    
-    // エラー時の修飾キークリーンアップ用
-    fn cleanup_modifiers(active_modifiers: &HashSet<String>) {
-        for modifier_key in active_modifiers {
-            if let Some(key) = string_to_key(modifier_key) {
-                let _ = send_key_event(&EventType::KeyRelease(key));
-                
-            }
-        }
-    }
-    
-    let mut active_modifiers = HashSet::new();
-    let mut executed_keys = 0;
-    
-    
-    
-    // 記録されたイベントを順次実行（press/release を完全に忠実に再現）
-    let execution_result: Result<(), String> = (|| {
-        for (index, recorded_key) in key_sequence.iter().enumerate() {
-            if let Some(key) = string_to_key(&recorded_key.key) {
-                let is_modifier = is_modifier_key(key);
-                
-                match recorded_key.event_type.as_str() {
-                    "press" => {
-                        send_key_event(&EventType::KeyPress(key))
-                            .map_err(|e| format!("Failed to press key {}: {:?}", recorded_key.key, e))?;
-                        
-                        if is_modifier {
-                            active_modifiers.insert(recorded_key.key.clone());
-                            
-                        } else {
-                            executed_keys += 1;
-                        }
-                    }
-                    "release" => {
-                        send_key_event(&EventType::KeyRelease(key))
-                            .map_err(|e| format!("Failed to release key {}: {:?}", recorded_key.key, e))?;
-                        
-                        if is_modifier {
-                            active_modifiers.remove(&recorded_key.key);
-                            
-                        } else {
-                            
-                        }
-                    }
-                    _ => {
-                        
-                    }
-                }
-                
-                // イベント間の適切な遅延（実際のタイミングを再現）
-                if index < key_sequence.len() - 1 {
-                    let current_time = recorded_key.timestamp;
-                    let next_time = key_sequence[index + 1].timestamp;
-                    let delay = (next_time.saturating_sub(current_time)).min(100); // 最大100ms
-                    
-                    if delay > 5 { // 5ms以上の遅延のみ適用
-                        thread::sleep(time::Duration::from_millis(delay));
-                    }
-                }
-                
-            } else {
-                
-            }
-        }
-        Ok(())
-    })();
-    
-    // エラー発生時の修飾キークリーンアップ
-    if execution_result.is_err() {
-        cleanup_modifiers(&active_modifiers);
-    }
+#[tauri::command]
+async fn generate_qr_code_png(state: tauri::State<'_, AppState>) -> Result<String, AppError> {
+    let url_scheme = server::connection_url(&state)?;
     
+    qr::render_png_data_url(&url_scheme).map_err(AppError::Internal)
+}
+
+#[tauri::command]
+async fn generate_qr_code_text(state: tauri::State<'_, AppState>) -> Result<String, AppError> {
+    let url_scheme = server::connection_url(&state)?;
     
-    Ok(format!("Successfully executed sequential shortcut '{}' with {} keys", action_name, executed_keys))
+    qr::render_terminal(&url_scheme).map_err(AppError::Internal)
+}
+
+#[tauri::command]
+async fn get_connection_url(state: tauri::State<'_, AppState>) -> Result<String, AppError> {
+    server::connection_url(&state)
+}
+
+
+#[tauri::command]
+async fn set_port(state: tauri::State<'_, AppState>, port: u16) -> Result<String, AppError> {
+    server::set_port(&state, port)
 }
 
 #[tauri::command]
-async fn set_port(state: tauri::State<'_, AppState>, port: u16) -> Result<String, String> {
-    let mut state = state.lock().map_err(|e| format!("Failed to lock state: {}", e))?;
-    
-    if state.operation_in_progress {
-        return Err("サーバー操作が実行中です。しばらくお待ちください。".to_string());
-    }
-    
-    if state.running {
-        return Err("サーバーが実行中です。まずサーバーを停止してください。".to_string());
-    }
-    
-    // ポート番号の妥当性チェック (u16の上限は65535なので上限チェックは不要)
-    if port < 1024 {
-        return Err("ポート番号は1024以上で指定してください。".to_string());
-    }
-    
-    state.port = port;
-    
-    Ok(format!("Port set to {}", port))
+async fn get_network_interfaces() -> Result<Vec<NetworkInterface>, AppError> {
+    Ok(list_network_interfaces())
 }
 
 #[tauri::command]
-async fn stop_server(state: tauri::State<'_, AppState>) -> Result<String, String> {
-    // 最初のチェックと状態設定
-    {
-        let mut state_guard = state.lock().map_err(|e| format!("Failed to lock state: {}", e))?;
-        
-        if state_guard.operation_in_progress {
-            return Err("サーバー操作が実行中です。しばらくお待ちください。".to_string());
-        }
-        
-        if !state_guard.running {
-            return Ok("Server is not running".to_string());
-        }
-        
-        state_guard.operation_in_progress = true;
-        state_guard.running = false;
-        state_guard.connected_clients.clear();
-        
-    } // MutexGuardはここで解放される
-    
-    // サーバーが完全に停止するまで待機
-    tokio::time::sleep(tokio::time::Duration::from_millis(2000)).await;
-    
-    // 操作完了フラグをクリア
-    {
-        let mut state_guard = state.lock().map_err(|e| format!("Failed to lock state: {}", e))?;
-        state_guard.operation_in_progress = false;
-    } // MutexGuardはここで解放される
-    
-    
-    Ok("Server stopped".to_string())
+async fn set_bind_address(state: tauri::State<'_, AppState>, address: Option<String>) -> Result<String, AppError> {
+    server::set_bind_address(&state, address.as_deref())
 }
 
 #[tauri::command]
-async fn start_server(state: tauri::State<'_, AppState>) -> Result<String, String> {
-    
-    let app_state = Arc::clone(&state);
+async fn stop_server(state: tauri::State<'_, AppState>) -> Result<String, AppError> {
+    server::stop_server(&state).await
+}
+
+#[tauri::command]
+async fn start_server(state: tauri::State<'_, AppState>) -> Result<String, AppError> {
+    let bind_addr = server::start_server(&state).await?;
     
-    let port = {
-        let mut state = app_state.lock().map_err(|e| {
-            
-            format!("Failed to lock state: {}", e)
-        })?;
-        
-        if state.operation_in_progress {
-            
-            return Err("サーバー操作が実行中です。しばらくお待ちください。".to_string());
-        }
-        
-        if state.running {
-            
-            return Err("Server is already running. Stop it first before starting a new one.".to_string());
-        }
-        
-        
-        state.operation_in_progress = true;
-        state.port
-    };
+    Ok(format!("Side Assist Server started on port {}", bind_addr.port()))
+}
 
-    // ポートが使用可能かチェック（一時的にバインドしてすぐに解放）
-    {
-        let test_listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{}", port)).await;
-        if let Err(e) = test_listener {
-            let mut state = app_state.lock().map_err(|e| format!("Failed to lock state: {}", e))?;
-            state.operation_in_progress = false;
-            return Err(format!("ポート{}は使用できません: {}", port, e));
-        }
-        // test_listenerはここで自動的にドロップされ、ポートが解放される
+/// アクセシビリティ権限の確認結果を再利用する時間（認証不要の `/capabilities` で毎回osascriptを実行しない）
+const ACCESSIBILITY_PERMISSION_CACHE_TTL: Duration = Duration::from_secs(5);
+
+lazy_static! {
+    static ref ACCESSIBILITY_PERMISSION_CACHE: Mutex<Option<(Instant, bool)>> = Mutex::new(None);
+}
+
+#[tauri::command]
+async fn check_accessibility_permission() -> Result<bool, AppError> {
+    // 権限を付与した直後の確認に使用するため、キャッシュせずに毎回確認する（結果はキャッシュを更新する）
+    let has_permission = tokio::task::spawn_blocking(check_accessibility_permission_blocking)
+        .await
+        .map_err(|e| AppError::Internal(format!("Task error: {}", e)))??;
+    
+    if let Ok(mut cache) = ACCESSIBILITY_PERMISSION_CACHE.lock() {
+        *cache = Some((Instant::now(), has_permission));
     }
+    Ok(has_permission)
+}
 
-    // サーバーを実際に開始
-    {
-        let mut state = app_state.lock().map_err(|e| format!("Failed to lock state: {}", e))?;
-        state.running = true;
-        state.operation_in_progress = false;
-    }
-
-    let server_state = Arc::clone(&app_state);
-    let error_state = Arc::clone(&app_state);
-    tokio::spawn(async move {
-        if let Err(_e) = run_http_server(server_state).await {
-            if let Ok(mut state) = error_state.lock() {
-                state.running = false;
-                state.operation_in_progress = false;
+/// アクセシビリティ権限を確認する関数（`ACCESSIBILITY_PERMISSION_CACHE_TTL` の間は前回の結果を返す）
+pub(crate) async fn cached_accessibility_permission() -> Result<bool, AppError> {
+    if let Ok(cache) = ACCESSIBILITY_PERMISSION_CACHE.lock() {
+        if let Some((checked_at, has_permission)) = *cache {
+            if checked_at.elapsed() < ACCESSIBILITY_PERMISSION_CACHE_TTL {
+                return Ok(has_permission);
             }
         }
-    });
-
-    // Start client cleanup task
-    let cleanup_state = Arc::clone(&app_state);
-    tokio::spawn(async move {
-        cleanup_inactive_clients(cleanup_state).await;
-    });
-
+    }
     
-    Ok(format!("Side Assist Server started on port {}", port))
+    check_accessibility_permission().await
 }
 
-#[tauri::command]
-async fn check_accessibility_permission() -> Result<bool, String> {
+// アクセシビリティ権限を確認する（macOSではosascriptを実行するため、非同期ランタイム上で直接呼ばない）
+fn check_accessibility_permission_blocking() -> Result<bool, String> {
     #[cfg(target_os = "macos")]
     {
         // 権限チェック：System Eventsに簡単なアクセスを試行
@@ -735,7 +1347,7 @@ async fn check_accessibility_permission() -> Result<bool, String> {
 }
 
 #[tauri::command]
-async fn open_system_preferences() -> Result<String, String> {
+async fn open_system_preferences() -> Result<String, AppError> {
     #[cfg(target_os = "macos")]
     {
         // macOSのシステム設定（アクセシビリティ）を開く
@@ -769,12 +1381,12 @@ async fn open_system_preferences() -> Result<String, String> {
             }
         }
         
-        Err(format!("全てのURL形式で失敗しました: {}", last_error))
+        Err(AppError::Internal(format!("全てのURL形式で失敗しました: {}", last_error)))
     }
     
     #[cfg(not(target_os = "macos"))]
     {
-        Err("macOS以外ではサポートされていません".to_string())
+        Err(AppError::Internal("macOS以外ではサポートされていません".to_string()))
     }
 }
 
@@ -784,14 +1396,14 @@ async fn open_system_preferences() -> Result<String, String> {
 
 
 #[tauri::command]
-async fn get_recording_modal_info(state: tauri::State<'_, AppState>) -> Result<Option<RecordingModalInfo>, String> {
-    let state_guard = state.lock().map_err(|e| format!("Failed to lock state: {}", e))?;
+async fn get_recording_modal_info(state: tauri::State<'_, AppState>) -> Result<Option<RecordingModalInfo>, AppError> {
+    let state_guard = state.lock()?;
     Ok(state_guard.recording_modal_info.clone())
 }
 
 #[tauri::command]
-async fn clear_recording_modal(state: tauri::State<'_, AppState>) -> Result<String, String> {
-    let mut state_guard = state.lock().map_err(|e| format!("Failed to lock state: {}", e))?;
+async fn clear_recording_modal(state: tauri::State<'_, AppState>) -> Result<String, AppError> {
+    let mut state_guard = state.lock()?;
     
     // キャンセル状態に設定（モバイル側に通知するため）
     if let Some(ref mut modal_info) = state_guard.recording_modal_info {
@@ -799,20 +1411,21 @@ async fn clear_recording_modal(state: tauri::State<'_, AppState>) -> Result<Stri
         modal_info.is_completed = true; // 完了状態にする
         modal_info.recorded_keys.clear(); // キーを空にしてキャンセルを示す
     }
+    state_guard.publish_recording_status();
     
     Ok("Recording modal cancelled".to_string())
 }
 
 #[tauri::command]
-async fn start_actual_recording(state: tauri::State<'_, AppState>, shortcut_type: String) -> Result<String, String> {
-    let mut state_guard = state.lock().map_err(|e| format!("Failed to lock state: {}", e))?;
+async fn start_actual_recording(state: tauri::State<'_, AppState>, shortcut_type: String) -> Result<String, AppError> {
+    let mut state_guard = state.lock()?;
     
     if let Some(ref mut modal_info) = state_guard.recording_modal_info {
         modal_info.is_recording = true;
         modal_info.start_time = Some(
             SystemTime::now()
                 .duration_since(UNIX_EPOCH)
-                .map_err(|_| "Failed to get system time")?
+                .map_err(|_| AppError::Internal("Failed to get system time".to_string()))?
                 .as_millis() as u64
         );
         modal_info.recorded_keys.clear();
@@ -826,15 +1439,18 @@ async fn start_actual_recording(state: tauri::State<'_, AppState>, shortcut_type
         
         
         
+        let message = format!("Recording started for: {}", modal_info.name);
+        state_guard.publish_recording_status();
+        
         // リアルキーリスナー開始
         let state_clone = Arc::clone(&state);
         tokio::spawn(async move {
             start_real_key_listener(state_clone).await;
         });
         
-        Ok(format!("Recording started for: {}", modal_info.name))
+        Ok(message)
     } else {
-        Err("No recording modal active".to_string())
+        Err(AppError::NoRecording)
     }
 }
 
@@ -842,16 +1458,16 @@ async fn start_actual_recording(state: tauri::State<'_, AppState>, shortcut_type
 async fn stop_actual_recording(
     state: tauri::State<'_, AppState>, 
     custom_name: Option<String>
-) -> Result<String, String> {
+) -> Result<String, AppError> {
     // まず録画停止フラグを設定
     {
-        let mut state_guard = state.lock().map_err(|e| format!("Failed to lock state: {}", e))?;
+        let mut state_guard = state.lock()?;
         
         if let Some(ref mut modal_info) = state_guard.recording_modal_info {
             modal_info.is_recording = false;
             
         } else {
-            return Err("No recording modal active".to_string());
+            return Err(AppError::NoRecording);
         }
     }
     
@@ -859,38 +1475,49 @@ async fn stop_actual_recording(
     tokio::time::sleep(tokio::time::Duration::from_millis(300)).await;
     
     let modal_info = {
-        let state_guard = state.lock().map_err(|e| format!("Failed to lock state: {}", e))?;
+        let state_guard = state.lock()?;
         
         if let Some(ref modal_info) = state_guard.recording_modal_info {
             modal_info.clone()
         } else {
-            return Err("No recording modal active".to_string());
+            return Err(AppError::NoRecording);
         }
     };
     
     let now = SystemTime::now()
         .duration_since(UNIX_EPOCH)
-        .map_err(|_| "Failed to get system time")?
+        .map_err(|_| AppError::Internal("Failed to get system time".to_string()))?
         .as_secs();
     
     // カスタムアクションを作成して保存
     let final_name = custom_name.unwrap_or(modal_info.name.clone());
-    let custom_action = CustomAction {
+    let mut custom_action = CustomAction {
         id: modal_info.action_id.clone(),
         name: final_name.clone(),
         icon: modal_info.icon,
         key_sequence: modal_info.recorded_keys,
         created_at: now,
         shortcut_type: modal_info.shortcut_type.clone(), // 録画時に設定されたタイプを使用
+        order: 0,
+        steps: Vec::new(),
     };
     
-    // 状態に追加して保存
-    {
-        let mut state_guard = state.lock().map_err(|e| format!("Failed to lock state: {}", e))?;
-        
-        
+    // 状態に追加してファイルに永続化保存
+    let custom_action = custom_actions::modify_and_persist(&state, |state_guard| {
+        // 録画中にステップが設定された場合は上書きしない
+        state_guard.check_key_sequence_editable(&custom_action.id)?;
+        // 録画し直した場合は表示順を維持し、新規の場合は末尾に追加
+        custom_action.order = match state_guard.custom_actions.get(&custom_action.id) {
+            Some(existing) => existing.order,
+            None => state_guard.next_custom_action_order(),
+        };
         state_guard.custom_actions.insert(modal_info.action_id.clone(), custom_action.clone());
-        
+        Ok(custom_action)
+    })
+    .await?;
+    
+    {
+        let mut state_guard = state.lock()?;
         
         // モーダル状態を録画完了状態に更新（即座にクリアしない）
         if let Some(ref mut modal_info) = state_guard.recording_modal_info {
@@ -899,25 +1526,9 @@ async fn stop_actual_recording(
             modal_info.name = final_name.clone(); // 編集された名前で更新
             modal_info.recorded_keys = custom_action.key_sequence.clone();
         }
+        state_guard.publish_recording_status();
     }
     
-    // カスタムアクションをファイルに永続化保存
-    {
-        let state_guard = state.lock().map_err(|e| format!("Failed to lock state: {}", e))?;
-        let actions_to_save = state_guard.custom_actions.clone();
-        drop(state_guard); // ロックを早期解放
-        
-        // 非同期保存を実行
-        tokio::spawn(async move {
-            
-            if let Err(_e) = save_custom_actions(&actions_to_save).await {
-                // Error handling for save_custom_actions
-            }
-        });
-    }
-    
-    
-    
     Ok(format!(
         "Recording stopped. Saved {} key events for: {}",
         custom_action.key_sequence.len(),
@@ -995,7 +1606,11 @@ fn rdev_callback(event: Event) {
                 .unwrap_or_default()
                 .as_millis() as u64;
             
-            let key_name = key_to_string(key);
+            // 再生できないキーは記録せず、録画中の画面に警告を表示する（保存後に無言でスキップされるのを防ぐ）
+            let Some(key_name) = key_to_string(key) else {
+                warn_unrecordable_key(key);
+                return;
+            };
             
             // 改良されたデバウンス: キー+イベントタイプの組み合わせで重複チェック
             let should_record = {
@@ -1048,9 +1663,9 @@ fn rdev_callback(event: Event) {
                 // グローバル録画状態に追加
                 if let Ok(mut keys_guard) = recorded_keys.lock() {
                     keys_guard.push(recorded_key.clone());
-                    // キー入力直後にメイン状態にも即座に同期
-                    sync_to_main_state(&keys_guard);
                 }
+                // キー入力直後にメイン状態にも即座に同期
+                append_to_main_state(recorded_key);
             } else {
                 
             }
@@ -1084,20 +1699,46 @@ fn update_modifier_state(key: rdev::Key, pressed: bool) {
     }
 }
 
-// メイン状態への即座同期関数
-fn sync_to_main_state(keys: &Vec<RecordedKey>) {
+// 記録できないキーが押されたことを購読中のクライアントに送信する関数
+fn warn_unrecordable_key(key: rdev::Key) {
     if let Ok(main_state_guard) = MAIN_STATE_REF.lock() {
         if let Some(ref main_state) = *main_state_guard {
-            if let Ok(mut state_guard) = main_state.lock() {
-                if let Some(ref mut modal_info) = state_guard.recording_modal_info {
-                    modal_info.recorded_keys = keys.clone();
-                    // 
+            if let Ok(state_guard) = main_state.lock() {
+                if let Some(ref modal_info) = state_guard.recording_modal_info {
+                    state_guard.publish(ServerEvent::RecordedKeyWarning {
+                        action_id: modal_info.action_id.clone(),
+                        key: format!("{:?}", key),
+                        message: format!("{:?} is not supported and was not recorded", key),
+                    });
                 }
             }
         }
     }
 }
 
+// 記録したキーをメイン状態に追加し、購読中のクライアントに送信する関数
+fn append_to_main_state(recorded_key: RecordedKey) {
+    if let Ok(main_state_guard) = MAIN_STATE_REF.lock() {
+        if let Some(ref main_state) = *main_state_guard {
+            if let Ok(mut state_guard) = main_state.lock() {
+                // 追加されたキーだけを送信する（録画状態全体は状態が変わった時のみ送信）
+                let event = match state_guard.recording_modal_info {
+                    Some(ref mut modal_info) => {
+                        modal_info.recorded_keys.push(recorded_key.clone());
+                        ServerEvent::RecordedKey {
+                            action_id: modal_info.action_id.clone(),
+                            index: modal_info.recorded_keys.len() - 1,
+                            key: recorded_key,
+                        }
+                    }
+                    None => return,
+                };
+                state_guard.publish(event);
+            }
+        }
+    }
+}
+
 // リアルキーリスナー実装（グローバルstateを使用）
 async fn start_real_key_listener(state: AppState) {
     
@@ -1137,19 +1778,8 @@ async fn start_real_key_listener(state: AppState) {
     // 停止フラグをリセット
     SHOULD_STOP_RECORDING.store(false, Ordering::Relaxed);
     
-    // rdev::listenは別スレッドで実行
-    let listener_handle = tokio::task::spawn_blocking(move || {
-        use rdev::listen;
-        
-        
-        
-        // rdev::listenでキーイベントを監視（関数ポインタを使用）
-        if let Err(_error) = listen(rdev_callback) {
-            // Error handling for rdev listen
-        }
-        
-        
-    });
+    // 入力イベントの監視を開始（起動済みの場合は共有のリスナーをそのまま使用）
+    start_input_listener(&state);
     
     // シンプルな監視ループ（停止待ち）
     loop {
@@ -1188,91 +1818,22 @@ async fn start_real_key_listener(state: AppState) {
             break;
         }
     }
-    
-    
-    
-    // リスナータスクの終了を待つ（タイムアウト付き）
-    let timeout = tokio::time::Duration::from_millis(1000);
-    let _ = tokio::time::timeout(timeout, listener_handle).await;
 }
 
-async fn run_http_server(state: AppState) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let port = {
-        let state_guard = state.lock().map_err(|e| format!("Failed to lock state: {}", e))?;
-        state_guard.port
-    };
-
-    let app = Router::new()
-        .route("/health", get(health_check))
-        .route("/input", post(handle_input))
-        .route("/auth", post(handle_auth))
-        .route("/recording/status", get(get_recording_status))
-        .route("/recording/acknowledge", post(acknowledge_recording))
-        .route("/custom_actions", get(get_custom_actions))
-        .route("/settings", get(get_settings))
-        .route("/settings", post(update_settings_endpoint))
-        .layer(CorsLayer::permissive())
-        .with_state(Arc::clone(&state));
-
-    let bind_addr = format!("0.0.0.0:{}", port);
-    
-    
-    let listener = tokio::net::TcpListener::bind(&bind_addr).await?;
-    
-    
-    
-    
-    
-    
-    
-    // サーバーの実行中に定期的にstateをチェックして停止する
-    let server_task = tokio::spawn(async move {
-        axum::serve(listener, app).await
-    });
-    
-    // サーバー状態監視タスク
-    let monitor_state = Arc::clone(&state);
-    let monitor_task = tokio::spawn(async move {
-        let mut interval = tokio::time::interval(tokio::time::Duration::from_millis(500));
-        loop {
-            interval.tick().await;
-            if let Ok(state_guard) = monitor_state.lock() {
-                if !state_guard.running {
-                    
-                    break;
-                }
-            }
-        }
-    });
-    
-    // どちらかのタスクが完了したら終了
-    tokio::select! {
-        result = server_task => {
-            match result {
-                Ok(Ok(())) => {},
-                Ok(Err(_e)) => {},
-                Err(_e) => {},
-            }
-        }
-        _ = monitor_task => {
-            
-        }
+/// OSの入力イベントの監視を開始する関数
+///
+/// 実際のカーソル位置の追跡（マウスの相対移動に使用）とキー録画で同じリスナーを共有します。
+pub(crate) fn start_input_listener(state: &AppState) {
+    if let Ok(state) = state.lock() {
+        state.input_backend.start_listener(rdev_callback);
     }
-    
-    // 状態を更新
-    if let Ok(mut state_guard) = state.lock() {
-        state_guard.running = false;
-        state_guard.connected_clients.clear();
-        state_guard.operation_in_progress = false;
-    }
-    
-    Ok(())
 }
 
 async fn health_check(
     headers: HeaderMap,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
     State(state): State<AppState>,
-) -> Result<JsonResponse<HealthResponse>, StatusCode> {
+) -> Result<JsonResponse<HealthResponse>, AppError> {
     
     
     
@@ -1283,24 +1844,19 @@ async fn health_check(
         },
         None => {
             
-            return Err(StatusCode::BAD_REQUEST);
+            return Err(AppError::MissingClientId);
         }
     };
 
     let timestamp = SystemTime::now()
         .duration_since(UNIX_EPOCH)
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .map_err(|e| AppError::Internal(format!("Failed to get system time: {}", e)))?
         .as_secs();
 
-    let mut state = state.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let metadata = ClientMetadata::from_headers(&headers, connect_info.map(|ConnectInfo(addr)| addr.ip()));
+    let mut state = state.lock()?;
     
-    state.connected_clients.insert(
-        client_id.clone(),
-        ClientInfo {
-            id: client_id.clone(),
-            last_health_check: timestamp,
-        },
-    );
+    state.touch_client(&client_id, timestamp, &metadata);
 
     let client_count = state.connected_clients.len();
     
@@ -1316,70 +1872,67 @@ async fn health_check(
 }
 
 async fn handle_input(
+    headers: HeaderMap,
     State(state): State<AppState>,
     Json(payload): Json<InputRequest>,
-) -> Result<JsonResponse<ApiResponse>, StatusCode> {
-    
-    // パスワード認証チェック
-    if let Some(provided_password) = &payload.password {
-        let is_valid = {
-            let state = state.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-            
-            if let (Some(stored_password), Some(expiry)) = (&state.one_time_password, state.password_expiry) {
-                let now = match SystemTime::now().duration_since(UNIX_EPOCH) {
-                    Ok(duration) => duration.as_secs(),
-                    Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
-                };
-                
-                provided_password == stored_password && now < expiry
-            } else {
-                false
-            }
-        };
-        
-        if !is_valid {
-        
-            return Err(StatusCode::UNAUTHORIZED);
-        }
-    } else {
-        
-        return Err(StatusCode::UNAUTHORIZED);
+) -> Result<JsonResponse<ApiResponse>, AppError> {
+    // 認証は auth::require_auth ミドルウェアで実施済み
+    if let Some(client_id) = client_id_header(&headers) {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0);
+        state.lock()?.record_client_action(client_id, payload.action.name(), timestamp);
     }
+    let message = execute_action(&state, &payload.action).await?;
     
-    
+    Ok(JsonResponse(ApiResponse {
+        success: true,
+        message,
+    }))
+}
+
+// アクションを実行する（HTTPとWebSocketで共通）
+async fn execute_action(state: &AppState, action: &ActionType) -> Result<String, AppError> {
+    let backend = Arc::clone(&state.lock()?.input_backend);
     
     // アクションタイプに基づいて処理を分岐
-    let result = match &payload.action {
+    match action {
         ActionType::Text { text } => {
-            
-            simulate_typing(text.clone()).await
+            type_text_with_report(backend, text).await
+        }
+        ActionType::Snippet { template, variables } => {
+            let text = expand_snippet(&backend, template, variables).await?;
+            type_text_with_report(backend, &text).await
         }
         ActionType::Copy => {
             
-            simulate_copy().await
+            send_copy(backend).await.map_err(AppError::injection)
         }
         ActionType::Paste => {
             
-            simulate_paste().await
+            send_paste(backend).await.map_err(AppError::injection)
         }
         ActionType::Custom { action_id } => {
             
             let action = {
-                let state_guard = state.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+                let state_guard = state.lock()?;
                 state_guard.custom_actions.get(action_id).cloned()
             };
             
             if let Some(action) = action {
                 
-                execute_custom_action(&action).await
+                run_custom_action(state, &action).await
             } else {
-                Err(format!("Custom action '{}' not found", action_id))
+                Err(AppError::ActionNotFound(action_id.clone()))
             }
         }
         ActionType::PrepareRecording { action_id, name, icon, shortcut_type } => {
             
             
-            let mut state_guard = state.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+            let mut state_guard = state.lock()?;
+            // ステップで構成するアクションは録画し直せない
+            state_guard.check_key_sequence_editable(action_id)?;
             
             // ショートカットタイプを決定
             let determined_shortcut_type = match shortcut_type.as_deref() {
@@ -1401,6 +1954,8 @@ async fn handle_input(
             });
             
             
+            state_guard.publish_recording_status();
+            
             Ok(format!("Recording prepared for action: {} (type: {:?})", name, determined_shortcut_type))
         }
         ActionType::Gesture { fingers: _, direction: _, action, action_data } => {
@@ -1409,96 +1964,133 @@ async fn handle_input(
             match action.as_str() {
                 "copy" => {
                     
-                    simulate_copy().await
+                    send_copy(backend).await.map_err(AppError::injection)
                 }
                 "paste" => {
                     
-                    simulate_paste().await
+                    send_paste(backend).await.map_err(AppError::injection)
                 }
                 "text_input" => {
                     if let Some(text) = action_data {
-                        
-                        simulate_typing(text.clone()).await
+                        type_text_with_report(backend, text).await
                     } else {
-                        Err("No text data provided for gesture text input".to_string())
+                        Err(AppError::InvalidRequest("No text data provided for gesture text input".to_string()))
                     }
                 }
                 "custom_action" => {
                     
                     // 最初のカスタムアクションを実行
                     let action = {
-                        let state_guard = state.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+                        let state_guard = state.lock()?;
                         state_guard.custom_actions.values().next().cloned()
                     };
                     
                     if let Some(action) = action {
                         
-                        execute_custom_action(&action).await
+                        run_custom_action(state, &action).await
                     } else {
-                        Err("No custom actions available for gesture".to_string())
+                        Err(AppError::InvalidRequest("No custom actions available for gesture".to_string()))
                     }
                 }
                 _ => {
-                    Err(format!("Unknown gesture action: {}", action))
+                    Err(AppError::InvalidRequest(format!("Unknown gesture action: {}", action)))
                 }
             }
         }
-    };
-    
-    match result {
-        Ok(message) => {
-            
-            Ok(JsonResponse(ApiResponse {
-                success: true,
-                message,
-            }))
+        ActionType::MouseMove { dx, dy } => {
+            simulate_mouse_move(backend, *dx, *dy).await.map_err(AppError::injection)
         }
-        Err(e) => {
-            Ok(JsonResponse(ApiResponse {
-                success: false,
-                message: e,
-            }))
+        ActionType::MouseMoveTo { x, y } => {
+            simulate_mouse_move_to(backend, *x, *y).await.map_err(AppError::injection)
         }
+        ActionType::MouseClick { button, clicks } => {
+            let button = parse_mouse_button(button.as_deref()).map_err(AppError::InvalidRequest)?;
+            simulate_mouse_click(backend, button, clicks.unwrap_or(1)).await.map_err(AppError::injection)
+        }
+        ActionType::MouseDrag { button, dx, dy } => {
+            let button = parse_mouse_button(button.as_deref()).map_err(AppError::InvalidRequest)?;
+            simulate_mouse_drag(backend, button, *dx, *dy).await.map_err(AppError::injection)
+        }
+        ActionType::MouseScroll { dx, dy } => {
+            simulate_mouse_scroll(backend, *dx, *dy).await.map_err(AppError::injection)
+        }
+    }
+}
+
+// テキストを入力し、入力できなかった文字があれば失敗として扱う
+async fn type_text_with_report(backend: Arc<dyn InputBackend>, text: &str) -> Result<String, AppError> {
+    let report = type_text(backend, text.to_string()).await.map_err(AppError::injection)?;
+    let message = report.message(text);
+    
+    if report.undelivered.is_empty() {
+        Ok(message)
+    } else {
+        Err(AppError::InjectionFailed {
+            message,
+            undelivered: Some(report.undelivered),
+        })
     }
 }
 
 async fn handle_auth(
+    headers: HeaderMap,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
     State(state): State<AppState>,
     Json(payload): Json<AuthRequest>,
-) -> Result<JsonResponse<ApiResponse>, StatusCode> {
-    let is_valid = {
-        let state = state.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+) -> Result<JsonResponse<AuthResponse>, AppError> {
+    let client_id = client_id_header(&headers);
+    let pairing = {
+        let mut state_guard = state.lock()?;
         
-        if let (Some(stored_password), Some(expiry)) = (&state.one_time_password, state.password_expiry) {
-            let now = match SystemTime::now().duration_since(UNIX_EPOCH) {
-                Ok(duration) => duration.as_secs(),
-                Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
-            };
-            
-            payload.password == *stored_password && now < expiry
-        } else {
-            false
+        // デバイストークンではなくワンタイムパスワードのみで検証する
+        let ip = connect_info.map(|ConnectInfo(addr)| addr.ip());
+        state_guard.verify_credentials(client_id, None, Some(&payload.password), ip)?;
+        
+        // クライアントIDがあればワンタイムパスワードを長期トークンと交換する
+        match client_id {
+            Some(client_id) => {
+                let previous = state_guard.paired_devices.get(client_id).cloned();
+                let token = state_guard.pair_device(client_id, payload.device_name)?;
+                Some((client_id, token, previous, state_guard.pairing_data()))
+            }
+            None => None,
         }
     };
     
-    if is_valid {
-        
-        Ok(JsonResponse(ApiResponse {
-            success: true,
-            message: "Authentication successful".to_string(),
-        }))
-    } else {
-        
-        Err(StatusCode::UNAUTHORIZED)
-    }
+    let token = match pairing {
+        Some((client_id, token, previous, data_to_save)) => {
+            // ペアリング情報をファイルに永続化保存（失敗した場合は発行したトークンを無効にする）
+            if let Err(e) = save_paired_devices(&data_to_save).await {
+                let mut state_guard = state.lock()?;
+                match previous {
+                    Some(previous) => state_guard.paired_devices.insert(client_id.to_string(), previous),
+                    None => state_guard.paired_devices.remove(client_id),
+                };
+                return Err(AppError::Storage(e));
+            }
+            Some(token)
+        }
+        None => None,
+    };
+    
+    Ok(JsonResponse(AuthResponse {
+        success: true,
+        message: "Authentication successful".to_string(),
+        token,
+    }))
 }
 
 async fn get_recording_status(
     State(state): State<AppState>,
-) -> Result<JsonResponse<RecordingStatusResponse>, StatusCode> {
-    let state_guard = state.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+) -> Result<JsonResponse<RecordingStatusResponse>, AppError> {
+    let state_guard = state.lock()?;
     
-    let response = if let Some(ref modal_info) = state_guard.recording_modal_info {
+    Ok(JsonResponse(recording_status_response(state_guard.recording_modal_info.as_ref())))
+}
+
+// 録画モーダル情報からモバイル向けの録画状態を生成する
+fn recording_status_response(modal_info: Option<&RecordingModalInfo>) -> RecordingStatusResponse {
+    if let Some(modal_info) = modal_info {
         if modal_info.is_completed {
             if modal_info.recorded_keys.is_empty() {
                 // キャンセル状態（キーが空の場合）
@@ -1547,25 +2139,22 @@ async fn get_recording_status(
             recorded_keys_count: None,
             message: None,
         }
-    };
-    
-    Ok(JsonResponse(response))
+    }
 }
 
 async fn get_custom_actions(
     State(state): State<AppState>,
-) -> Result<JsonResponse<Vec<CustomAction>>, StatusCode> {
-    let state_guard = state.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-    let actions: Vec<CustomAction> = state_guard.custom_actions.values().cloned().collect();
+) -> Result<JsonResponse<Vec<CustomAction>>, AppError> {
+    let state_guard = state.lock()?;
     
-    Ok(JsonResponse(actions))
+    Ok(JsonResponse(state_guard.sorted_custom_actions()))
 }
 
 // Settings endpoints
 async fn get_settings(
     State(state): State<AppState>,
-) -> Result<JsonResponse<settings::AppSettings>, StatusCode> {
-    let _state_guard = state.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+) -> Result<JsonResponse<settings::AppSettings>, AppError> {
+    let _state_guard = state.lock()?;
     
     let current_settings = get_current_settings();
     
@@ -1573,7 +2162,7 @@ async fn get_settings(
     Ok(JsonResponse(current_settings))
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 struct UpdateSettingsRequest {
     settings: serde_json::Value,
 }
@@ -1581,33 +2170,34 @@ struct UpdateSettingsRequest {
 async fn update_settings_endpoint(
     State(state): State<AppState>,
     Json(request): Json<UpdateSettingsRequest>,
-) -> Result<JsonResponse<settings::AppSettings>, StatusCode> {
-    let _state_guard = state.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-    
+) -> Result<JsonResponse<settings::AppSettings>, AppError> {
+    let state_guard = state.lock()?;
     
+    // セキュリティに関わる設定はデスクトップでのみ変更できる
+    if let Some(name) = find_desktop_only_setting(&request.settings) {
+        return Err(AppError::DesktopOnlySetting(name.to_string()));
+    }
     
     // settings.rsのupdate_settings関数を使用して永続化
     // アプリハンドルが必要だが、storage.rsのパターンに合わせて独立したパス取得を使用
-    match update_settings_persistent(request.settings) {
-        Ok(updated_settings) => {
-            
-            Ok(JsonResponse(updated_settings))
-        }
-        Err(_e) => {
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
-    }
+    let updated_settings = update_settings_persistent(request.settings).map_err(AppError::Storage)?;
+    state_guard.publish(ServerEvent::SettingsChanged {
+        settings: updated_settings.clone(),
+    });
+    
+    Ok(JsonResponse(updated_settings))
 }
 
 async fn acknowledge_recording(
     State(state): State<AppState>,
-) -> Result<JsonResponse<ApiResponse>, StatusCode> {
-    let mut state_guard = state.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+) -> Result<JsonResponse<ApiResponse>, AppError> {
+    let mut state_guard = state.lock()?;
     
     if let Some(ref modal_info) = state_guard.recording_modal_info {
         if modal_info.is_completed {
             // 録画完了状態をクリア
             state_guard.recording_modal_info = None;
+            state_guard.publish_recording_status();
             
             
             Ok(JsonResponse(ApiResponse {
@@ -1615,51 +2205,10 @@ async fn acknowledge_recording(
                 message: "Recording acknowledged".to_string(),
             }))
         } else {
-            Ok(JsonResponse(ApiResponse {
-                success: false,
-                message: "No completed recording to acknowledge".to_string(),
-            }))
+            Err(AppError::RecordingNotCompleted)
         }
     } else {
-        Ok(JsonResponse(ApiResponse {
-            success: false,
-            message: "No recording session active".to_string(),
-        }))
-    }
-}
-
-async fn cleanup_inactive_clients(state: AppState) {
-    let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(30));
-    
-    loop {
-        interval.tick().await;
-        
-        let now = match SystemTime::now().duration_since(UNIX_EPOCH) {
-            Ok(duration) => duration.as_secs(),
-            Err(_) => {
-                continue; // スキップして次のループ処理を継続
-            }
-        };
-        
-        if let Ok(mut state) = state.lock() {
-            let timeout = 15; // 15 seconds timeout
-            let mut to_remove = Vec::new();
-            
-            for (client_id, client_info) in &state.connected_clients {
-                if now - client_info.last_health_check > timeout {
-                    to_remove.push(client_id.clone());
-                }
-            }
-            
-            for client_id in &to_remove {
-                state.connected_clients.remove(client_id);
-                
-            }
-            
-            if !to_remove.is_empty() {
-                
-            }
-        }
+        Err(AppError::NoRecording)
     }
 }
 
@@ -1684,6 +2233,8 @@ pub fn run() {
         .invoke_handler(tauri::generate_handler![
             get_server_status,
             set_port,
+            get_network_interfaces,
+            set_bind_address,
             stop_server,
             start_server,
             simulate_typing,
@@ -1694,46 +2245,81 @@ pub fn run() {
             generate_one_time_password,
             get_current_password,
             generate_qr_code,
+            generate_qr_code_png,
+            generate_qr_code_text,
+            get_connection_url,
             get_recording_modal_info,
 clear_recording_modal,
             start_actual_recording,
             stop_actual_recording,
             load_custom_actions_on_startup,
             get_all_custom_actions,
-            update_custom_action_name
+            update_custom_action_name,
+            update_custom_action_icon,
+            delete_custom_action,
+            duplicate_custom_action,
+            reorder_custom_actions,
+            replace_key_sequence,
+            edit_key_sequence,
+            create_custom_action,
+            set_macro_steps,
+            get_paired_devices,
+            revoke_paired_device,
+            get_connected_clients,
+            disconnect_client,
+            ban_client,
+            unban_client,
+            get_banned_clients,
+            get_app_settings,
+            update_app_settings
         ])
         .setup(|app| {
             // Tauri起動後にカスタムアクションと設定を読み込み
             let state: tauri::State<AppState> = app.state();
             let state_clone: Arc<Mutex<ServerState>> = Arc::clone(&state);
             
-            tauri::async_runtime::spawn(async move {
-                // 設定を読み込み
-                
-                match load_settings_persistent() {
-                    Ok(_settings) => {
-                        
-                    }
-                    Err(_e) => {
-                        // Error handling for load_settings_persistent failure
-                    }
-                }
-                
-                // カスタムアクションを読み込み
-                
-                match load_custom_actions().await {
-                    Ok(loaded_actions) => {
-                        if let Ok(mut state_guard) = state_clone.lock() {
-                            state_guard.custom_actions = loaded_actions;
-                            
-                        } else {
-                            // Error handling for state lock failure
+            // セキュリティ警告・クライアントの接続状況をデスクトップUIに転送
+            if let Ok(state_guard) = state.lock() {
+                let mut events = state_guard.events.subscribe();
+                let app_handle = app.handle().clone();
+                let event_state = Arc::clone(&state);
+                tauri::async_runtime::spawn(async move {
+                    loop {
+                        match events.recv().await {
+                            Ok(ServerEvent::SecurityAlert(alert)) => {
+                                let _ = app_handle.emit("security-alert", alert);
+                            }
+                            Ok(ServerEvent::ClientsChanged { clients }) => {
+                                let _ = app_handle.emit("clients-changed", clients);
+                            }
+                            Ok(event @ ServerEvent::ClientConnected { .. }) => {
+                                let _ = app_handle.emit("client-connected", event);
+                            }
+                            Ok(event @ ServerEvent::ClientDisconnected { .. }) => {
+                                let _ = app_handle.emit("client-disconnected", event);
+                            }
+                            Ok(event @ ServerEvent::ClientTimedOut { .. }) => {
+                                let _ = app_handle.emit("client-timed-out", event);
+                            }
+                            Ok(event @ ServerEvent::RecordedKeyWarning { .. }) => {
+                                let _ = app_handle.emit("recorded-key-warning", event);
+                            }
+                            Ok(_) => {}
+                            // 取りこぼした場合は現在のクライアント一覧を送り直す
+                            Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => {
+                                let clients = event_state.lock().map(|state_guard| state_guard.client_list());
+                                if let Ok(clients) = clients {
+                                    let _ = app_handle.emit("clients-changed", clients);
+                                }
+                            }
+                            Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
                         }
                     }
-                    Err(_e) => {
-                        // Error handling for load_custom_actions failure
-                    }
-                }
+                });
+            }
+            
+            tauri::async_runtime::spawn(async move {
+                server::load_persistent_state(&state_clone).await;
             });
             
             Ok(())
@@ -1741,3 +2327,93 @@ clear_recording_modal,
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state_with_client(client_id: &str) -> ServerState {
+        let mut state = ServerState::default();
+        state
+            .connected_clients
+            .insert(client_id.to_string(), ClientInfo::new(client_id, 0));
+        state
+    }
+
+    fn published_client_lists(events: &mut tokio::sync::broadcast::Receiver<ServerEvent>) -> usize {
+        std::iter::from_fn(|| events.try_recv().ok())
+            .filter(|event| matches!(event, ServerEvent::ClientsChanged { .. }))
+            .count()
+    }
+
+    #[test]
+    fn client_actions_within_the_interval_are_coalesced() {
+        let mut state = state_with_client("client-1");
+        let mut events = state.events.subscribe();
+
+        for i in 0..100 {
+            state.record_client_action("client-1", "type_text", i);
+        }
+        assert_eq!(published_client_lists(&mut events), 1);
+
+        // 見送った記録は最新の状態で1回だけ送信する
+        state.flush_client_activity();
+        state.flush_client_activity();
+        assert_eq!(published_client_lists(&mut events), 1);
+        assert_eq!(state.connected_clients["client-1"].last_action_at, Some(99));
+    }
+
+    #[test]
+    fn client_action_is_published_after_the_interval() {
+        let mut state = state_with_client("client-1");
+        let mut events = state.events.subscribe();
+
+        state.client_activity_published_at = Instant::now().checked_sub(CLIENT_ACTIVITY_PUBLISH_INTERVAL);
+        state.record_client_action("client-1", "click", 1);
+        assert_eq!(published_client_lists(&mut events), 1);
+        assert!(!state.client_activity_pending);
+    }
+
+    #[test]
+    fn closing_a_session_keeps_a_newer_registration() {
+        let mut state = state_with_client("client-1");
+        let old_session = state.connected_clients["client-1"].session_id;
+
+        // 切断後に同じクライアントIDで再接続した
+        state.remove_client("client-1");
+        state.touch_client("client-1", 1, &ClientMetadata::default());
+        state.remove_client_session("client-1", old_session);
+        assert!(state.connected_clients.contains_key("client-1"));
+
+        let new_session = state.connected_clients["client-1"].session_id;
+        state.remove_client_session("client-1", new_session);
+        assert!(!state.connected_clients.contains_key("client-1"));
+    }
+
+    #[test]
+    fn unknown_client_action_is_not_published() {
+        let mut state = ServerState::default();
+        let mut events = state.events.subscribe();
+
+        state.record_client_action("unknown", "click", 1);
+        state.flush_client_activity();
+        assert_eq!(published_client_lists(&mut events), 0);
+    }
+
+    #[test]
+    fn action_types_list_every_action_type_variant() {
+        // スキーマの `oneOf` はバリアントごとに1つあり、`type` の値を含む
+        let schema = serde_json::to_value(schemars::schema_for!(ActionType)).unwrap();
+        let mut variant_types: Vec<&str> = schema["oneOf"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|variant| variant["properties"]["type"]["enum"][0].as_str().unwrap())
+            .collect();
+        variant_types.sort_unstable();
+
+        let mut listed_types = ACTION_TYPES.to_vec();
+        listed_types.sort_unstable();
+        assert_eq!(variant_types, listed_types);
+    }
+}
@@ -0,0 +1,656 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+use rdev::{EventType, Key};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::backend::InputBackend;
+use crate::error::AppError;
+use crate::keyboard::string_to_key;
+use crate::snippets::{expand_snippet, validate_template};
+use crate::simulation::{
+    parse_mouse_button, send_copy, send_paste, simulate_mouse_click, simulate_mouse_drag, simulate_mouse_move,
+    simulate_mouse_move_to, simulate_mouse_scroll, type_text,
+};
+use crate::{AppState, CustomAction, RecordedKey, ShortcutType};
+
+/// 1つのカスタムアクションに保存できるステップ数の上限
+pub const MAX_MACRO_STEPS: usize = 1000;
+
+/// `wait` ステップで待機できる最大時間（ミリ秒）
+pub const MAX_WAIT_MS: u64 = 60_000;
+
+/// `invoke_action` で呼び出せるアクションの深さの上限（自身を呼び出す設定ミスで止まらないようにする）
+pub const MAX_INVOKE_DEPTH: usize = 8;
+
+/// キーイベント間の待機時間（ミリ秒、OSがイベントを取りこぼさないようにする）
+const KEY_EVENT_DELAY_MS: u64 = 20;
+
+/// 通常ショートカット（`ShortcutType::Normal`）のキー間の待機時間（ミリ秒）
+const NORMAL_SHORTCUT_INTERVAL_MS: u64 = 50;
+
+/// シーケンシャルショートカットで再現するキー間隔の上限（ミリ秒）
+const SEQUENTIAL_MAX_DELAY_MS: u64 = 100;
+
+/// シーケンシャルショートカットで再現するキー間隔の下限（これより短い間隔は省略する）
+const SEQUENTIAL_MIN_DELAY_MS: u64 = 5;
+
+/// `MacroStep` の `type` の値の一覧（`/capabilities` で公開、バリアントを追加したらここにも追加する）
+pub const MACRO_STEP_TYPES: &[&str] = &[
+    "text",
+    "snippet",
+    "wait",
+    "key_press",
+    "key_release",
+    "chord",
+    "mouse_move",
+    "mouse_move_to",
+    "mouse_click",
+    "mouse_drag",
+    "mouse_scroll",
+    "copy",
+    "paste",
+    "set_clipboard",
+    "invoke_action",
+];
+
+/// カスタムアクションの1ステップ
+///
+/// `type` フィールドでステップの種類を判別します。キー名は `/capabilities` の `keys` の値です。
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MacroStep {
+    /// テキストを入力する（日本語などはクリップボード経由）
+    Text { text: String },
+    /// テンプレートの変数（`{date}`, `{clipboard}` など）を実行時に展開してテキストを入力する
+    Snippet { template: String },
+    /// 指定時間待機する
+    Wait { ms: u64 },
+    /// キーを押す（離すまで押したまま）
+    KeyPress { key: String },
+    /// キーを離す
+    KeyRelease { key: String },
+    /// キーを順に押してから逆順に離す（例: `["MetaLeft", "ShiftLeft", "KeyT"]`）
+    Chord { keys: Vec<String> },
+    /// マウスカーソルを相対移動する（ピクセル）
+    MouseMove { dx: f64, dy: f64 },
+    /// マウスカーソルを絶対移動する（ピクセル）
+    MouseMoveTo { x: f64, y: f64 },
+    /// マウスボタンをクリックする
+    MouseClick {
+        button: Option<String>, // "left" (default), "right", "middle"
+        clicks: Option<u8>,     // 1 (default)〜3
+    },
+    /// マウスドラッグ
+    MouseDrag {
+        button: Option<String>,
+        dx: f64,
+        dy: f64,
+    },
+    /// スクロール
+    MouseScroll {
+        #[serde(default)]
+        dx: i64,
+        #[serde(default)]
+        dy: i64,
+    },
+    /// コピー（Cmd+C / Ctrl+C）
+    Copy,
+    /// ペースト（Cmd+V / Ctrl+V）
+    Paste,
+    /// クリップボードにテキストを設定する
+    SetClipboard { text: String },
+    /// 別のカスタムアクションを実行する
+    InvokeAction {
+        #[serde(rename = "actionId")]
+        action_id: String,
+    },
+}
+
+impl CustomAction {
+    /// 実行するステップ（`steps` が空の場合は録画した `key_sequence` から変換する）
+    pub fn effective_steps(&self) -> Vec<MacroStep> {
+        if self.steps.is_empty() {
+            legacy_steps(&self.key_sequence, &self.shortcut_type)
+        } else {
+            self.steps.clone()
+        }
+    }
+
+    /// `invoke_action` で `action_id` のアクションを直接呼び出しているか
+    pub fn invokes(&self, action_id: &str) -> bool {
+        self.steps
+            .iter()
+            .any(|step| matches!(step, MacroStep::InvokeAction { action_id: callee } if callee == action_id))
+    }
+}
+
+/// 録画したキーシーケンスをステップに変換する関数
+///
+/// `steps` がない従来のアクションの再生に使用します。
+/// 解釈できないキー名・イベント種類は従来の再生処理と同様に無視します。
+///
+/// # Arguments
+///
+/// * `key_sequence` - 録画したキーイベント
+/// * `shortcut_type` - `Normal` は押したキーを1つずつ押して離し、`Sequential` はpress/releaseと間隔を再現する
+///
+/// # Returns
+///
+/// * `Vec<MacroStep>` - 変換したステップ
+pub fn legacy_steps(key_sequence: &[RecordedKey], shortcut_type: &ShortcutType) -> Vec<MacroStep> {
+    let valid_keys = key_sequence
+        .iter()
+        .filter(|recorded_key| string_to_key(&recorded_key.key).is_some());
+
+    match shortcut_type {
+        ShortcutType::Normal => valid_keys
+            .filter(|recorded_key| recorded_key.event_type == "press")
+            .flat_map(|recorded_key| {
+                [
+                    MacroStep::Chord {
+                        keys: vec![recorded_key.key.clone()],
+                    },
+                    MacroStep::Wait {
+                        ms: NORMAL_SHORTCUT_INTERVAL_MS,
+                    },
+                ]
+            })
+            .collect(),
+        ShortcutType::Sequential => {
+            let mut steps = Vec::new();
+            let mut previous_timestamp: Option<u64> = None;
+
+            for recorded_key in valid_keys {
+                let step = match recorded_key.event_type.as_str() {
+                    "press" => MacroStep::KeyPress {
+                        key: recorded_key.key.clone(),
+                    },
+                    "release" => MacroStep::KeyRelease {
+                        key: recorded_key.key.clone(),
+                    },
+                    _ => continue,
+                };
+
+                // イベント間の実際の間隔を再現する（長すぎる間隔は切り詰める）
+                if let Some(previous_timestamp) = previous_timestamp {
+                    let delay = recorded_key
+                        .timestamp
+                        .saturating_sub(previous_timestamp)
+                        .min(SEQUENTIAL_MAX_DELAY_MS);
+                    if delay > SEQUENTIAL_MIN_DELAY_MS {
+                        steps.push(MacroStep::Wait { ms: delay });
+                    }
+                }
+                previous_timestamp = Some(recorded_key.timestamp);
+                steps.push(step);
+            }
+
+            steps
+        }
+    }
+}
+
+/// ステップを検証する関数
+///
+/// キー名・マウスボタン・クリック回数・待機時間を確認します。
+/// `invoke_action` の呼び出し先が存在するかは呼び出し元で確認してください。
+///
+/// # Arguments
+///
+/// * `steps` - 検証するステップ
+///
+/// # Returns
+///
+/// * `Ok(())` - 実行できるステップの場合
+/// * `Err(AppError::InvalidRequest)` - 不正なステップがある場合（最初の1つを報告）
+pub fn validate_steps(steps: &[MacroStep]) -> Result<(), AppError> {
+    if steps.len() > MAX_MACRO_STEPS {
+        return Err(AppError::InvalidRequest(format!(
+            "A custom action can have at most {} steps",
+            MAX_MACRO_STEPS
+        )));
+    }
+
+    let invalid = |index: usize, message: String| AppError::InvalidRequest(format!("Step {}: {}", index, message));
+    let check_key = |index: usize, key: &str| {
+        string_to_key(key)
+            .map(|_| ())
+            .ok_or_else(|| invalid(index, format!("unknown key '{}'", key)))
+    };
+
+    for (index, step) in steps.iter().enumerate() {
+        match step {
+            MacroStep::Snippet { template } => {
+                validate_template(template).map_err(|e| invalid(index, e))?;
+            }
+            MacroStep::Wait { ms } if *ms > MAX_WAIT_MS => {
+                return Err(invalid(index, format!("wait must be at most {} ms", MAX_WAIT_MS)));
+            }
+            MacroStep::KeyPress { key } | MacroStep::KeyRelease { key } => check_key(index, key)?,
+            MacroStep::Chord { keys } => {
+                if keys.is_empty() {
+                    return Err(invalid(index, "chord needs at least one key".to_string()));
+                }
+                for key in keys {
+                    check_key(index, key)?;
+                }
+            }
+            MacroStep::MouseClick { button, clicks } => {
+                parse_mouse_button(button.as_deref()).map_err(|e| invalid(index, e))?;
+                if !(1..=3).contains(&clicks.unwrap_or(1)) {
+                    return Err(invalid(index, "clicks must be between 1 and 3".to_string()));
+                }
+            }
+            MacroStep::MouseDrag { button, .. } => {
+                parse_mouse_button(button.as_deref()).map_err(|e| invalid(index, e))?;
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// `invoke_action` による呼び出し関係を検証する関数
+///
+/// `action_id` のステップを `steps` に変更した場合に、アクションが互いに呼び出し合わないこと、
+/// 呼び出しの深さ（呼び出し元を含むアクション数）が `MAX_INVOKE_DEPTH` 以下であることを確認します。
+/// 変更したアクションを呼び出している他のアクションも深さが変わるため、全てのアクションを確認します。
+/// 存在しないアクションの呼び出しはここでは無視します（新しいステップについては呼び出し元で確認してください）。
+///
+/// # Arguments
+///
+/// * `actions` - 現在のカスタムアクション
+/// * `action_id` - ステップを変更するアクションのID（新規作成の場合は作成するアクションのID）
+/// * `steps` - 変更後のステップ
+///
+/// # Returns
+///
+/// * `Ok(())` - 循環せず、深さも上限以下の場合
+/// * `Err(AppError::ActionCycle)` - 循環する場合（最初に見つかった循環を報告）
+/// * `Err(AppError::ActionNestingTooDeep)` - 深さが上限を超える場合
+pub fn validate_invocations(
+    actions: &HashMap<String, CustomAction>,
+    action_id: &str,
+    steps: &[MacroStep],
+) -> Result<(), AppError> {
+    let graph = InvocationGraph {
+        actions,
+        action_id,
+        steps,
+    };
+    let mut depths = HashMap::new();
+    let mut path = Vec::new();
+
+    for id in actions.keys().map(String::as_str).chain(std::iter::once(action_id)) {
+        graph.depth(id, &mut depths, &mut path)?;
+    }
+    Ok(())
+}
+
+// 変更後のステップで見た `invoke_action` の呼び出し関係
+struct InvocationGraph<'a> {
+    actions: &'a HashMap<String, CustomAction>,
+    action_id: &'a str,
+    steps: &'a [MacroStep],
+}
+
+impl<'a> InvocationGraph<'a> {
+    fn steps_of(&self, id: &str) -> Option<&'a [MacroStep]> {
+        if id == self.action_id {
+            Some(self.steps)
+        } else {
+            self.actions.get(id).map(|action| action.steps.as_slice())
+        }
+    }
+
+    // `id` から始まる呼び出しの深さを返す（`path` は現在たどっている呼び出し元、`depths` は確認済みの深さ）
+    fn depth(
+        &self,
+        id: &'a str,
+        depths: &mut HashMap<&'a str, usize>,
+        path: &mut Vec<&'a str>,
+    ) -> Result<usize, AppError> {
+        if let Some(depth) = depths.get(id) {
+            return Ok(*depth);
+        }
+        if let Some(start) = path.iter().position(|caller| *caller == id) {
+            let mut cycle: Vec<String> = path[start..].iter().map(|caller| caller.to_string()).collect();
+            cycle.push(id.to_string());
+            return Err(AppError::ActionCycle(cycle));
+        }
+        let Some(steps) = self.steps_of(id) else {
+            return Ok(0);
+        };
+
+        path.push(id);
+        let mut deepest_callee = 0;
+        for step in steps {
+            if let MacroStep::InvokeAction { action_id } = step {
+                deepest_callee = deepest_callee.max(self.depth(action_id, depths, path)?);
+            }
+        }
+        path.pop();
+
+        let depth = deepest_callee + 1;
+        if depth > MAX_INVOKE_DEPTH {
+            return Err(AppError::ActionNestingTooDeep {
+                max_depth: MAX_INVOKE_DEPTH,
+            });
+        }
+        depths.insert(id, depth);
+        Ok(depth)
+    }
+}
+
+/// カスタムアクションを実行する関数
+///
+/// `steps`（従来のアクションは `key_sequence` から変換したステップ）を先頭から順に実行します。
+/// 失敗した場合は残りのステップを中止します。`key_press` で押したまま `key_release` していない
+/// キーは、成功・失敗にかかわらず最後に離します（修飾キーが押されたままにならないようにする）。
+///
+/// # Arguments
+///
+/// * `state` - アプリケーション状態（入力バックエンドと `invoke_action` の呼び出し先の取得に使用）
+/// * `action` - 実行するカスタムアクション
+///
+/// # Returns
+///
+/// * `Ok(String)` - 成功時のメッセージ
+/// * `Err(AppError)` - 失敗したステップのエラー
+pub async fn run_custom_action(state: &AppState, action: &CustomAction) -> Result<String, AppError> {
+    let backend = Arc::clone(&state.lock()?.input_backend);
+
+    // 再生開始前に少し待機（スマートフォンの操作直後にキーが送られないようにする）
+    wait(&backend, 200).await?;
+
+    let steps = action.effective_steps();
+    let mut held_keys = Vec::new();
+    let result = run_steps(state, &backend, &steps, 0, &mut held_keys).await;
+
+    release_keys(&backend, &held_keys).await;
+    let executed = result?;
+
+    Ok(format!(
+        "Successfully executed custom action '{}' ({} steps)",
+        action.name, executed
+    ))
+}
+
+// ステップを順に実行し、実行したステップ数を返す（`invoke_action` から再帰的に呼び出す）
+fn run_steps<'a>(
+    state: &'a AppState,
+    backend: &'a Arc<dyn InputBackend>,
+    steps: &'a [MacroStep],
+    depth: usize,
+    held_keys: &'a mut Vec<Key>,
+) -> Pin<Box<dyn Future<Output = Result<usize, AppError>> + Send + 'a>> {
+    Box::pin(async move {
+        let mut executed = 0;
+
+        for step in steps {
+            match step {
+                MacroStep::InvokeAction { action_id } => {
+                    // 保存時に確認済みだが、手動で編集したファイルなどで循環していても止まるようにする
+                    if depth + 1 >= MAX_INVOKE_DEPTH {
+                        return Err(AppError::ActionNestingTooDeep {
+                            max_depth: MAX_INVOKE_DEPTH,
+                        });
+                    }
+                    let action = state
+                        .lock()?
+                        .custom_actions
+                        .get(action_id)
+                        .cloned()
+                        .ok_or_else(|| AppError::ActionNotFound(action_id.clone()))?;
+                    executed += run_steps(state, backend, &action.effective_steps(), depth + 1, held_keys).await?;
+                }
+                step => {
+                    run_step(backend, step, held_keys).await?;
+                    executed += 1;
+                }
+            }
+        }
+
+        Ok(executed)
+    })
+}
+
+// 1つのステップを実行する（`invoke_action` 以外）
+async fn run_step(
+    backend: &Arc<dyn InputBackend>,
+    step: &MacroStep,
+    held_keys: &mut Vec<Key>,
+) -> Result<(), AppError> {
+    match step {
+        MacroStep::Text { text } => type_step_text(backend, text).await?,
+        MacroStep::Snippet { template } => {
+            let text = expand_snippet(backend, template, &HashMap::new()).await?;
+            type_step_text(backend, &text).await?;
+        }
+        MacroStep::Wait { ms } => wait(backend, *ms).await?,
+        MacroStep::KeyPress { key } => {
+            let key = parse_key(key)?;
+            send_key_events(backend, vec![EventType::KeyPress(key)]).await?;
+            if !held_keys.contains(&key) {
+                held_keys.push(key);
+            }
+        }
+        MacroStep::KeyRelease { key } => {
+            let key = parse_key(key)?;
+            send_key_events(backend, vec![EventType::KeyRelease(key)]).await?;
+            held_keys.retain(|held| *held != key);
+        }
+        MacroStep::Chord { keys } => {
+            let keys = keys.iter().map(|key| parse_key(key)).collect::<Result<Vec<_>, _>>()?;
+            let events = keys
+                .iter()
+                .map(|key| EventType::KeyPress(*key))
+                .chain(keys.iter().rev().map(|key| EventType::KeyRelease(*key)))
+                .collect();
+            send_key_events(backend, events).await?;
+        }
+        MacroStep::MouseMove { dx, dy } => {
+            simulate_mouse_move(Arc::clone(backend), *dx, *dy).await.map_err(AppError::injection)?;
+        }
+        MacroStep::MouseMoveTo { x, y } => {
+            simulate_mouse_move_to(Arc::clone(backend), *x, *y).await.map_err(AppError::injection)?;
+        }
+        MacroStep::MouseClick { button, clicks } => {
+            let button = parse_mouse_button(button.as_deref()).map_err(AppError::InvalidRequest)?;
+            simulate_mouse_click(Arc::clone(backend), button, clicks.unwrap_or(1))
+                .await
+                .map_err(AppError::injection)?;
+        }
+        MacroStep::MouseDrag { button, dx, dy } => {
+            let button = parse_mouse_button(button.as_deref()).map_err(AppError::InvalidRequest)?;
+            simulate_mouse_drag(Arc::clone(backend), button, *dx, *dy).await.map_err(AppError::injection)?;
+        }
+        MacroStep::MouseScroll { dx, dy } => {
+            simulate_mouse_scroll(Arc::clone(backend), *dx, *dy).await.map_err(AppError::injection)?;
+        }
+        MacroStep::Copy => {
+            send_copy(Arc::clone(backend)).await.map_err(AppError::injection)?;
+        }
+        MacroStep::Paste => {
+            send_paste(Arc::clone(backend)).await.map_err(AppError::injection)?;
+        }
+        MacroStep::SetClipboard { text } => {
+            let (backend, text) = (Arc::clone(backend), text.clone());
+            tokio::task::spawn_blocking(move || backend.set_clipboard_text(&text))
+                .await
+                .map_err(|e| AppError::Internal(format!("Task error: {}", e)))?
+                .map_err(AppError::injection)?;
+        }
+        MacroStep::InvokeAction { action_id } => {
+            return Err(AppError::Internal(format!(
+                "invoke_action '{}' must be run by run_steps",
+                action_id
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+// テキストを入力する（入力できなかった文字があれば失敗として扱う）
+async fn type_step_text(backend: &Arc<dyn InputBackend>, text: &str) -> Result<(), AppError> {
+    let report = type_text(Arc::clone(backend), text.to_string()).await.map_err(AppError::injection)?;
+    if !report.undelivered.is_empty() {
+        return Err(AppError::InjectionFailed {
+            message: report.message(text),
+            undelivered: Some(report.undelivered),
+        });
+    }
+    Ok(())
+}
+
+fn parse_key(key: &str) -> Result<Key, AppError> {
+    string_to_key(key).ok_or_else(|| AppError::InvalidRequest(format!("Unknown key: {}", key)))
+}
+
+// キーイベントを順に送信する（各イベントの後に少し待機）
+async fn send_key_events(backend: &Arc<dyn InputBackend>, events: Vec<EventType>) -> Result<(), AppError> {
+    let backend = Arc::clone(backend);
+    tokio::task::spawn_blocking(move || {
+        for event in &events {
+            backend
+                .simulate(event)
+                .map_err(|e| format!("Failed to send {:?}: {:?}", event, e))?;
+            backend.sleep(Duration::from_millis(KEY_EVENT_DELAY_MS));
+        }
+        Ok::<(), String>(())
+    })
+    .await
+    .map_err(|e| AppError::Internal(format!("Task error: {}", e)))?
+    .map_err(AppError::injection)
+}
+
+// 押したままのキーを離す（実行後のクリーンアップ、エラーは無視する）
+async fn release_keys(backend: &Arc<dyn InputBackend>, keys: &[Key]) {
+    let events = keys.iter().rev().map(|key| EventType::KeyRelease(*key)).collect();
+    let _ = send_key_events(backend, events).await;
+}
+
+// 入力バックエンドで待機する（テスト用バックエンドでは待機しない）
+async fn wait(backend: &Arc<dyn InputBackend>, ms: u64) -> Result<(), AppError> {
+    let backend = Arc::clone(backend);
+    tokio::task::spawn_blocking(move || backend.sleep(Duration::from_millis(ms)))
+        .await
+        .map_err(|e| AppError::Internal(format!("Task error: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn invoke(action_id: &str) -> MacroStep {
+        MacroStep::InvokeAction {
+            action_id: action_id.to_string(),
+        }
+    }
+
+    fn action(id: &str, steps: Vec<MacroStep>) -> CustomAction {
+        CustomAction {
+            id: id.to_string(),
+            name: id.to_string(),
+            icon: None,
+            key_sequence: Vec::new(),
+            created_at: 0,
+            shortcut_type: ShortcutType::default(),
+            order: 0,
+            steps,
+        }
+    }
+
+    fn actions(list: Vec<CustomAction>) -> HashMap<String, CustomAction> {
+        list.into_iter().map(|action| (action.id.clone(), action)).collect()
+    }
+
+    // `action-1` から `action-{len}` まで順に呼び出し、最後のアクションはテキストを入力する
+    fn chain(len: usize) -> HashMap<String, CustomAction> {
+        actions(
+            (1..=len)
+                .map(|i| {
+                    let step = if i < len {
+                        invoke(&format!("action-{}", i + 1))
+                    } else {
+                        MacroStep::Text { text: "end".to_string() }
+                    };
+                    action(&format!("action-{}", i), vec![step])
+                })
+                .collect(),
+        )
+    }
+
+    fn assert_cycle(result: Result<(), AppError>, expected_len: usize) {
+        match result {
+            Err(AppError::ActionCycle(cycle)) => {
+                assert_eq!(cycle.len(), expected_len, "{:?}", cycle);
+                assert_eq!(cycle.first(), cycle.last(), "{:?}", cycle);
+            }
+            other => panic!("expected an action cycle, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn self_invocation_is_a_cycle() {
+        let actions = actions(vec![action("a", vec![MacroStep::Copy])]);
+
+        assert_cycle(validate_invocations(&actions, "a", &[invoke("a")]), 2);
+    }
+
+    #[test]
+    fn direct_cycle_is_rejected() {
+        let actions = actions(vec![action("a", vec![MacroStep::Copy]), action("b", vec![invoke("a")])]);
+
+        assert_cycle(validate_invocations(&actions, "a", &[invoke("b")]), 3);
+    }
+
+    #[test]
+    fn indirect_cycle_is_rejected() {
+        let actions = actions(vec![
+            action("a", vec![MacroStep::Copy]),
+            action("b", vec![invoke("c")]),
+            action("c", vec![invoke("a")]),
+        ]);
+
+        assert_cycle(validate_invocations(&actions, "a", &[invoke("b")]), 4);
+    }
+
+    #[test]
+    fn invoking_the_same_action_twice_is_not_a_cycle() {
+        let actions = actions(vec![action("a", vec![MacroStep::Copy]), action("b", vec![MacroStep::Paste])]);
+
+        assert!(validate_invocations(&actions, "a", &[invoke("b"), invoke("b")]).is_ok());
+    }
+
+    #[test]
+    fn nesting_up_to_the_maximum_depth_is_accepted() {
+        let actions = chain(MAX_INVOKE_DEPTH);
+
+        assert!(validate_invocations(&actions, "action-1", &actions["action-1"].steps).is_ok());
+    }
+
+    #[test]
+    fn nesting_beyond_the_maximum_depth_is_rejected() {
+        let actions = chain(MAX_INVOKE_DEPTH + 1);
+
+        assert!(matches!(
+            validate_invocations(&actions, "action-1", &actions["action-1"].steps),
+            Err(AppError::ActionNestingTooDeep { max_depth: MAX_INVOKE_DEPTH })
+        ));
+
+        // 既存の呼び出しの先頭に追加する場合も拒否する
+        let actions = chain(MAX_INVOKE_DEPTH);
+        assert!(matches!(
+            validate_invocations(&actions, "new", &[invoke("action-1")]),
+            Err(AppError::ActionNestingTooDeep { .. })
+        ));
+    }
+}
@@ -1,4 +1,16 @@
-use std::net::UdpSocket;
+use serde::Serialize;
+use std::net::{IpAddr, UdpSocket};
+
+/// ローカルのネットワークインターフェースとそのアドレス
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NetworkInterface {
+    pub name: String,
+    pub address: String,
+    pub is_ipv6: bool,
+    pub is_loopback: bool,
+    pub is_link_local: bool, // リンクローカルアドレス（IPv6はスコープIDが必要なため接続先候補から除外する）
+}
 
 /// ローカルIPアドレスを取得する関数
 ///
@@ -34,5 +46,57 @@ pub fn get_local_ip_address() -> Option<String> {
         }
     }
 
-    None
+    // インターネットに接続されていないLANでは、インターフェースのアドレスを使用
+    get_candidate_addresses(None).into_iter().next()
+}
+
+/// 全てのネットワークインターフェースのアドレスを取得する関数（IPv6を含む）
+///
+/// # Returns
+///
+/// * `Vec<NetworkInterface>` - インターフェースのアドレス一覧（取得に失敗した場合は空）
+pub fn list_network_interfaces() -> Vec<NetworkInterface> {
+    let interfaces = match if_addrs::get_if_addrs() {
+        Ok(interfaces) => interfaces,
+        Err(_) => return Vec::new(),
+    };
+
+    interfaces
+        .into_iter()
+        .map(|interface| NetworkInterface {
+            address: interface.ip().to_string(),
+            is_ipv6: interface.ip().is_ipv6(),
+            is_loopback: interface.is_loopback(),
+            is_link_local: interface.is_link_local(),
+            name: interface.name,
+        })
+        .collect()
+}
+
+/// モバイルアプリに提示する接続先アドレスの候補を取得する関数
+///
+/// 特定のアドレスで待ち受けている場合はそのアドレスのみを返します。
+/// 全インターフェースで待ち受けている場合は、ループバックとIPv6リンクローカルを除いた
+/// アドレスをIPv4を優先して返します（IPv6は待ち受けていないため含めません）。
+///
+/// # Arguments
+///
+/// * `bind_address` - 待ち受けアドレス（`None` の場合は全インターフェース）
+pub fn get_candidate_addresses(bind_address: Option<IpAddr>) -> Vec<String> {
+    if let Some(address) = bind_address {
+        return vec![address.to_string()];
+    }
+
+    list_network_interfaces()
+        .into_iter()
+        .filter(|interface| !interface.is_loopback && !interface.is_link_local && !interface.is_ipv6)
+        .map(|interface| interface.address)
+        .collect()
+}
+
+/// 指定されたアドレスがこのマシンのインターフェースに割り当てられているか確認する関数
+pub fn is_local_address(address: IpAddr) -> bool {
+    list_network_interfaces()
+        .iter()
+        .any(|interface| interface.address == address.to_string())
 }
@@ -0,0 +1,339 @@
+use axum::response::Json as JsonResponse;
+use lazy_static::lazy_static;
+use schemars::gen::{SchemaGenerator, SchemaSettings};
+use schemars::schema::Schema;
+use serde_json::{json, Map, Value};
+
+use crate::capabilities::Capabilities;
+use crate::custom_actions::{
+    CreateCustomActionRequest, EditKeySequenceRequest, ReorderCustomActionsRequest, ReplaceKeySequenceRequest,
+    SetMacroStepsRequest, UpdateCustomActionRequest,
+};
+use crate::error::AppError;
+use crate::events::ServerEvent;
+use crate::server::API_PREFIX;
+use crate::settings::AppSettings;
+use crate::websocket::{ActionResultMessage, WebSocketRequest};
+use crate::{
+    ApiResponse, AuthRequest, AuthResponse, CustomAction, HealthResponse, InputRequest,
+    RecordingStatusResponse, UpdateSettingsRequest,
+};
+
+// 生成したドキュメント（型とルートが変わらない限り同じ内容のため一度だけ生成する）
+lazy_static! {
+    static ref OPENAPI_SPEC: Value = openapi_spec();
+}
+
+/// ドキュメントに記載するエンドポイント
+///
+/// `server::api_endpoints` にルートを追加・変更した場合はここも更新してください
+/// （`tests/openapi.rs` が記載されたパスとルーティングされたパスが一致することを確認します）。
+struct Endpoint {
+    method: &'static str,
+    path: &'static str,
+    summary: &'static str,
+    public: bool,            // `auth::PUBLIC_PATHS` に含まれるか
+    client_id: Option<bool>, // `x-client-id` ヘッダー（`Some(true)` は必須）
+    request: Option<Schema>, // JSONボディ
+    response: Response,
+}
+
+/// 成功時のレスポンス
+enum Response {
+    Json(Schema),
+    EventStream(Schema), // 各イベントの `data`
+    WebSocket,
+}
+
+fn endpoints(gen: &mut SchemaGenerator) -> Vec<Endpoint> {
+    vec![
+        Endpoint {
+            method: "get",
+            path: "/health",
+            summary: "Check that the server is reachable and register the client as connected",
+            public: true,
+            client_id: Some(true),
+            request: None,
+            response: Response::Json(gen.subschema_for::<HealthResponse>()),
+        },
+        Endpoint {
+            method: "get",
+            path: "/capabilities",
+            summary: "Report the server version, supported actions and keys",
+            public: true,
+            client_id: None,
+            request: None,
+            response: Response::Json(gen.subschema_for::<Capabilities>()),
+        },
+        Endpoint {
+            method: "get",
+            path: "/openapi.json",
+            summary: "This document",
+            public: true,
+            client_id: None,
+            request: None,
+            response: Response::Json(gen.subschema_for::<Value>()),
+        },
+        Endpoint {
+            method: "post",
+            path: "/auth",
+            summary: "Verify the one-time password and, with x-client-id, pair the device and issue a token",
+            public: true,
+            client_id: Some(false),
+            request: Some(gen.subschema_for::<AuthRequest>()),
+            response: Response::Json(gen.subschema_for::<AuthResponse>()),
+        },
+        Endpoint {
+            method: "post",
+            path: "/input",
+            summary: "Execute an action (text input, shortcut, mouse, ...)",
+            public: false,
+            client_id: None,
+            request: Some(gen.subschema_for::<InputRequest>()),
+            response: Response::Json(gen.subschema_for::<ApiResponse>()),
+        },
+        Endpoint {
+            method: "get",
+            path: "/recording/status",
+            summary: "Get the shortcut recording status",
+            public: false,
+            client_id: None,
+            request: None,
+            response: Response::Json(gen.subschema_for::<RecordingStatusResponse>()),
+        },
+        Endpoint {
+            method: "get",
+            path: "/recording/events",
+            summary: "Server-Sent Events: recording_status on each state change, recorded_key for each key, recorded_key_warning for keys that cannot be recorded, and resync after missed events",
+            public: false,
+            client_id: None,
+            request: None,
+            response: Response::EventStream(gen.subschema_for::<ServerEvent>()),
+        },
+        Endpoint {
+            method: "post",
+            path: "/recording/acknowledge",
+            summary: "Clear a completed recording",
+            public: false,
+            client_id: None,
+            request: None,
+            response: Response::Json(gen.subschema_for::<ApiResponse>()),
+        },
+        Endpoint {
+            method: "get",
+            path: "/custom_actions",
+            summary: "List custom actions",
+            public: false,
+            client_id: None,
+            request: None,
+            response: Response::Json(gen.subschema_for::<Vec<CustomAction>>()),
+        },
+        Endpoint {
+            method: "post",
+            path: "/custom_actions",
+            summary: "Create a custom action from steps (text, waits, key chords, mouse, clipboard, other actions)",
+            public: false,
+            client_id: None,
+            request: Some(gen.subschema_for::<CreateCustomActionRequest>()),
+            response: Response::Json(gen.subschema_for::<CustomAction>()),
+        },
+        Endpoint {
+            method: "put",
+            path: "/custom_actions/order",
+            summary: "Set the display order of custom actions (unlisted actions keep their order after the listed ones)",
+            public: false,
+            client_id: None,
+            request: Some(gen.subschema_for::<ReorderCustomActionsRequest>()),
+            response: Response::Json(gen.subschema_for::<Vec<CustomAction>>()),
+        },
+        Endpoint {
+            method: "patch",
+            path: "/custom_actions/{id}",
+            summary: "Rename a custom action or change its icon (an empty icon removes it)",
+            public: false,
+            client_id: None,
+            request: Some(gen.subschema_for::<UpdateCustomActionRequest>()),
+            response: Response::Json(gen.subschema_for::<CustomAction>()),
+        },
+        Endpoint {
+            method: "delete",
+            path: "/custom_actions/{id}",
+            summary: "Delete a custom action",
+            public: false,
+            client_id: None,
+            request: None,
+            response: Response::Json(gen.subschema_for::<ApiResponse>()),
+        },
+        Endpoint {
+            method: "post",
+            path: "/custom_actions/{id}/duplicate",
+            summary: "Copy a custom action to the end of the list",
+            public: false,
+            client_id: None,
+            request: None,
+            response: Response::Json(gen.subschema_for::<CustomAction>()),
+        },
+        Endpoint {
+            method: "put",
+            path: "/custom_actions/{id}/key_sequence",
+            summary: "Replace the key sequence of a custom action (every key must be a name from /capabilities)",
+            public: false,
+            client_id: None,
+            request: Some(gen.subschema_for::<ReplaceKeySequenceRequest>()),
+            response: Response::Json(gen.subschema_for::<CustomAction>()),
+        },
+        Endpoint {
+            method: "patch",
+            path: "/custom_actions/{id}/key_sequence",
+            summary: "Insert, remove, move or edit steps of a key sequence; the edits apply together or not at all",
+            public: false,
+            client_id: None,
+            request: Some(gen.subschema_for::<EditKeySequenceRequest>()),
+            response: Response::Json(gen.subschema_for::<CustomAction>()),
+        },
+        Endpoint {
+            method: "put",
+            path: "/custom_actions/{id}/steps",
+            summary: "Replace the steps of a custom action; it then runs the steps instead of its recorded key sequence",
+            public: false,
+            client_id: None,
+            request: Some(gen.subschema_for::<SetMacroStepsRequest>()),
+            response: Response::Json(gen.subschema_for::<CustomAction>()),
+        },
+        Endpoint {
+            method: "get",
+            path: "/settings",
+            summary: "Get the settings",
+            public: false,
+            client_id: None,
+            request: None,
+            response: Response::Json(gen.subschema_for::<AppSettings>()),
+        },
+        Endpoint {
+            method: "post",
+            path: "/settings",
+            summary: "Update some of the settings (rateLimitPerMinute and allowPlainHttp can only be changed on the desktop)",
+            public: false,
+            client_id: None,
+            request: Some(gen.subschema_for::<UpdateSettingsRequest>()),
+            response: Response::Json(gen.subschema_for::<AppSettings>()),
+        },
+        Endpoint {
+            method: "get",
+            path: "/ws",
+            summary: "WebSocket: send WebSocketRequest, receive ActionResultMessage and ServerEvent",
+            public: false,
+            client_id: Some(false),
+            request: None,
+            response: Response::WebSocket,
+        },
+    ]
+}
+
+fn json_content(schema: &Schema) -> Value {
+    json!({ "application/json": { "schema": schema } })
+}
+
+fn operation(endpoint: &Endpoint, error: &Schema) -> Value {
+    let mut operation = Map::new();
+    operation.insert("summary".to_string(), json!(endpoint.summary));
+
+    // パスパラメータ（`{id}` など）
+    let mut parameters: Vec<Value> = endpoint
+        .path
+        .split('/')
+        .filter_map(|segment| segment.strip_prefix('{')?.strip_suffix('}'))
+        .map(|name| json!({ "name": name, "in": "path", "required": true, "schema": { "type": "string" } }))
+        .collect();
+    if let Some(required) = endpoint.client_id {
+        parameters.push(json!({
+            "name": "x-client-id",
+            "in": "header",
+            "required": required,
+            "schema": { "type": "string" },
+        }));
+    }
+    if !parameters.is_empty() {
+        operation.insert("parameters".to_string(), Value::Array(parameters));
+    }
+
+    if let Some(request) = &endpoint.request {
+        operation.insert(
+            "requestBody".to_string(),
+            json!({ "required": true, "content": json_content(request) }),
+        );
+    }
+
+    let success = match &endpoint.response {
+        Response::Json(schema) => json!({ "200": { "description": "OK", "content": json_content(schema) } }),
+        Response::EventStream(schema) => json!({
+            "200": {
+                "description": "OK",
+                "content": { "text/event-stream": { "schema": schema } },
+            }
+        }),
+        Response::WebSocket => json!({ "101": { "description": "Switching Protocols" } }),
+    };
+    let mut responses = success.as_object().cloned().unwrap_or_default();
+    responses.insert(
+        "default".to_string(),
+        json!({ "description": "Error (see `code`)", "content": json_content(error) }),
+    );
+    operation.insert("responses".to_string(), Value::Object(responses));
+
+    if !endpoint.public {
+        operation.insert(
+            "security".to_string(),
+            json!([{ "deviceToken": [], "clientId": [] }, { "password": [] }]),
+        );
+    }
+
+    Value::Object(operation)
+}
+
+/// HTTP API（`/api/v1`）のOpenAPIドキュメントを生成する関数
+///
+/// スキーマはリクエスト・レスポンスのRust型（`JsonSchema`）から生成します。
+/// WebSocketのメッセージ（`WebSocketRequest`・`ActionResultMessage`・`ServerEvent`）も
+/// `components.schemas` に含めます。
+pub fn openapi_spec() -> Value {
+    let mut gen = SchemaSettings::openapi3().into_generator();
+    let error = gen.subschema_for::<AppError>();
+    let endpoints = endpoints(&mut gen);
+    // WebSocketのメッセージは定義だけを登録する
+    gen.subschema_for::<WebSocketRequest>();
+    gen.subschema_for::<ActionResultMessage>();
+
+    let mut paths = Map::new();
+    for endpoint in &endpoints {
+        let path = paths
+            .entry(format!("{}{}", API_PREFIX, endpoint.path))
+            .or_insert_with(|| Value::Object(Map::new()));
+        if let Value::Object(path) = path {
+            path.insert(endpoint.method.to_string(), operation(endpoint, &error));
+        }
+    }
+
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "Side Assist Desktop API",
+            "version": env!("CARGO_PKG_VERSION"),
+            "description": "Endpoints are also served without the version prefix for older mobile app builds.",
+        },
+        "paths": paths,
+        "components": {
+            "schemas": gen.take_definitions(),
+            "securitySchemes": {
+                "deviceToken": { "type": "http", "scheme": "bearer" },
+                "clientId": { "type": "apiKey", "in": "header", "name": "x-client-id" },
+                "password": { "type": "apiKey", "in": "header", "name": "x-password" },
+            },
+        },
+    })
+}
+
+/// `/openapi.json` エンドポイント（認証不要）
+pub async fn get_openapi() -> JsonResponse<Value> {
+    JsonResponse(OPENAPI_SPEC.clone())
+}
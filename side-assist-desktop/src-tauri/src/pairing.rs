@@ -0,0 +1,205 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use axum::http::HeaderMap;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::clients::BannedClient;
+use crate::storage::get_app_data_dir;
+
+/// ペアリング済みデバイスの情報（ディスクに保存される）
+///
+/// トークン自体は保存せず、SHA-256ハッシュのみを保持します。
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PairedDevice {
+    pub client_id: String, // ペアリング時の x-client-id
+    pub device_name: Option<String>,
+    pub token_hash: String,
+    pub paired_at: u64,
+    pub last_used_at: Option<u64>,
+}
+
+/// デスクトップUIに返すペアリング済みデバイスの情報（トークンハッシュを含まない）
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PairedDeviceInfo {
+    pub client_id: String,
+    pub device_name: Option<String>,
+    pub paired_at: u64,
+    pub last_used_at: Option<u64>,
+}
+
+impl From<&PairedDevice> for PairedDeviceInfo {
+    fn from(device: &PairedDevice) -> Self {
+        Self {
+            client_id: device.client_id.clone(),
+            device_name: device.device_name.clone(),
+            paired_at: device.paired_at,
+            last_used_at: device.last_used_at,
+        }
+    }
+}
+
+/// ペアリング情報ファイルに保存する内容（キーはいずれもクライアントID）
+#[derive(Clone, Debug, Default)]
+pub struct PairingData {
+    pub devices: HashMap<String, PairedDevice>,
+    pub banned_clients: HashMap<String, BannedClient>, // アプリを再起動しても接続を拒否する
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct PairedDevicesStorage {
+    devices: Vec<PairedDevice>,
+    #[serde(default)] // バージョン1のファイルには含まれない
+    banned_clients: Vec<BannedClient>,
+    version: u32,
+    last_updated: u64,
+}
+
+/// 新しいデバイストークンを生成する関数
+///
+/// # Returns
+///
+/// * `String` - 256ビットの乱数をURLセーフなBase64で表現したトークン
+pub fn generate_device_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// トークンのSHA-256ハッシュ（16進文字列）を計算する関数
+pub fn hash_token(token: &str) -> String {
+    Sha256::digest(token.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// `Authorization: Bearer <token>` ヘッダーからトークンを取得する関数
+pub fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get("authorization")
+        .and_then(|h| h.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(|token| token.trim())
+}
+
+/// `x-client-id` ヘッダーからクライアントIDを取得する関数
+pub fn client_id_header(headers: &HeaderMap) -> Option<&str> {
+    headers.get("x-client-id").and_then(|h| h.to_str().ok())
+}
+
+/// ペアリング情報保存ファイルのパスを取得する関数
+///
+/// # Returns
+///
+/// * `Ok(PathBuf)` - ペアリング情報保存ファイルのパス
+/// * `Err(String)` - ディレクトリの取得または作成に失敗した場合のエラー
+pub fn get_paired_devices_file_path() -> Result<PathBuf, String> {
+    Ok(get_app_data_dir()?.join("paired_devices.json"))
+}
+
+/// ペアリング済みデバイスと接続を禁止したクライアントをファイルに保存する関数
+///
+/// # Arguments
+///
+/// * `data` - 保存するペアリング情報
+///
+/// # Returns
+///
+/// * `Ok(())` - 保存が成功した場合
+/// * `Err(String)` - 保存に失敗した場合のエラーメッセージ
+pub async fn save_paired_devices(data: &PairingData) -> Result<(), String> {
+    let file_path = get_paired_devices_file_path()?;
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|_| "Failed to get current time")?
+        .as_secs();
+
+    let storage = PairedDevicesStorage {
+        devices: data.devices.values().cloned().collect(),
+        banned_clients: data.banned_clients.values().cloned().collect(),
+        version: 2,
+        last_updated: now,
+    };
+
+    let json_content = serde_json::to_string_pretty(&storage)
+        .map_err(|e| format!("Failed to serialize paired devices: {}", e))?;
+
+    tokio::fs::write(&file_path, json_content).await
+        .map_err(|e| format!("Failed to write paired devices to file: {}", e))?;
+
+    Ok(())
+}
+
+/// ペアリング済みデバイスと接続を禁止したクライアントをファイルから読み込む関数
+///
+/// # Returns
+///
+/// * `Ok(PairingData)` - 読み込んだペアリング情報
+/// * `Err(String)` - 読み込みに失敗した場合のエラーメッセージ
+pub async fn load_paired_devices() -> Result<PairingData, String> {
+    let file_path = get_paired_devices_file_path()?;
+
+    if !file_path.exists() {
+        return Ok(PairingData::default());
+    }
+
+    let json_content = tokio::fs::read_to_string(&file_path).await
+        .map_err(|e| format!("Failed to read paired devices file: {}", e))?;
+
+    let storage: PairedDevicesStorage = serde_json::from_str(&json_content)
+        .map_err(|e| format!("Failed to parse paired devices file: {}", e))?;
+
+    Ok(PairingData {
+        devices: storage
+            .devices
+            .into_iter()
+            .map(|device| (device.client_id.clone(), device))
+            .collect(),
+        banned_clients: storage
+            .banned_clients
+            .into_iter()
+            .map(|client| (client.client_id.clone(), client))
+            .collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn version_1_file_loads_without_banned_clients() {
+        let json = r#"{"devices":[],"version":1,"last_updated":0}"#;
+        let storage: PairedDevicesStorage = serde_json::from_str(json).unwrap();
+        assert!(storage.banned_clients.is_empty());
+    }
+
+    #[test]
+    fn banned_clients_round_trip() {
+        let storage = PairedDevicesStorage {
+            devices: Vec::new(),
+            banned_clients: vec![BannedClient {
+                client_id: "client-1".to_string(),
+                device_name: Some("Phone".to_string()),
+                ip: Some("192.168.0.2".parse().unwrap()),
+                banned_at: 10,
+            }],
+            version: 2,
+            last_updated: 10,
+        };
+        let json = serde_json::to_string(&storage).unwrap();
+        let loaded: PairedDevicesStorage = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(loaded.banned_clients.len(), 1);
+        assert_eq!(loaded.banned_clients[0].client_id, "client-1");
+        assert_eq!(loaded.banned_clients[0].ip, storage.banned_clients[0].ip);
+    }
+}
@@ -0,0 +1,57 @@
+use std::io::Cursor;
+
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use image::{ImageFormat, Luma};
+use qrcode::render::{svg, unicode};
+use qrcode::QrCode;
+
+/// PNGの1モジュールあたりのピクセル数
+const PNG_MODULE_SIZE: u32 = 8;
+
+fn encode(data: &str) -> Result<QrCode, String> {
+    QrCode::new(data).map_err(|e| format!("QRコード生成エラー: {}", e))
+}
+
+/// QRコードをSVG文字列として生成する関数（デスクトップUI表示用）
+pub fn render_svg(data: &str) -> Result<String, String> {
+    let code = encode(data)?;
+
+    Ok(code
+        .render::<svg::Color>()
+        .min_dimensions(200, 200)
+        .dark_color(svg::Color("#000000"))
+        .light_color(svg::Color("#ffffff"))
+        .build())
+}
+
+/// QRコードをPNG画像のData URL（`data:image/png;base64,...`）として生成する関数
+///
+/// SVGを表示できない通知やクリップボードへのコピーに使用します。
+pub fn render_png_data_url(data: &str) -> Result<String, String> {
+    let code = encode(data)?;
+    let image = code
+        .render::<Luma<u8>>()
+        .module_dimensions(PNG_MODULE_SIZE, PNG_MODULE_SIZE)
+        .build();
+
+    let mut png = Cursor::new(Vec::new());
+    image
+        .write_to(&mut png, ImageFormat::Png)
+        .map_err(|e| format!("PNGエンコードエラー: {}", e))?;
+
+    Ok(format!("data:image/png;base64,{}", STANDARD.encode(png.into_inner())))
+}
+
+/// QRコードをターミナル表示用のテキストとして生成する関数
+///
+/// 上下2モジュールを1文字（`▀` `▄` `█`）で表現するため、等幅フォントでほぼ正方形に表示されます。
+/// 暗い背景でも読み取れるよう、背景を明色として描画します。
+pub fn render_terminal(data: &str) -> Result<String, String> {
+    let code = encode(data)?;
+
+    Ok(code
+        .render::<unicode::Dense1x2>()
+        .dark_color(unicode::Dense1x2::Light)
+        .light_color(unicode::Dense1x2::Dark)
+        .build())
+}
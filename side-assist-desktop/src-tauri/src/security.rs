@@ -0,0 +1,272 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+
+/// ロックアウトが始まるまでに許容する連続失敗回数（クライアントID・IPアドレスごと）
+pub const MAX_FAILURES_BEFORE_LOCKOUT: u32 = 5;
+
+/// 現在のワンタイムパスワードに対する失敗回数の上限（超えるとパスワードを無効化）
+pub const MAX_OTP_FAILURES: u32 = 10;
+
+/// 最初のロックアウト時間（以降は失敗ごとに倍増）
+const BASE_LOCKOUT: Duration = Duration::from_secs(30);
+
+/// ロックアウト時間の上限
+const MAX_LOCKOUT: Duration = Duration::from_secs(15 * 60);
+
+/// レート制限の集計期間
+const RATE_LIMIT_WINDOW: Duration = Duration::from_secs(60);
+
+/// 古い失敗記録を破棄するまでの時間
+const RECORD_RETENTION: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Clone, Debug)]
+struct FailureRecord {
+    count: u32,
+    last_failure: Instant,
+    locked_until: Option<Instant>,
+}
+
+impl FailureRecord {
+    fn new(now: Instant) -> Self {
+        Self {
+            count: 0,
+            last_failure: now,
+            locked_until: None,
+        }
+    }
+
+    // 失敗を記録し、ロックアウトが発生した場合はその時間を返す
+    fn record(&mut self, now: Instant) -> Option<Duration> {
+        self.count += 1;
+        self.last_failure = now;
+
+        if self.count < MAX_FAILURES_BEFORE_LOCKOUT {
+            return None;
+        }
+
+        // 上限を超えた回数に応じて指数的に延長する
+        let exponent = (self.count - MAX_FAILURES_BEFORE_LOCKOUT).min(16);
+        let lockout = BASE_LOCKOUT.saturating_mul(1 << exponent).min(MAX_LOCKOUT);
+        self.locked_until = Some(now + lockout);
+        Some(lockout)
+    }
+
+    fn remaining_lockout(&self, now: Instant) -> Option<Duration> {
+        self.locked_until
+            .filter(|until| *until > now)
+            .map(|until| until - now)
+    }
+}
+
+#[derive(Clone, Debug)]
+struct RequestWindow {
+    started: Instant,
+    count: u32,
+}
+
+/// 失敗した認証情報の種類
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CredentialKind {
+    /// ワンタイムパスワード
+    Password,
+    /// ペアリング済みデバイスのトークン
+    DeviceToken,
+}
+
+/// 認証失敗の記録結果
+#[derive(Clone, Debug, Default)]
+pub struct FailureOutcome {
+    /// 今回の失敗でロックアウトされた場合、その時間
+    pub locked_out: Option<Duration>,
+    /// 現在のワンタイムパスワードへの失敗回数
+    pub otp_failures: u32,
+    /// デバイストークンの失敗回数（ワンタイムパスワードの無効化には数えない）
+    pub token_failures: u32,
+    /// ワンタイムパスワードを無効化すべきかどうか
+    pub invalidate_otp: bool,
+}
+
+/// レート制限の判定結果
+#[derive(Clone, Debug, PartialEq)]
+pub enum RateLimitOutcome {
+    Allowed,
+    /// 今回のリクエストで初めて制限を超えた
+    Exceeded,
+    /// 既に制限を超えている
+    StillExceeded,
+}
+
+/// 認証試行とリクエスト頻度の記録
+///
+/// クライアントID（`x-client-id`）とIPアドレスのそれぞれで連続失敗回数を数え、
+/// 上限を超えた場合は指数バックオフでロックアウトします。
+/// ワンタイムパスワードの無効化はパスワードの失敗のみで判定し、失効したデバイストークンでの
+/// 再試行などではパスワードを無効化しません。
+#[derive(Clone, Debug, Default)]
+pub struct SecurityState {
+    failures_by_client: HashMap<String, FailureRecord>,
+    failures_by_ip: HashMap<IpAddr, FailureRecord>,
+    requests_by_ip: HashMap<IpAddr, RequestWindow>,
+    otp_failures: u32,
+    token_failures: u32,
+}
+
+impl SecurityState {
+    /// クライアントIDまたはIPアドレスがロックアウト中であれば残り時間を返す
+    pub fn lockout_remaining(&self, client_id: Option<&str>, ip: Option<IpAddr>) -> Option<Duration> {
+        let now = Instant::now();
+        let by_client = client_id
+            .and_then(|id| self.failures_by_client.get(id))
+            .and_then(|record| record.remaining_lockout(now));
+        let by_ip = ip
+            .and_then(|ip| self.failures_by_ip.get(&ip))
+            .and_then(|record| record.remaining_lockout(now));
+
+        by_client.max(by_ip)
+    }
+
+    /// 認証失敗を記録する
+    ///
+    /// # Arguments
+    ///
+    /// * `client_id` - リクエストのクライアントID
+    /// * `ip` - リクエスト元のIPアドレス
+    /// * `kind` - 失敗した認証情報の種類（ロックアウトはどちらも数える）
+    pub fn record_failure(&mut self, client_id: Option<&str>, ip: Option<IpAddr>, kind: CredentialKind) -> FailureOutcome {
+        let now = Instant::now();
+        let mut locked_out = None;
+
+        if let Some(client_id) = client_id {
+            let record = self
+                .failures_by_client
+                .entry(client_id.to_string())
+                .or_insert_with(|| FailureRecord::new(now));
+            locked_out = locked_out.max(record.record(now));
+        }
+        if let Some(ip) = ip {
+            let record = self
+                .failures_by_ip
+                .entry(ip)
+                .or_insert_with(|| FailureRecord::new(now));
+            locked_out = locked_out.max(record.record(now));
+        }
+
+        match kind {
+            CredentialKind::Password => self.otp_failures += 1,
+            CredentialKind::DeviceToken => self.token_failures += 1,
+        }
+        FailureOutcome {
+            locked_out,
+            otp_failures: self.otp_failures,
+            token_failures: self.token_failures,
+            invalidate_otp: kind == CredentialKind::Password && self.otp_failures >= MAX_OTP_FAILURES,
+        }
+    }
+
+    /// 認証成功時に連続失敗回数をリセットする
+    pub fn record_success(&mut self, client_id: Option<&str>, ip: Option<IpAddr>) {
+        if let Some(client_id) = client_id {
+            self.failures_by_client.remove(client_id);
+        }
+        if let Some(ip) = ip {
+            self.failures_by_ip.remove(&ip);
+        }
+    }
+
+    /// 新しいワンタイムパスワードの発行時に失敗回数をリセットする
+    pub fn reset_otp_failures(&mut self) {
+        self.otp_failures = 0;
+    }
+
+    /// IPアドレスごとのリクエスト数を数え、1分あたりの上限を超えていないか判定する
+    ///
+    /// # Arguments
+    ///
+    /// * `ip` - リクエスト元のIPアドレス（不明な場合は制限しない）
+    /// * `limit_per_minute` - 1分あたりの上限（設定で `MIN_RATE_LIMIT_PER_MINUTE` 以上に揃えられる）
+    pub fn check_rate_limit(&mut self, ip: Option<IpAddr>, limit_per_minute: u32) -> RateLimitOutcome {
+        let Some(ip) = ip else {
+            return RateLimitOutcome::Allowed;
+        };
+
+        let now = Instant::now();
+        let window = self.requests_by_ip.entry(ip).or_insert(RequestWindow {
+            started: now,
+            count: 0,
+        });
+        if now.duration_since(window.started) >= RATE_LIMIT_WINDOW {
+            window.started = now;
+            window.count = 0;
+        }
+
+        window.count = window.count.saturating_add(1);
+        if window.count <= limit_per_minute {
+            RateLimitOutcome::Allowed
+        } else if window.count == limit_per_minute.saturating_add(1) {
+            RateLimitOutcome::Exceeded
+        } else {
+            RateLimitOutcome::StillExceeded
+        }
+    }
+
+    /// 古い記録を破棄する（定期的に呼び出す）
+    pub fn prune(&mut self) {
+        let now = Instant::now();
+        let is_stale = |record: &FailureRecord| {
+            record.remaining_lockout(now).is_none() && now.duration_since(record.last_failure) > RECORD_RETENTION
+        };
+
+        self.failures_by_client.retain(|_, record| !is_stale(record));
+        self.failures_by_ip.retain(|_, record| !is_stale(record));
+        self.requests_by_ip
+            .retain(|_, window| now.duration_since(window.started) < RATE_LIMIT_WINDOW);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn device_token_failures_do_not_invalidate_the_otp() {
+        let mut security = SecurityState::default();
+
+        for _ in 0..MAX_OTP_FAILURES * 2 {
+            let outcome = security.record_failure(None, None, CredentialKind::DeviceToken);
+            assert!(!outcome.invalidate_otp);
+            assert_eq!(outcome.otp_failures, 0);
+        }
+
+        let outcome = security.record_failure(None, None, CredentialKind::Password);
+        assert_eq!(outcome.otp_failures, 1);
+        assert_eq!(outcome.token_failures, MAX_OTP_FAILURES * 2);
+        assert!(!outcome.invalidate_otp);
+    }
+
+    #[test]
+    fn password_failures_invalidate_the_otp_at_the_limit() {
+        let mut security = SecurityState::default();
+
+        for _ in 1..MAX_OTP_FAILURES {
+            assert!(!security.record_failure(None, None, CredentialKind::Password).invalidate_otp);
+        }
+        assert!(security.record_failure(None, None, CredentialKind::Password).invalidate_otp);
+
+        security.reset_otp_failures();
+        assert!(!security.record_failure(None, None, CredentialKind::Password).invalidate_otp);
+    }
+
+    #[test]
+    fn device_token_failures_still_lock_out_the_client() {
+        let mut security = SecurityState::default();
+
+        for _ in 1..MAX_FAILURES_BEFORE_LOCKOUT {
+            let outcome = security.record_failure(Some("phone"), None, CredentialKind::DeviceToken);
+            assert!(outcome.locked_out.is_none());
+        }
+        let outcome = security.record_failure(Some("phone"), None, CredentialKind::DeviceToken);
+        assert_eq!(outcome.locked_out, Some(BASE_LOCKOUT));
+        assert!(security.lockout_remaining(Some("phone"), None).is_some());
+    }
+}
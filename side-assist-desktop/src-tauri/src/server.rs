@@ -0,0 +1,627 @@
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use axum::{
+    handler::Handler,
+    http::Method,
+    middleware,
+    routing::{on, MethodFilter, MethodRouter},
+    Router,
+};
+use axum_server::{tls_rustls::RustlsConfig, Handle};
+use rand::Rng;
+use tokio::sync::{broadcast::error::RecvError, watch};
+use tokio_util::sync::CancellationToken;
+use tower_http::cors::CorsLayer;
+
+use crate::capabilities::get_capabilities;
+use crate::custom_actions::{
+    handle_create_custom_action, handle_delete_custom_action, handle_duplicate_custom_action,
+    handle_edit_key_sequence, handle_reorder_custom_actions, handle_replace_key_sequence,
+    handle_set_macro_steps, handle_update_custom_action,
+};
+use crate::discovery::{start_advertising, stop_advertising};
+use crate::error::AppError;
+use crate::network::{get_candidate_addresses, get_local_ip_address, is_local_address};
+use crate::openapi::get_openapi;
+use crate::pairing::load_paired_devices;
+use crate::events::ServerEvent;
+use crate::settings::{
+    get_current_settings, load_settings_persistent, update_settings_persistent, AppSettings,
+    MIN_HEARTBEAT_SWEEP_INTERVAL_SECS,
+};
+use crate::storage::load_custom_actions;
+use crate::tls::{PlainHttpFallbackAcceptor, TlsIdentity};
+use crate::{
+    acknowledge_recording, auth, get_custom_actions, get_recording_status, get_settings, handle_auth,
+    handle_input, health_check, sse, update_settings_endpoint, websocket, AppState,
+};
+
+pub use crate::auth::PUBLIC_PATHS;
+pub use crate::storage::set_app_data_dir;
+
+/// バージョン付きAPIのパスのプレフィックス（プレフィックスなしのパスも互換のため引き続き使用できる）
+pub const API_PREFIX: &str = "/api/v1";
+
+/// ワンタイムパスワードの標準の有効期間（秒）
+pub const DEFAULT_PASSWORD_TTL_SECS: u64 = 300;
+
+/// 停止時に処理中のリクエストの完了を待つ最大時間
+const GRACEFUL_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// `stop_server` がサーバータスクの終了を待つ最大時間
+const STOP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 実行中のサーバーを停止するためのハンドル
+#[derive(Clone, Debug)]
+pub struct ServerHandle {
+    shutdown: CancellationToken,
+    stopped: watch::Receiver<bool>, // 待ち受けソケットを閉じた後に `true` になる
+}
+
+impl ServerHandle {
+    /// サーバーの停止が要求された時に完了するトークン（WebSocket接続などの終了に使用）
+    pub fn shutdown_token(&self) -> CancellationToken {
+        self.shutdown.clone()
+    }
+}
+
+fn now_secs() -> Result<u64, String> {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .map_err(|e| format!("システム時刻の取得に失敗しました: {}", e))
+}
+
+/// 保存済みの設定・ペアリング済みデバイス・カスタムアクションを読み込む関数
+///
+/// 初回起動時はHTTPS/WSS用の自己署名証明書も生成します。
+/// 読み込みに失敗した項目は初期状態のまま続行します。
+pub async fn load_persistent_state(state: &AppState) {
+    // 設定を読み込み
+    if let Err(_e) = load_settings_persistent() {
+        // Error handling for load_settings_persistent failure
+    }
+
+    // ペアリング済みデバイスを読み込み
+    match load_paired_devices().await {
+        Ok(loaded) => {
+            if let Ok(mut state_guard) = state.lock() {
+                state_guard.paired_devices = loaded.devices;
+                state_guard.banned_clients = loaded.banned_clients;
+            }
+        }
+        Err(_e) => {
+            // Error handling for load_paired_devices failure
+        }
+    }
+
+    // カスタムアクションを読み込み
+    match load_custom_actions().await {
+        Ok(loaded_actions) => {
+            if let Ok(mut state_guard) = state.lock() {
+                state_guard.custom_actions = loaded_actions;
+                state_guard.publish_custom_actions();
+            }
+        }
+        Err(_e) => {
+            // Error handling for load_custom_actions failure
+        }
+    }
+
+    // 初回起動時にHTTPS/WSS用の自己署名証明書を生成しておく
+    if let Ok(mut state_guard) = state.lock() {
+        if let Err(_e) = state_guard.tls_identity() {
+            // Error handling for certificate generation failure
+        }
+    }
+}
+
+/// ポート番号を設定する関数（サーバー停止中のみ）
+pub fn set_port(state: &AppState, port: u16) -> Result<String, AppError> {
+    let mut state = state.lock()?;
+    
+    if state.operation_in_progress {
+        return Err(AppError::ServerBusy);
+    }
+    
+    if state.running {
+        return Err(AppError::ServerRunning);
+    }
+    
+    // ポート番号の妥当性チェック (u16の上限は65535なので上限チェックは不要)
+    if port < 1024 {
+        return Err(AppError::InvalidRequest("ポート番号は1024以上で指定してください。".to_string()));
+    }
+    
+    state.port = port;
+    
+    Ok(format!("Port set to {}", port))
+}
+
+/// 待ち受けアドレスを設定する関数（サーバー停止中のみ）
+///
+/// # Arguments
+///
+/// * `address` - このマシンのIPアドレス（`None`・空文字・`0.0.0.0` の場合は全インターフェース）
+pub fn set_bind_address(state: &AppState, address: Option<&str>) -> Result<String, AppError> {
+    let mut state = state.lock()?;
+    
+    if state.operation_in_progress {
+        return Err(AppError::ServerBusy);
+    }
+    
+    if state.running {
+        return Err(AppError::ServerRunning);
+    }
+    
+    // 未指定・未指定アドレス（0.0.0.0）の場合は全インターフェースで待ち受ける
+    let bind_address = match address.map(str::trim).filter(|address| !address.is_empty()) {
+        Some(address) => {
+            let address: IpAddr = address
+                .parse()
+                .map_err(|_| AppError::InvalidRequest(format!("無効なIPアドレスです: {}", address)))?;
+            if address.is_unspecified() {
+                None
+            } else if is_local_address(address) {
+                Some(address)
+            } else {
+                return Err(AppError::InvalidRequest(format!("このマシンのアドレスではありません: {}", address)));
+            }
+        }
+        None => None,
+    };
+    
+    state.bind_address = bind_address;
+    
+    Ok(match bind_address {
+        Some(address) => format!("Bind address set to {}", address),
+        None => "Bind address set to all interfaces".to_string(),
+    })
+}
+
+/// サーバーを起動する関数
+///
+/// 待ち受けソケットと証明書を準備した後、HTTPS/WSSサーバーと非アクティブなクライアントの
+/// 削除タスクをバックグラウンドで開始します。どちらのタスクも `stop_server` で停止します。
+///
+/// # Returns
+///
+/// * `Ok(SocketAddr)` - 実際に待ち受けているアドレス（ポート0を指定した場合は割り当てられたポート）
+/// * `Err(AppError)` - 既に起動中（`ServerRunning`）・操作中（`ServerBusy`）、またはポートが使用できない（`PortUnavailable`）場合
+pub async fn start_server(state: &AppState) -> Result<SocketAddr, AppError> {
+    let (bind_addr, identity) = {
+        let mut state = state.lock()?;
+        
+        if state.operation_in_progress {
+            return Err(AppError::ServerBusy);
+        }
+        
+        if state.running {
+            return Err(AppError::ServerRunning);
+        }
+        
+        state.operation_in_progress = true;
+        (state.bind_socket_addr(), state.tls_identity())
+    };
+
+    // マウスの相対移動で実際のカーソル位置を使用するため、入力イベントの監視を開始する
+    crate::start_input_listener(state);
+
+    let prepared = prepare_listener(bind_addr, identity).await;
+    let mut state_guard = state.lock()?;
+    state_guard.operation_in_progress = false;
+    let (listener, tls_config, fingerprint) = prepared?;
+    let local_addr = listener
+        .local_addr()
+        .map_err(|e| AppError::Internal(format!("Failed to get listening address: {}", e)))?;
+
+    // サーバーを実際に開始
+    let shutdown = CancellationToken::new();
+    let (stopped_sender, stopped) = watch::channel(false);
+    state_guard.running = true;
+    state_guard.server_handle = Some(ServerHandle {
+        shutdown: shutdown.clone(),
+        stopped,
+    });
+    drop(state_guard);
+
+    tokio::spawn(run_http_server(
+        Arc::clone(state),
+        listener,
+        tls_config,
+        fingerprint,
+        shutdown.clone(),
+        stopped_sender,
+    ));
+
+    // Start client cleanup task
+    tokio::spawn(cleanup_inactive_clients(Arc::clone(state), shutdown.clone()));
+    tokio::spawn(flush_client_activity(Arc::clone(state), shutdown));
+
+    Ok(local_addr)
+}
+
+// 待ち受けソケットとTLS設定を準備する（ポートが使用できない場合はここでエラーになる）
+async fn prepare_listener(
+    bind_addr: SocketAddr,
+    identity: Result<TlsIdentity, String>,
+) -> Result<(std::net::TcpListener, RustlsConfig, String), AppError> {
+    let identity = identity.map_err(AppError::Storage)?;
+    let tls_config = RustlsConfig::from_pem(identity.cert_pem.into_bytes(), identity.key_pem.into_bytes())
+        .await
+        .map_err(|e| AppError::Internal(format!("Failed to load TLS certificate: {}", e)))?;
+
+    let listener = std::net::TcpListener::bind(bind_addr).map_err(|e| AppError::PortUnavailable {
+        port: bind_addr.port(),
+        reason: e.to_string(),
+    })?;
+    listener
+        .set_nonblocking(true)
+        .map_err(|e| AppError::Internal(format!("Failed to configure listener: {}", e)))?;
+
+    Ok((listener, tls_config, identity.fingerprint))
+}
+
+/// サーバーを停止する関数
+///
+/// 処理中のリクエストの完了を待ってから（最大 `GRACEFUL_SHUTDOWN_TIMEOUT`）待ち受けソケットを閉じ、
+/// バックグラウンドタスクを停止します。戻った時点でポートは解放されています。
+/// 停止がタイムアウトした場合はサーバーを実行中のまま残すため、再度停止できます。
+pub async fn stop_server(state: &AppState) -> Result<String, AppError> {
+    let handle = {
+        let mut state_guard = state.lock()?;
+        
+        if state_guard.operation_in_progress {
+            return Err(AppError::ServerBusy);
+        }
+        
+        match state_guard.server_handle.take() {
+            Some(handle) => {
+                state_guard.operation_in_progress = true;
+                handle
+            }
+            None => return Ok("Server is not running".to_string()),
+        }
+    }; // MutexGuardはここで解放される
+    
+    handle.shutdown.cancel();
+    
+    // サーバータスクが待ち受けソケットを閉じるまで待機
+    // （タスクが異常終了して送信側が破棄された場合も停止済みとみなす）
+    let mut stopped = handle.stopped.clone();
+    let wait_result = tokio::time::timeout(STOP_TIMEOUT, stopped.wait_for(|stopped| *stopped)).await;
+    
+    // 操作完了フラグをクリア
+    {
+        let mut state_guard = state.lock()?;
+        state_guard.operation_in_progress = false;
+        
+        // タイムアウトしてもサーバータスクがまだ動いている場合はハンドルを戻し、再度停止できるようにする
+        // （タスクが終了していれば `running` は既にfalseになっている）
+        if wait_result.is_err() && state_guard.running && state_guard.server_handle.is_none() {
+            state_guard.server_handle = Some(handle);
+        }
+    } // MutexGuardはここで解放される
+    
+    match wait_result {
+        Ok(_) => Ok("Server stopped".to_string()),
+        Err(_) => Err(AppError::Internal("サーバーの停止がタイムアウトしました。".to_string())),
+    }
+}
+
+/// 新しい5桁のワンタイムパスワードを生成して設定する関数
+///
+/// # Arguments
+///
+/// * `ttl_secs` - 有効期間（秒）
+pub fn generate_one_time_password(state: &AppState, ttl_secs: u64) -> Result<String, AppError> {
+    let mut rng = rand::thread_rng();
+    let password: String = (0..5).map(|_| rng.gen_range(0..10).to_string()).collect();
+    
+    set_one_time_password(state, &password, ttl_secs)?;
+    
+    Ok(password)
+}
+
+/// 指定されたワンタイムパスワードを設定する関数（モバイルアプリと同じく5桁の数字のみ）
+///
+/// # Arguments
+///
+/// * `password` - 5桁の数字
+/// * `ttl_secs` - 有効期間（秒）
+pub fn set_one_time_password(state: &AppState, password: &str, ttl_secs: u64) -> Result<(), AppError> {
+    if password.len() != 5 || !password.chars().all(|ch| ch.is_ascii_digit()) {
+        return Err(AppError::InvalidRequest("ワンタイムパスワードは5桁の数字で指定してください。".to_string()));
+    }
+    
+    let expiry = now_secs()?.saturating_add(ttl_secs);
+    
+    let mut state = state.lock()?;
+    state.one_time_password = Some(password.to_string());
+    state.password_expiry = Some(expiry);
+    state.security.reset_otp_failures();
+    
+    Ok(())
+}
+
+/// デスクトップから設定を変更して保存する関数
+///
+/// HTTPの `/settings` と異なり、`DESKTOP_ONLY_SETTINGS`（レート制限など）も変更できます。
+///
+/// # Arguments
+///
+/// * `updates` - 変更する設定（`/settings` の `settings` と同じ形式、含まれない項目は変更しない）
+pub fn update_settings(state: &AppState, updates: serde_json::Value) -> Result<AppSettings, AppError> {
+    let state = state.lock()?;
+    let updated_settings = update_settings_persistent(updates).map_err(AppError::Storage)?;
+    state.publish(ServerEvent::SettingsChanged {
+        settings: updated_settings.clone(),
+    });
+    
+    Ok(updated_settings)
+}
+
+/// 有効なワンタイムパスワードを取得する関数（未生成・期限切れの場合は `None`）
+pub fn current_password(state: &AppState) -> Result<Option<String>, AppError> {
+    let state = state.lock()?;
+    let now = now_secs()?;
+    
+    match (&state.one_time_password, state.password_expiry) {
+        (Some(password), Some(expiry)) if now < expiry => Ok(Some(password.clone())),
+        _ => Ok(None),
+    }
+}
+
+/// QRコードに埋め込む `sideassist://connect` URLを生成する関数
+///
+/// # Returns
+///
+/// * `Ok(String)` - 接続先アドレス・ポート・ワンタイムパスワード・証明書フィンガープリントを含むURL
+/// * `Err(AppError)` - パスワードが未生成（`PasswordNotSet`）・期限切れ（`PasswordExpired`）、またはIPアドレスを取得できない場合
+pub fn connection_url(state: &AppState) -> Result<String, AppError> {
+    let mut state = state.lock()?;
+    
+    // パスワードが存在するかチェック
+    let password = match (&state.one_time_password, state.password_expiry) {
+        (Some(password), Some(expiry)) => {
+            if now_secs()? < expiry {
+                password.clone()
+            } else {
+                return Err(AppError::PasswordExpired);
+            }
+        }
+        _ => {
+            return Err(AppError::PasswordNotSet);
+        }
+    };
+    
+    // 接続先の候補（外部への経路で使われるアドレスを先頭にする）
+    let mut addresses = get_candidate_addresses(state.bind_address);
+    if state.bind_address.is_none() {
+        if let Some(preferred) = get_local_ip_address() {
+            addresses.retain(|address| *address != preferred);
+            addresses.insert(0, preferred);
+        }
+    }
+    
+    let local_ip = addresses.first().cloned().ok_or_else(|| {
+        AppError::Internal("ローカルIPアドレスを取得できませんでした。ネットワーク接続を確認してください。".to_string())
+    })?;
+    let port = state.port;
+    // モバイルアプリが自己署名証明書をピン留めするためのフィンガープリント
+    let fingerprint = state.tls_identity().map_err(AppError::Storage)?.fingerprint;
+    
+    // URLスキーム形式でデータを作成（改行や空白を確実に除去）
+    Ok(format!(
+        "sideassist://connect?ip={}&ips={}&port={}&password={}&fingerprint={}",
+        local_ip, addresses.join(","), port, password, fingerprint
+    )
+    .trim()
+    .replace('\n', "")
+    .replace('\r', "")
+    .replace(' ', ""))
+}
+
+/// HTTPサーバーのルーターを構築する
+///
+/// サーバーを起動せずにルーターだけを組み立てるため、結合テストから
+/// `RecordingBackend` と組み合わせてリクエストを直接送ることができます。
+/// 全てのエンドポイントは `API_PREFIX`（`/api/v1`）付きとプレフィックスなし（従来のモバイルアプリ用）の
+/// 両方で公開します。
+pub fn create_router(state: AppState) -> Router {
+    let routes = api_routes();
+    
+    Router::new()
+        .nest(API_PREFIX, routes.clone())
+        .merge(routes)
+        // PUBLIC_PATHS以外は全て認証必須（CORSのプリフライトは認証より先に処理される）
+        .layer(middleware::from_fn_with_state(Arc::clone(&state), auth::require_auth))
+        .layer(CorsLayer::permissive())
+        .with_state(state)
+}
+
+// バージョン間で共通のエンドポイント
+/// APIのルート一覧（メソッド・パス・ハンドラー）
+///
+/// `openapi::endpoints` に記載されていないルートは `tests/openapi.rs` で検出されます。
+fn api_endpoints() -> Vec<(Method, &'static str, MethodRouter<AppState>)> {
+    vec![
+        endpoint(Method::GET, "/health", health_check),
+        endpoint(Method::GET, "/capabilities", get_capabilities),
+        endpoint(Method::GET, "/openapi.json", get_openapi),
+        endpoint(Method::POST, "/input", handle_input),
+        endpoint(Method::POST, "/auth", handle_auth),
+        endpoint(Method::GET, "/recording/status", get_recording_status),
+        endpoint(Method::GET, "/recording/events", sse::handle_recording_events),
+        endpoint(Method::POST, "/recording/acknowledge", acknowledge_recording),
+        endpoint(Method::GET, "/custom_actions", get_custom_actions),
+        endpoint(Method::POST, "/custom_actions", handle_create_custom_action),
+        endpoint(Method::PUT, "/custom_actions/order", handle_reorder_custom_actions),
+        endpoint(Method::PATCH, "/custom_actions/:id", handle_update_custom_action),
+        endpoint(Method::DELETE, "/custom_actions/:id", handle_delete_custom_action),
+        endpoint(Method::POST, "/custom_actions/:id/duplicate", handle_duplicate_custom_action),
+        endpoint(Method::PUT, "/custom_actions/:id/key_sequence", handle_replace_key_sequence),
+        endpoint(Method::PATCH, "/custom_actions/:id/key_sequence", handle_edit_key_sequence),
+        endpoint(Method::PUT, "/custom_actions/:id/steps", handle_set_macro_steps),
+        endpoint(Method::GET, "/settings", get_settings),
+        endpoint(Method::POST, "/settings", update_settings_endpoint),
+        endpoint(Method::GET, "/ws", websocket::handle_websocket),
+    ]
+}
+
+fn endpoint<H, T>(method: Method, path: &'static str, handler: H) -> (Method, &'static str, MethodRouter<AppState>)
+where
+    H: Handler<T, AppState>,
+    T: 'static,
+{
+    let filter = MethodFilter::try_from(method.clone()).expect("unsupported HTTP method");
+    (method, path, on(filter, handler))
+}
+
+/// ルーティングされているAPIのメソッドとパスの一覧（`API_PREFIX` なし、パスパラメータは `:id` 形式）
+pub fn api_route_list() -> Vec<(Method, &'static str)> {
+    api_endpoints()
+        .into_iter()
+        .map(|(method, path, _)| (method, path))
+        .collect()
+}
+
+fn api_routes() -> Router<AppState> {
+    api_endpoints()
+        .into_iter()
+        .fold(Router::new(), |router, (_, path, handler)| router.route(path, handler))
+}
+
+// HTTPS/WSSサーバーを実行する
+//
+// `shutdown` がキャンセルされると新しい接続の受け付けを止め、処理中のリクエストの完了を
+// 待ってから終了します。終了時はmDNSの公開を取り消し、`stopped` で停止を通知します。
+async fn run_http_server(
+    state: AppState,
+    listener: std::net::TcpListener,
+    tls_config: RustlsConfig,
+    fingerprint: String,
+    shutdown: CancellationToken,
+    stopped: watch::Sender<bool>,
+) {
+    let bind_addr = listener.local_addr().ok();
+    let app = create_router(Arc::clone(&state));
+    
+    // 同じLAN内のモバイルアプリから見つけられるようにmDNSで公開する
+    if let Some(bind_addr) = bind_addr {
+        if let Err(_e) = start_advertising(bind_addr, &fingerprint) {
+            // mDNSが使えない環境でもQRコード・手動入力で接続できるため続行する
+        }
+    }
+    
+    let handle = Handle::new();
+    let shutdown_handle = handle.clone();
+    let shutdown_signal = shutdown.clone();
+    tokio::spawn(async move {
+        shutdown_signal.cancelled().await;
+        shutdown_handle.graceful_shutdown(Some(GRACEFUL_SHUTDOWN_TIMEOUT));
+    });
+    
+    // 通信内容（パスワードや入力テキスト）を保護するためTLSで待ち受ける
+    // （設定の `allowPlainHttp` が有効な間は、HTTPS未対応のモバイルアプリ用に同じポートで平文HTTPも受け付ける）
+    // 認証失敗・リクエスト数をIPアドレスごとに記録するため接続元を取得する
+    let result = axum_server::from_tcp(listener)
+        .acceptor(PlainHttpFallbackAcceptor::new(tls_config))
+        .handle(handle)
+        .serve(app.into_make_service_with_connect_info::<SocketAddr>())
+        .await;
+    if let Err(_e) = result {
+        // Error handling for server failure
+    }
+    
+    // サーバーが異常終了した場合もバックグラウンドタスクを止める
+    shutdown.cancel();
+    
+    // mDNSの公開を取り消す
+    let _ = tokio::task::spawn_blocking(stop_advertising).await;
+    
+    // 状態を更新
+    if let Ok(mut state_guard) = state.lock() {
+        state_guard.running = false;
+        state_guard.connected_clients.clear();
+        state_guard.publish_clients();
+        state_guard.server_handle = None;
+    }
+    
+    let _ = stopped.send(true);
+}
+
+/// ハートビートが途絶えたクライアントを定期的に削除する
+///
+/// 確認間隔とタイムアウトは `AppSettings` から毎回読み込むため、変更はサーバーを再起動せずに反映されます。
+async fn cleanup_inactive_clients(state: AppState, shutdown: CancellationToken) {
+    let mut events = match state.lock() {
+        Ok(state_guard) => state_guard.events.subscribe(),
+        Err(_) => return,
+    };
+    let mut last_sweep = tokio::time::Instant::now();
+    
+    loop {
+        let interval = Duration::from_secs(
+            get_current_settings()
+                .heartbeat_sweep_interval_secs
+                .max(MIN_HEARTBEAT_SWEEP_INTERVAL_SECS),
+        );
+        
+        tokio::select! {
+            _ = tokio::time::sleep_until(last_sweep + interval) => {}
+            event = events.recv() => {
+                if let Err(RecvError::Closed) = event {
+                    break;
+                }
+                // 設定の変更（SettingsChanged）を反映するため、確認間隔を読み直して待ち直す
+                continue;
+            }
+            _ = shutdown.cancelled() => break,
+        }
+        last_sweep = tokio::time::Instant::now();
+        
+        let now = match SystemTime::now().duration_since(UNIX_EPOCH) {
+            Ok(duration) => duration.as_secs(),
+            Err(_) => {
+                continue; // スキップして次のループ処理を継続
+            }
+        };
+        
+        if let Ok(mut state) = state.lock() {
+            let timeout = get_current_settings().heartbeat_timeout_secs;
+            let mut to_remove = Vec::new();
+            
+            for (client_id, client_info) in &state.connected_clients {
+                if now.saturating_sub(client_info.last_health_check) > timeout {
+                    to_remove.push(client_id.clone());
+                }
+            }
+            
+            for client_id in &to_remove {
+                state.time_out_client(client_id);
+            }
+            
+            state.security.prune();
+        }
+    }
+}
+
+/// 間引いたクライアントのアクションの記録を定期的に送信する
+async fn flush_client_activity(state: AppState, shutdown: CancellationToken) {
+    let mut interval = tokio::time::interval(crate::CLIENT_ACTIVITY_PUBLISH_INTERVAL);
+    
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {}
+            _ = shutdown.cancelled() => break,
+        }
+        
+        match state.lock() {
+            Ok(mut state_guard) => state_guard.flush_client_activity(),
+            Err(_) => break,
+        }
+    }
+}
@@ -1,20 +1,81 @@
 use lazy_static::lazy_static;
 use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::PathBuf;
 use std::sync::Mutex;
 
+use crate::snippets::is_variable_name;
+use crate::storage::get_app_data_dir;
+
 // 設定の構造体
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct AppSettings {
     #[serde(rename = "hapticsEnabled")]
     pub haptics_enabled: bool,
+    // 1つのIPアドレスから受け付ける1分あたりのリクエスト数（`MIN_RATE_LIMIT_PER_MINUTE` 以上）
+    #[serde(rename = "rateLimitPerMinute", default = "default_rate_limit_per_minute")]
+    pub rate_limit_per_minute: u32,
+    // 最後のヘルスチェックからこの秒数が経過したクライアントを切断扱いにする
+    #[serde(rename = "heartbeatTimeoutSecs", default = "default_heartbeat_timeout_secs")]
+    pub heartbeat_timeout_secs: u64,
+    // 非アクティブなクライアントを確認する間隔（秒）
+    #[serde(rename = "heartbeatSweepIntervalSecs", default = "default_heartbeat_sweep_interval_secs")]
+    pub heartbeat_sweep_interval_secs: u64,
+    // スニペットのユーザー定義変数（`{name}` で参照、同名の組み込み変数より優先）
+    #[serde(rename = "snippetVariables", default)]
+    pub snippet_variables: BTreeMap<String, String>,
+    // TLSに対応していないモバイルアプリのために同じポートで平文HTTPも受け付ける（移行期間中のみ、初期状態は無効）
+    #[serde(rename = "allowPlainHttp", default)]
+    pub allow_plain_http: bool,
+}
+
+/// 1分あたりのリクエスト数の下限（0などでレート制限を無効にできないようにする）
+pub const MIN_RATE_LIMIT_PER_MINUTE: u32 = 60;
+
+/// デスクトップでのみ変更できる設定（セキュリティに関わるため、HTTPの `/settings` では変更できない）
+pub const DESKTOP_ONLY_SETTINGS: &[&str] = &["rateLimitPerMinute", "allowPlainHttp"];
+
+/// ハートビートのタイムアウトの下限（WebSocket接続中の生存確認の間隔より長くする）
+pub const MIN_HEARTBEAT_TIMEOUT_SECS: u64 = 10;
+
+/// 非アクティブなクライアントを確認する間隔の下限
+pub const MIN_HEARTBEAT_SWEEP_INTERVAL_SECS: u64 = 1;
+
+fn default_rate_limit_per_minute() -> u32 {
+    600
+}
+
+fn default_heartbeat_timeout_secs() -> u64 {
+    15
+}
+
+fn default_heartbeat_sweep_interval_secs() -> u64 {
+    30
+}
+
+impl AppSettings {
+    // 下限を下回る値を下限に揃える（ファイルから読み込んだ値と変更後の値の両方に適用する）
+    fn clamped(mut self) -> Self {
+        self.rate_limit_per_minute = self.rate_limit_per_minute.max(MIN_RATE_LIMIT_PER_MINUTE);
+        self.heartbeat_timeout_secs = self.heartbeat_timeout_secs.max(MIN_HEARTBEAT_TIMEOUT_SECS);
+        self.heartbeat_sweep_interval_secs = self
+            .heartbeat_sweep_interval_secs
+            .max(MIN_HEARTBEAT_SWEEP_INTERVAL_SECS);
+        self
+    }
 }
 
 impl Default for AppSettings {
     fn default() -> Self {
         Self {
             haptics_enabled: true,
+            rate_limit_per_minute: default_rate_limit_per_minute(),
+            heartbeat_timeout_secs: default_heartbeat_timeout_secs(),
+            heartbeat_sweep_interval_secs: default_heartbeat_sweep_interval_secs(),
+            snippet_variables: BTreeMap::new(),
+            allow_plain_http: false,
         }
     }
 }
@@ -32,33 +93,20 @@ pub fn get_current_settings() -> AppSettings {
     }
 }
 
-// 独立したパス取得（storage.rsのパターンに合わせて）
+// 設定ファイルのパス（データディレクトリはカスタムアクションと共通）
 fn get_settings_file_path() -> Result<PathBuf, String> {
-    let app_data_dir = if cfg!(target_os = "macos") {
-        dirs::home_dir()
-            .ok_or("Failed to get home directory")?
-            .join("Library")
-            .join("Application Support")
-            .join("Side Assist Plus")
-    } else if cfg!(target_os = "windows") {
-        dirs::data_dir()
-            .ok_or("Failed to get data directory")?
-            .join("Side Assist Plus")
-    } else {
-        dirs::data_dir()
-            .ok_or("Failed to get data directory")?
-            .join("side-assist-plus")
-    };
-
-    if !app_data_dir.exists() {
-        fs::create_dir_all(&app_data_dir)
-            .map_err(|e| format!("Failed to create app data directory: {}", e))?;
-    }
+    Ok(get_app_data_dir()?.join("settings.json"))
+}
 
-    Ok(app_data_dir.join("settings.json"))
+/// HTTPの `/settings` から変更できない設定が含まれていれば、その名前を返す関数
+pub fn find_desktop_only_setting(updates: &serde_json::Value) -> Option<&'static str> {
+    DESKTOP_ONLY_SETTINGS
+        .iter()
+        .copied()
+        .find(|name| updates.get(name).is_some())
 }
 
-// 永続化対応の設定更新関数
+// 永続化対応の設定更新関数（デスクトップ専用の設定も変更できるため、HTTPからは `find_desktop_only_setting` で確認してから呼ぶ）
 pub fn update_settings_persistent(updates: serde_json::Value) -> Result<AppSettings, String> {
     let mut current_settings = get_current_settings();
 
@@ -66,6 +114,27 @@ pub fn update_settings_persistent(updates: serde_json::Value) -> Result<AppSetti
     if let Some(haptics_enabled) = updates.get("hapticsEnabled").and_then(|v| v.as_bool()) {
         current_settings.haptics_enabled = haptics_enabled;
     }
+    if let Some(rate_limit) = updates.get("rateLimitPerMinute").and_then(|v| v.as_u64()) {
+        current_settings.rate_limit_per_minute = rate_limit.min(u32::MAX as u64) as u32;
+    }
+    if let Some(timeout) = updates.get("heartbeatTimeoutSecs").and_then(|v| v.as_u64()) {
+        current_settings.heartbeat_timeout_secs = timeout;
+    }
+    if let Some(interval) = updates.get("heartbeatSweepIntervalSecs").and_then(|v| v.as_u64()) {
+        current_settings.heartbeat_sweep_interval_secs = interval;
+    }
+    if let Some(allow_plain_http) = updates.get("allowPlainHttp").and_then(|v| v.as_bool()) {
+        current_settings.allow_plain_http = allow_plain_http;
+    }
+    if let Some(variables) = updates.get("snippetVariables").and_then(|v| v.as_object()) {
+        // 変数全体を置き換える（変数名が不正な項目と値が文字列でない項目は無視）
+        current_settings.snippet_variables = variables
+            .iter()
+            .filter(|(name, _)| is_variable_name(name))
+            .filter_map(|(name, value)| value.as_str().map(|value| (name.clone(), value.to_string())))
+            .collect();
+    }
+    let current_settings = current_settings.clamped();
 
     // ファイルに保存
     let file_path = get_settings_file_path()?;
@@ -90,8 +159,9 @@ pub fn load_settings_persistent() -> Result<AppSettings, String> {
         let contents = fs::read_to_string(&file_path)
             .map_err(|e| format!("Failed to read settings file: {}", e))?;
 
-        let settings: AppSettings = serde_json::from_str(&contents)
-            .map_err(|e| format!("Failed to parse settings file: {}", e))?;
+        let settings: AppSettings = serde_json::from_str::<AppSettings>(&contents)
+            .map_err(|e| format!("Failed to parse settings file: {}", e))?
+            .clamped();
 
         // グローバル状態を更新
         if let Ok(mut global_settings) = SETTINGS.lock() {
@@ -117,3 +187,32 @@ pub fn load_settings_persistent() -> Result<AppSettings, String> {
         Ok(default_settings)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_below_the_minimum_are_raised() {
+        let settings = AppSettings {
+            rate_limit_per_minute: 0,
+            heartbeat_timeout_secs: 0,
+            heartbeat_sweep_interval_secs: 0,
+            ..AppSettings::default()
+        }
+        .clamped();
+
+        assert_eq!(settings.rate_limit_per_minute, MIN_RATE_LIMIT_PER_MINUTE);
+        assert_eq!(settings.heartbeat_timeout_secs, MIN_HEARTBEAT_TIMEOUT_SECS);
+        assert_eq!(settings.heartbeat_sweep_interval_secs, MIN_HEARTBEAT_SWEEP_INTERVAL_SECS);
+    }
+
+    #[test]
+    fn security_settings_are_desktop_only() {
+        let updates = serde_json::json!({ "hapticsEnabled": false, "rateLimitPerMinute": 0 });
+        assert_eq!(find_desktop_only_setting(&updates), Some("rateLimitPerMinute"));
+
+        let updates = serde_json::json!({ "hapticsEnabled": false });
+        assert_eq!(find_desktop_only_setting(&updates), None);
+    }
+}
@@ -1,16 +1,200 @@
-use crate::keyboard::char_to_key;
-use rdev::{simulate, EventType, SimulateError, Key};
-use std::{thread, time};
+use crate::backend::InputBackend;
+use crate::keyboard::char_to_keystroke;
+use crate::AppState;
+use rdev::{Button, EventType, SimulateError, Key};
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::sync::Arc;
+use std::time;
 
 /// キーイベント送信用のヘルパー関数
-fn send(event_type: &EventType) -> Result<(), SimulateError> {
-    let result = simulate(event_type);
+fn send(backend: &dyn InputBackend, event_type: &EventType) -> Result<(), SimulateError> {
+    backend.simulate(event_type)
+}
+
+// Tauriコマンドから呼び出す場合はアプリケーション状態の入力バックエンドを使用する
+fn state_backend(state: &AppState) -> Result<Arc<dyn InputBackend>, String> {
+    state
+        .lock()
+        .map(|state| Arc::clone(&state.input_backend))
+        .map_err(|e| format!("Failed to lock state: {}", e))
+}
+
+/// 入力できなかった文字の情報
+#[derive(Clone, Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UndeliveredChar {
+    pub index: usize, // テキスト内の文字位置（0始まり、char単位）
+    pub character: String,
+    pub reason: String,
+}
+
+/// テキスト入力の結果レポート
+#[derive(Clone, Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TypingReport {
+    pub typed_chars: usize,
+    pub undelivered: Vec<UndeliveredChar>,
+}
+
+impl TypingReport {
+    /// ユーザー向けの結果メッセージを生成する
+    pub fn message(&self, text: &str) -> String {
+        if self.undelivered.is_empty() {
+            format!("Successfully typed: {}", text)
+        } else {
+            let chars: String = self.undelivered.iter().map(|c| c.character.as_str()).collect();
+            format!(
+                "Typed {} characters, {} could not be delivered: {}",
+                self.typed_chars,
+                self.undelivered.len(),
+                chars
+            )
+        }
+    }
+}
+
+/// プラットフォーム別のショートカット用修飾キー（macOS: Command, その他: Ctrl）
+fn shortcut_modifier() -> Key {
+    #[cfg(target_os = "macos")]
+    return Key::MetaLeft;
+
+    #[cfg(not(target_os = "macos"))]
+    return Key::ControlLeft;
+}
+
+/// 1文字分のキーストロークを送信する（必要に応じてShiftで囲む）
+fn type_keystroke(backend: &dyn InputBackend, key: Key, shift: bool) -> Result<(), SimulateError> {
+    if shift {
+        send(backend, &EventType::KeyPress(Key::ShiftLeft))?;
+    }
+    let result = send(backend, &EventType::KeyPress(key)).and_then(|_| send(backend, &EventType::KeyRelease(key)));
+    if shift {
+        // 押下に失敗してもShiftが押しっぱなしにならないよう必ず離す
+        let _ = send(backend, &EventType::KeyRelease(Key::ShiftLeft));
+    }
     result
 }
 
+/// キーストロークで表現できない文字列をクリップボード経由で貼り付ける
+///
+/// 元のクリップボード内容（テキストのみ）は貼り付け後に復元します。
+fn paste_unicode(backend: &dyn InputBackend, segment: &str) -> Result<(), String> {
+    let previous = backend.get_clipboard_text().ok();
+
+    backend.set_clipboard_text(segment)?;
+    // OS側でクリップボードが反映されるまで待機
+    backend.sleep(time::Duration::from_millis(30));
+
+    let modifier = shortcut_modifier();
+    let result = send(backend, &EventType::KeyPress(modifier))
+        .and_then(|_| send(backend, &EventType::KeyPress(Key::KeyV)))
+        .and_then(|_| send(backend, &EventType::KeyRelease(Key::KeyV)));
+    let _ = send(backend, &EventType::KeyRelease(modifier));
+    result.map_err(|e| format!("Failed to paste text: {:?}", e))?;
+
+    // 貼り付けが完了する前に復元しないよう待機
+    backend.sleep(time::Duration::from_millis(100));
+    if let Some(previous) = previous {
+        let _ = backend.set_clipboard_text(&previous);
+    }
+
+    Ok(())
+}
+
+/// テキストを入力し、入力できなかった文字をレポートする（ブロッキング版）
+///
+/// キーボード配列に依存しない文字（`char_to_keystroke` で変換できる英数字など）はキーイベントとして送信し、
+/// それ以外の文字（日本語、絵文字、配列によって位置が異なる記号など）は連続する部分ごとに
+/// クリップボード経由で貼り付けます。
+/// "\r\n" は1回のEnterとして扱います。
+///
+/// # Arguments
+///
+/// * `backend` - 入力バックエンド
+/// * `text` - タイピングするテキスト
+///
+/// # Returns
+///
+/// * `TypingReport` - 入力できた文字数と入力できなかった文字の一覧
+pub fn type_text_blocking(backend: &dyn InputBackend, text: &str) -> TypingReport {
+    let mut report = TypingReport::default();
+    let mut pending: Vec<(usize, char)> = Vec::new();
+
+    fn flush(backend: &dyn InputBackend, pending: &mut Vec<(usize, char)>, report: &mut TypingReport) {
+        if pending.is_empty() {
+            return;
+        }
+        let segment: String = pending.iter().map(|(_, ch)| *ch).collect();
+        match paste_unicode(backend, &segment) {
+            Ok(()) => report.typed_chars += pending.len(),
+            Err(reason) => {
+                for (index, ch) in pending.iter() {
+                    report.undelivered.push(UndeliveredChar {
+                        index: *index,
+                        character: ch.to_string(),
+                        reason: reason.clone(),
+                    });
+                }
+            }
+        }
+        pending.clear();
+    }
+
+    let mut chars = text.chars().enumerate().peekable();
+    while let Some((index, ch)) = chars.next() {
+        // "\r\n" と単独の "\r" はどちらも改行として扱う
+        let ch = if ch == '\r' {
+            if matches!(chars.peek(), Some((_, '\n'))) {
+                report.typed_chars += 1;
+                continue;
+            }
+            '\n'
+        } else {
+            ch
+        };
+
+        match char_to_keystroke(ch) {
+            Some((key, shift)) => {
+                flush(backend, &mut pending, &mut report);
+                match type_keystroke(backend, key, shift) {
+                    Ok(()) => report.typed_chars += 1,
+                    Err(e) => report.undelivered.push(UndeliveredChar {
+                        index,
+                        character: ch.to_string(),
+                        reason: format!("Failed to simulate key: {:?}", e),
+                    }),
+                }
+            }
+            None => pending.push((index, ch)),
+        }
+    }
+    flush(backend, &mut pending, &mut report);
+
+    report
+}
+
+/// テキストを入力し、入力できなかった文字をレポートする
+///
+/// # Arguments
+///
+/// * `backend` - 入力バックエンド
+/// * `text` - タイピングするテキスト
+///
+/// # Returns
+///
+/// * `Ok(TypingReport)` - 入力結果のレポート
+/// * `Err(String)` - タスク実行エラー時のメッセージ
+pub async fn type_text(backend: Arc<dyn InputBackend>, text: String) -> Result<TypingReport, String> {
+    tokio::task::spawn_blocking(move || type_text_blocking(backend.as_ref(), &text))
+        .await
+        .map_err(|e| format!("Task error: {}", e))
+}
+
 /// テキストタイピングシミュレーション関数
 /// 
 /// 指定されたテキストを1文字ずつキーボード入力としてシミュレートします。
+/// 入力できなかった文字がある場合はその内容をメッセージに含めます。
 /// 
 /// # Arguments
 /// 
@@ -21,26 +205,9 @@ fn send(event_type: &EventType) -> Result<(), SimulateError> {
 /// * `Ok(String)` - 成功時のメッセージ
 /// * `Err(String)` - エラー時のメッセージ
 #[tauri::command]
-pub async fn simulate_typing(text: String) -> Result<String, String> {
-    
-    
-    tokio::task::spawn_blocking(move || {
-        // 文字列を1文字ずつシミュレート
-        for ch in text.chars() {
-            if let Some(key) = char_to_key(ch) {
-                
-                send(&EventType::KeyPress(key))
-                    .map_err(|_| format!("Failed to press key for character: {}", ch))?;
-                send(&EventType::KeyRelease(key))
-                    .map_err(|_| format!("Failed to release key for character: {}", ch))?;
-            } else {
-                
-            }
-        }
-        
-        
-        Ok(format!("Successfully typed: {}", text))
-    }).await.map_err(|e| format!("Task error: {}", e))?
+pub async fn simulate_typing(state: tauri::State<'_, AppState>, text: String) -> Result<String, String> {
+    let report = type_text(state_backend(&state)?, text.clone()).await?;
+    Ok(report.message(&text))
 }
 
 /// コピーコマンドシミュレーション関数
@@ -52,17 +219,20 @@ pub async fn simulate_typing(text: String) -> Result<String, String> {
 /// * `Ok(String)` - 成功時のメッセージ
 /// * `Err(String)` - エラー時のメッセージ
 #[tauri::command]
-pub async fn simulate_copy() -> Result<String, String> {
-    
-    
-    tokio::task::spawn_blocking(|| {
-        fn send_copy(event_type: &EventType) -> Result<(), SimulateError> {
+pub async fn simulate_copy(state: tauri::State<'_, AppState>) -> Result<String, String> {
+    send_copy(state_backend(&state)?).await
+}
+
+/// コピーのキーコンビネーション（Cmd+C/Ctrl+C）を指定したバックエンドで送信する
+pub async fn send_copy(backend: Arc<dyn InputBackend>) -> Result<String, String> {
+    tokio::task::spawn_blocking(move || {
+        let send_copy = |event_type: &EventType| -> Result<(), SimulateError> {
             let delay = time::Duration::from_millis(20);
-            let result = simulate(event_type);
+            let result = backend.simulate(event_type);
             // OS同期のための待機（特にmacOS）
-            thread::sleep(delay);
+            backend.sleep(delay);
             result
-        }
+        };
         
         
         
@@ -108,17 +278,20 @@ pub async fn simulate_copy() -> Result<String, String> {
 /// * `Ok(String)` - 成功時のメッセージ
 /// * `Err(String)` - エラー時のメッセージ
 #[tauri::command]
-pub async fn simulate_paste() -> Result<String, String> {
-    
-    
-    tokio::task::spawn_blocking(|| {
-        fn send_paste(event_type: &EventType) -> Result<(), SimulateError> {
+pub async fn simulate_paste(state: tauri::State<'_, AppState>) -> Result<String, String> {
+    send_paste(state_backend(&state)?).await
+}
+
+/// ペーストのキーコンビネーション（Cmd+V/Ctrl+V）を指定したバックエンドで送信する
+pub async fn send_paste(backend: Arc<dyn InputBackend>) -> Result<String, String> {
+    tokio::task::spawn_blocking(move || {
+        let send_paste = |event_type: &EventType| -> Result<(), SimulateError> {
             let delay = time::Duration::from_millis(20);
-            let result = simulate(event_type);
+            let result = backend.simulate(event_type);
             // OS同期のための待機（特にmacOS）
-            thread::sleep(delay);
+            backend.sleep(delay);
             result
-        }
+        };
         
         
         
@@ -153,4 +326,195 @@ pub async fn simulate_paste() -> Result<String, String> {
         #[cfg(not(target_os = "macos"))]
         return Ok("Successfully executed paste command (Ctrl+V) via rdev".to_string());
     }).await.map_err(|e| format!("Task error: {}", e))?
-}
\ No newline at end of file
+}
+
+/// マウスボタン名をrdev::Buttonに変換する関数
+///
+/// # Arguments
+///
+/// * `name` - ボタン名（"left", "right", "middle"）。省略時は左ボタン
+///
+/// # Returns
+///
+/// * `Ok(Button)` - 変換成功時のrdev::Button
+/// * `Err(String)` - サポートされていないボタン名の場合
+pub fn parse_mouse_button(name: Option<&str>) -> Result<Button, String> {
+    match name.unwrap_or("left") {
+        "left" => Ok(Button::Left),
+        "right" => Ok(Button::Right),
+        "middle" => Ok(Button::Middle),
+        other => Err(format!("Unknown mouse button: {}", other)),
+    }
+}
+
+/// 現在のカーソル位置を取得する（一度もマウスが動いていない場合は画面中央）
+fn current_cursor_position(backend: &dyn InputBackend) -> (f64, f64) {
+    if let Some(position) = backend.cursor_position() {
+        return position;
+    }
+
+    match backend.screen_size() {
+        Some((width, height)) => (width / 2.0, height / 2.0),
+        None => (0.0, 0.0),
+    }
+}
+
+/// カーソルを絶対座標に移動する（画面サイズが分かる場合は画面内に収める）
+fn move_cursor_to(backend: &dyn InputBackend, x: f64, y: f64) -> Result<(f64, f64), SimulateError> {
+    let (x, y) = match backend.screen_size() {
+        Some((width, height)) => (x.clamp(0.0, (width - 1.0).max(0.0)), y.clamp(0.0, (height - 1.0).max(0.0))),
+        None => (x.max(0.0), y.max(0.0)),
+    };
+
+    send(backend, &EventType::MouseMove { x, y })?;
+    Ok((x, y))
+}
+
+/// マウス相対移動シミュレーション関数
+///
+/// 現在のカーソル位置（ユーザーが物理マウスで動かした位置を含む）から指定量だけカーソルを移動します。
+///
+/// # Arguments
+///
+/// * `backend` - 入力バックエンド
+/// * `dx` - 横方向の移動量（ピクセル）
+/// * `dy` - 縦方向の移動量（ピクセル）
+///
+/// # Returns
+///
+/// * `Ok(String)` - 成功時のメッセージ
+/// * `Err(String)` - エラー時のメッセージ
+pub async fn simulate_mouse_move(backend: Arc<dyn InputBackend>, dx: f64, dy: f64) -> Result<String, String> {
+    tokio::task::spawn_blocking(move || {
+        let (x, y) = current_cursor_position(backend.as_ref());
+        let (x, y) = move_cursor_to(backend.as_ref(), x + dx, y + dy)
+            .map_err(|e| format!("Failed to move mouse: {:?}", e))?;
+
+        Ok(format!("Moved mouse to ({}, {})", x, y))
+    }).await.map_err(|e| format!("Task error: {}", e))?
+}
+
+/// マウス絶対移動シミュレーション関数
+///
+/// # Arguments
+///
+/// * `backend` - 入力バックエンド
+/// * `x` - 移動先のX座標（ピクセル）
+/// * `y` - 移動先のY座標（ピクセル）
+///
+/// # Returns
+///
+/// * `Ok(String)` - 成功時のメッセージ
+/// * `Err(String)` - エラー時のメッセージ
+pub async fn simulate_mouse_move_to(backend: Arc<dyn InputBackend>, x: f64, y: f64) -> Result<String, String> {
+    tokio::task::spawn_blocking(move || {
+        let (x, y) = move_cursor_to(backend.as_ref(), x, y)
+            .map_err(|e| format!("Failed to move mouse: {:?}", e))?;
+
+        Ok(format!("Moved mouse to ({}, {})", x, y))
+    }).await.map_err(|e| format!("Task error: {}", e))?
+}
+
+/// マウスクリックシミュレーション関数
+///
+/// 現在のカーソル位置で指定ボタンのクリックを行います。`clicks` に2を指定するとダブルクリックになります。
+///
+/// # Arguments
+///
+/// * `backend` - 入力バックエンド
+/// * `button` - クリックするボタン
+/// * `clicks` - クリック回数（1〜3）
+///
+/// # Returns
+///
+/// * `Ok(String)` - 成功時のメッセージ
+/// * `Err(String)` - エラー時のメッセージ
+pub async fn simulate_mouse_click(backend: Arc<dyn InputBackend>, button: Button, clicks: u8) -> Result<String, String> {
+    if !(1..=3).contains(&clicks) {
+        return Err(format!("Click count must be between 1 and 3: {}", clicks));
+    }
+
+    tokio::task::spawn_blocking(move || {
+        let backend = backend.as_ref();
+
+        for i in 0..clicks {
+            if i > 0 {
+                // OSのダブルクリック判定時間内に収まる間隔
+                backend.sleep(time::Duration::from_millis(50));
+            }
+            send(backend, &EventType::ButtonPress(button))
+                .map_err(|e| format!("Failed to press mouse button: {:?}", e))?;
+            backend.sleep(time::Duration::from_millis(20));
+            send(backend, &EventType::ButtonRelease(button))
+                .map_err(|e| format!("Failed to release mouse button: {:?}", e))?;
+        }
+
+        Ok(format!("Clicked {:?} button {} time(s)", button, clicks))
+    }).await.map_err(|e| format!("Task error: {}", e))?
+}
+
+/// マウスドラッグシミュレーション関数
+///
+/// 現在のカーソル位置でボタンを押し、指定量だけ段階的に移動してから離します。
+///
+/// # Arguments
+///
+/// * `backend` - 入力バックエンド
+/// * `button` - ドラッグに使用するボタン
+/// * `dx` - 横方向の移動量（ピクセル）
+/// * `dy` - 縦方向の移動量（ピクセル）
+///
+/// # Returns
+///
+/// * `Ok(String)` - 成功時のメッセージ
+/// * `Err(String)` - エラー時のメッセージ
+pub async fn simulate_mouse_drag(backend: Arc<dyn InputBackend>, button: Button, dx: f64, dy: f64) -> Result<String, String> {
+    const STEPS: u32 = 10;
+
+    tokio::task::spawn_blocking(move || {
+        let backend = backend.as_ref();
+        let (start_x, start_y) = current_cursor_position(backend);
+
+        send(backend, &EventType::ButtonPress(button))
+            .map_err(|e| format!("Failed to press mouse button: {:?}", e))?;
+
+        // アプリがドラッグとして認識できるよう、複数回に分けて移動
+        let mut move_result = Ok((start_x, start_y));
+        for step in 1..=STEPS {
+            backend.sleep(time::Duration::from_millis(15));
+            let progress = step as f64 / STEPS as f64;
+            move_result = move_cursor_to(backend, start_x + dx * progress, start_y + dy * progress);
+            if move_result.is_err() {
+                break;
+            }
+        }
+
+        // 移動に失敗してもボタンが押しっぱなしにならないよう必ず離す
+        let release_result = send(backend, &EventType::ButtonRelease(button));
+        let (x, y) = move_result.map_err(|e| format!("Failed to move mouse: {:?}", e))?;
+        release_result.map_err(|e| format!("Failed to release mouse button: {:?}", e))?;
+
+        Ok(format!("Dragged with {:?} button to ({}, {})", button, x, y))
+    }).await.map_err(|e| format!("Task error: {}", e))?
+}
+
+/// マウススクロールシミュレーション関数
+///
+/// # Arguments
+///
+/// * `backend` - 入力バックエンド
+/// * `delta_x` - 横方向のスクロール量（rdevのWheelイベントにそのまま渡す）
+/// * `delta_y` - 縦方向のスクロール量（rdevのWheelイベントにそのまま渡す）
+///
+/// # Returns
+///
+/// * `Ok(String)` - 成功時のメッセージ
+/// * `Err(String)` - エラー時のメッセージ
+pub async fn simulate_mouse_scroll(backend: Arc<dyn InputBackend>, delta_x: i64, delta_y: i64) -> Result<String, String> {
+    tokio::task::spawn_blocking(move || {
+        send(backend.as_ref(), &EventType::Wheel { delta_x, delta_y })
+            .map_err(|e| format!("Failed to scroll: {:?}", e))?;
+
+        Ok(format!("Scrolled ({}, {})", delta_x, delta_y))
+    }).await.map_err(|e| format!("Task error: {}", e))?
+}
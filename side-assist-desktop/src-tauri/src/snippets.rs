@@ -0,0 +1,260 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use chrono::Local;
+use uuid::Uuid;
+
+use crate::backend::InputBackend;
+use crate::error::AppError;
+use crate::settings::get_current_settings;
+
+/// 組み込み変数の一覧（`/capabilities` で公開）
+pub const BUILTIN_SNIPPET_VARIABLES: &[&str] = &["date", "time", "datetime", "clipboard", "uuid"];
+
+/// テンプレートの構成要素
+enum Part<'a> {
+    Literal(String),
+    Variable(&'a str),
+}
+
+/// 変数名として使える文字列か（英数字と `_` のみ）
+pub fn is_variable_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+// テンプレートを文字列と変数に分割する（`{{` / `}}` は `{` / `}` そのものとして扱う）
+fn parse_template(template: &str) -> Result<Vec<Part<'_>>, String> {
+    let mut parts = Vec::new();
+    let mut literal = String::new();
+    let mut chars = template.char_indices().peekable();
+
+    while let Some((position, c)) = chars.next() {
+        match c {
+            '{' if chars.peek().map(|(_, next)| *next) == Some('{') => {
+                chars.next();
+                literal.push('{');
+            }
+            '}' if chars.peek().map(|(_, next)| *next) == Some('}') => {
+                chars.next();
+                literal.push('}');
+            }
+            '{' => {
+                let rest = &template[position + 1..];
+                let end = rest
+                    .find('}')
+                    .ok_or_else(|| format!("Unclosed '{{' at position {}", position))?;
+                let name = &rest[..end];
+                if !is_variable_name(name) {
+                    return Err(format!(
+                        "Invalid variable name '{}' at position {} (use letters, digits and '_', or '{{{{' for a literal brace)",
+                        name, position
+                    ));
+                }
+
+                if !literal.is_empty() {
+                    parts.push(Part::Literal(std::mem::take(&mut literal)));
+                }
+                parts.push(Part::Variable(name));
+
+                // 変数名と `}` を読み飛ばす
+                while chars.next_if(|(index, _)| *index <= position + 1 + end).is_some() {}
+            }
+            '}' => {
+                return Err(format!(
+                    "Unexpected '}}' at position {} (use '}}}}' for a literal brace)",
+                    position
+                ));
+            }
+            c => literal.push(c),
+        }
+    }
+
+    if !literal.is_empty() {
+        parts.push(Part::Literal(literal));
+    }
+    Ok(parts)
+}
+
+/// スニペットのテンプレートを検証する関数
+///
+/// 括弧の対応と変数名のみを確認します（ユーザー定義変数は実行時まで変更できるため、
+/// 変数が定義されているかは確認しません）。
+///
+/// # Arguments
+///
+/// * `template` - 検証するテンプレート
+///
+/// # Returns
+///
+/// * `Ok(())` - 展開できるテンプレートの場合
+/// * `Err(String)` - 構文エラーの内容
+pub fn validate_template(template: &str) -> Result<(), String> {
+    parse_template(template).map(|_| ())
+}
+
+/// スニペットのテンプレートを展開する関数
+///
+/// `{name}` を変数の値に置き換えます。変数は `variables`（呼び出し時に指定した値）、
+/// 設定の `snippetVariables`（ユーザー定義変数）、組み込み変数の順に探します。
+/// 組み込み変数は `{date}`, `{time}`, `{datetime}`（ローカル時刻）、`{clipboard}`（現在のクリップボード）、
+/// `{uuid}`（ランダムなUUID）です。
+///
+/// # Arguments
+///
+/// * `backend` - `{clipboard}` の読み取りに使用する入力バックエンド
+/// * `template` - 展開するテンプレート
+/// * `variables` - 呼び出し時に指定した変数
+///
+/// # Returns
+///
+/// * `Ok(String)` - 展開したテキスト
+/// * `Err(AppError::InvalidRequest)` - 構文エラー、または未定義の変数がある場合
+/// * `Err(AppError::InjectionFailed)` - クリップボードを読み取れなかった場合
+pub async fn expand_snippet(
+    backend: &Arc<dyn InputBackend>,
+    template: &str,
+    variables: &HashMap<String, String>,
+) -> Result<String, AppError> {
+    let user_variables = get_current_settings().snippet_variables;
+    expand_template(backend, template, variables, &user_variables).await
+}
+
+// `expand_snippet` の本体（ユーザー定義変数を設定から読み込まずに受け取る）
+async fn expand_template(
+    backend: &Arc<dyn InputBackend>,
+    template: &str,
+    variables: &HashMap<String, String>,
+    user_variables: &BTreeMap<String, String>,
+) -> Result<String, AppError> {
+    let parts = parse_template(template).map_err(AppError::InvalidRequest)?;
+    let now = Local::now();
+
+    let mut expanded = String::new();
+    for part in parts {
+        match part {
+            Part::Literal(text) => expanded.push_str(&text),
+            Part::Variable(name) => {
+                if let Some(value) = variables.get(name).or_else(|| user_variables.get(name)) {
+                    expanded.push_str(value);
+                    continue;
+                }
+                match name {
+                    "date" => expanded.push_str(&now.format("%Y-%m-%d").to_string()),
+                    "time" => expanded.push_str(&now.format("%H:%M").to_string()),
+                    "datetime" => expanded.push_str(&now.format("%Y-%m-%d %H:%M").to_string()),
+                    "clipboard" => expanded.push_str(&read_clipboard(backend).await?),
+                    "uuid" => expanded.push_str(&Uuid::new_v4().to_string()),
+                    _ => {
+                        return Err(AppError::InvalidRequest(format!(
+                            "Unknown snippet variable '{}'",
+                            name
+                        )))
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(expanded)
+}
+
+async fn read_clipboard(backend: &Arc<dyn InputBackend>) -> Result<String, AppError> {
+    let backend = Arc::clone(backend);
+    tokio::task::spawn_blocking(move || backend.get_clipboard_text())
+        .await
+        .map_err(|e| AppError::Internal(format!("Task error: {}", e)))?
+        .map_err(AppError::injection)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::RecordingBackend;
+
+    // 構成要素を比較しやすい文字列にする（変数は `$name`）
+    fn parts(template: &str) -> Result<Vec<String>, String> {
+        Ok(parse_template(template)?
+            .into_iter()
+            .map(|part| match part {
+                Part::Literal(text) => text,
+                Part::Variable(name) => format!("${}", name),
+            })
+            .collect())
+    }
+
+    fn variables(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect()
+    }
+
+    #[test]
+    fn doubled_braces_are_literal_braces() {
+        assert_eq!(parts("{{a}} }}").unwrap(), vec!["{a} }"]);
+        assert_eq!(parts("{{date}}").unwrap(), vec!["{date}"]);
+        assert_eq!(parts("{{{date}").unwrap(), vec!["{", "$date"]);
+    }
+
+    #[test]
+    fn stray_closing_brace_is_rejected() {
+        let error = parts("{date}}").unwrap_err();
+        assert!(error.starts_with("Unexpected '}' at position 6"), "{}", error);
+
+        assert!(parts("a } b").is_err());
+    }
+
+    #[test]
+    fn unclosed_brace_is_rejected() {
+        let error = parts("Hello {name").unwrap_err();
+        assert!(error.starts_with("Unclosed '{' at position 6"), "{}", error);
+    }
+
+    #[test]
+    fn invalid_variable_names_are_rejected() {
+        for template in ["{}", "{my name}", "{a-b}", "{日付}", "{{{x y}"] {
+            let error = parts(template).unwrap_err();
+            assert!(error.starts_with("Invalid variable name"), "{}: {}", template, error);
+        }
+    }
+
+    #[test]
+    fn multibyte_text_around_variables_is_kept() {
+        assert_eq!(
+            parts("こんにちは{name}さん、{date}です").unwrap(),
+            vec!["こんにちは", "$name", "さん、", "$date", "です"]
+        );
+        assert_eq!(parts("{name}🎉{{絵文字}}").unwrap(), vec!["$name", "🎉{絵文字}"]);
+    }
+
+    #[tokio::test]
+    async fn call_time_variables_take_precedence_over_user_and_builtin_variables() {
+        let recorder = Arc::new(RecordingBackend::new());
+        recorder.set_clipboard_text("クリップボード").unwrap();
+        let backend: Arc<dyn InputBackend> = recorder;
+        let user_variables: BTreeMap<String, String> = [("name", "user"), ("date", "user date")]
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+
+        let expanded = expand_template(
+            &backend,
+            "{name} / {date} / {clipboard}",
+            &variables(&[("name", "call")]),
+            &user_variables,
+        )
+        .await
+        .unwrap();
+        assert_eq!(expanded, "call / user date / クリップボード");
+
+        let expanded = expand_template(&backend, "{clipboard}", &variables(&[("clipboard", "call")]), &user_variables)
+            .await
+            .unwrap();
+        assert_eq!(expanded, "call");
+    }
+
+    #[tokio::test]
+    async fn unknown_variables_are_rejected_when_expanding() {
+        let backend: Arc<dyn InputBackend> = Arc::new(RecordingBackend::new());
+
+        let result = expand_template(&backend, "{missing}", &HashMap::new(), &BTreeMap::new()).await;
+        assert!(matches!(result, Err(AppError::InvalidRequest(_))));
+    }
+}
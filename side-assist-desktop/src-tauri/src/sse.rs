@@ -0,0 +1,111 @@
+use std::collections::VecDeque;
+use std::convert::Infallible;
+use std::time::Duration;
+
+use axum::{
+    extract::State,
+    response::sse::{Event, KeepAlive, Sse},
+};
+use futures::stream::{self, Stream};
+use tokio::sync::broadcast::{error::RecvError, Receiver};
+use tokio_util::sync::CancellationToken;
+
+use crate::error::AppError;
+use crate::events::ServerEvent;
+use crate::{recording_status_response, AppState};
+
+/// 接続を維持するためのコメントを送信する間隔
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// `/recording/events` のストリームの状態
+struct RecordingEvents {
+    state: AppState,
+    events: Receiver<ServerEvent>,
+    shutdown: CancellationToken,
+    pending: VecDeque<ServerEvent>, // 次に送信するイベント
+}
+
+/// `/recording/events` エンドポイント（Server-Sent Events）
+///
+/// 接続直後に現在の録画状態（`recording_status`）を送信し、以降は録画状態の変化と
+/// 記録された各キー（`recorded_key`）を送信します。`/recording/status` のポーリングの代わりに使用します。
+/// 受信が遅れてイベントを取りこぼした場合は `resync` を送信し、続けて現在の録画状態を送り直します。
+/// SSEの `event` フィールドはWebSocketの `type` と同じ値で、`data` は同じJSONです。
+pub async fn handle_recording_events(
+    State(state): State<AppState>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, AppError> {
+    let (events, shutdown) = {
+        let state_guard = state.lock()?;
+        // サーバーを起動せずにルーターだけを使う場合は停止要求を受けない
+        let shutdown = state_guard
+            .server_handle
+            .as_ref()
+            .map(|handle| handle.shutdown_token())
+            .unwrap_or_default();
+        (state_guard.events.subscribe(), shutdown)
+    };
+    let pending = current_status(&state).into_iter().collect();
+
+    let stream = stream::unfold(
+        RecordingEvents {
+            state,
+            events,
+            shutdown,
+            pending,
+        },
+        next_event,
+    );
+
+    Ok(Sse::new(stream).keep_alive(KeepAlive::new().interval(KEEP_ALIVE_INTERVAL)))
+}
+
+// 次に送信する録画イベントを待つ（サーバー停止時はストリームを終了する）
+async fn next_event(mut stream: RecordingEvents) -> Option<(Result<Event, Infallible>, RecordingEvents)> {
+    loop {
+        if let Some(event) = stream.pending.pop_front() {
+            match to_sse_event(&event) {
+                Some(event) => return Some((Ok(event), stream)),
+                None => continue,
+            }
+        }
+
+        let event = tokio::select! {
+            event = stream.events.recv() => event,
+            _ = stream.shutdown.cancelled() => return None,
+        };
+        match event {
+            Ok(
+                event @ (ServerEvent::RecordingStatus(_)
+                | ServerEvent::RecordedKey { .. }
+                | ServerEvent::RecordedKeyWarning { .. }),
+            ) => {
+                stream.pending.push_back(event);
+            }
+            Ok(_) => {}
+            // 取りこぼしたことを通知し、現在の状態を送り直す（キー数は `recordedKeysCount` で確認できる）
+            Err(RecvError::Lagged(skipped)) => {
+                stream.pending.push_back(ServerEvent::Resync { skipped });
+                stream.pending.extend(current_status(&stream.state));
+            }
+            Err(RecvError::Closed) => return None,
+        }
+    }
+}
+
+fn current_status(state: &AppState) -> Option<ServerEvent> {
+    let state_guard = state.lock().ok()?;
+    Some(ServerEvent::RecordingStatus(recording_status_response(
+        state_guard.recording_modal_info.as_ref(),
+    )))
+}
+
+fn to_sse_event(event: &ServerEvent) -> Option<Event> {
+    let name = match event {
+        ServerEvent::RecordingStatus(_) => "recording_status",
+        ServerEvent::RecordedKey { .. } => "recorded_key",
+        ServerEvent::RecordedKeyWarning { .. } => "recorded_key_warning",
+        ServerEvent::Resync { .. } => "resync",
+        _ => return None,
+    };
+    Event::default().event(name).json_data(event).ok()
+}
@@ -1,23 +1,47 @@
 use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
+use std::sync::RwLock;
 use std::time::{SystemTime, UNIX_EPOCH};
 
+use lazy_static::lazy_static;
+
 // lib.rsで定義された型を一時的に参照
 // 後の段階でこれらの型もここに移動予定
 
-/// カスタムアクション保存ファイルのパスを取得する関数
+// コマンドラインで指定されたデータディレクトリ（未指定の場合はプラットフォーム標準）
+lazy_static! {
+    static ref APP_DATA_DIR_OVERRIDE: RwLock<Option<PathBuf>> = RwLock::new(None);
+}
+
+/// アプリケーションデータディレクトリを変更する関数
+///
+/// ヘッドレス起動時の `--data-dir` 用です。読み込み・保存の前に呼び出してください。
+pub fn set_app_data_dir(path: PathBuf) {
+    if let Ok(mut data_dir) = APP_DATA_DIR_OVERRIDE.write() {
+        *data_dir = Some(path);
+    }
+}
+
+/// アプリケーションデータディレクトリを取得する関数
 /// 
-/// プラットフォーム別のアプリケーションデータディレクトリを取得し、
-/// カスタムアクションの保存ファイルパスを返します。
+/// プラットフォーム別のアプリケーションデータディレクトリ（`set_app_data_dir` で
+/// 変更されている場合はそのディレクトリ）を取得し、存在しない場合は作成します。
 /// 
 /// # Returns
 /// 
-/// * `Ok(PathBuf)` - カスタムアクション保存ファイルのパス
+/// * `Ok(PathBuf)` - アプリケーションデータディレクトリのパス
 /// * `Err(String)` - ディレクトリの取得または作成に失敗した場合のエラー
-pub fn get_custom_actions_file_path() -> Result<PathBuf, String> {
+pub fn get_app_data_dir() -> Result<PathBuf, String> {
+    let data_dir_override = APP_DATA_DIR_OVERRIDE
+        .read()
+        .ok()
+        .and_then(|data_dir| data_dir.clone());
+    
     // アプリケーション固有のデータディレクトリを取得
-    let app_data_dir = if cfg!(target_os = "macos") {
+    let app_data_dir = if let Some(data_dir) = data_dir_override {
+        data_dir
+    } else if cfg!(target_os = "macos") {
         dirs::home_dir()
             .ok_or("Failed to get home directory")?
             .join("Library")
@@ -40,7 +64,17 @@ pub fn get_custom_actions_file_path() -> Result<PathBuf, String> {
         
     }
 
-    Ok(app_data_dir.join("custom_actions.json"))
+    Ok(app_data_dir)
+}
+
+/// カスタムアクション保存ファイルのパスを取得する関数
+/// 
+/// # Returns
+/// 
+/// * `Ok(PathBuf)` - カスタムアクション保存ファイルのパス
+/// * `Err(String)` - ディレクトリの取得または作成に失敗した場合のエラー
+pub fn get_custom_actions_file_path() -> Result<PathBuf, String> {
+    Ok(get_app_data_dir()?.join("custom_actions.json"))
 }
 
 /// カスタムアクションをファイルに保存する関数
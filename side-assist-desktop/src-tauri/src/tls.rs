@@ -0,0 +1,230 @@
+use std::fs;
+use std::future::Future;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::time::Duration;
+
+use axum_server::accept::Accept;
+use axum_server::tls_rustls::{RustlsAcceptor, RustlsConfig};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use sha2::{Digest, Sha256};
+use tokio::net::TcpStream;
+use tokio_util::either::Either;
+
+use crate::settings::get_current_settings;
+use crate::storage::get_app_data_dir;
+
+/// TLSのハンドシェイクレコードの先頭バイト（ContentType: handshake）
+const TLS_HANDSHAKE_RECORD: u8 = 0x16;
+
+/// 接続後に最初のバイトを待つ時間
+const FIRST_BYTE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 自己署名証明書に含めるホスト名（接続時はフィンガープリントで検証するため固定値で十分）
+const CERTIFICATE_SUBJECT_NAMES: &[&str] = &["localhost", "side-assist.local"];
+
+/// HTTPS/WSSで使用する証明書と秘密鍵
+///
+/// 初回起動時に自己署名証明書を生成し、アプリケーションデータディレクトリに保存します。
+/// モバイルアプリはQRコードに含まれるフィンガープリントで証明書を検証（ピン留め）します。
+#[derive(Clone)]
+pub struct TlsIdentity {
+    pub cert_pem: String,
+    pub key_pem: String,
+    /// 証明書（DER）のSHA-256ハッシュ（16進文字列）
+    pub fingerprint: String,
+}
+
+// 秘密鍵を含むためDebug出力ではフィンガープリントのみを表示する
+impl std::fmt::Debug for TlsIdentity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TlsIdentity")
+            .field("fingerprint", &self.fingerprint)
+            .finish_non_exhaustive()
+    }
+}
+
+fn get_certificate_file_path() -> Result<PathBuf, String> {
+    Ok(get_app_data_dir()?.join("tls_cert.pem"))
+}
+
+fn get_private_key_file_path() -> Result<PathBuf, String> {
+    Ok(get_app_data_dir()?.join("tls_key.pem"))
+}
+
+/// 保存済みの証明書を読み込む関数（存在しない場合は生成して保存する）
+///
+/// # Returns
+///
+/// * `Ok(TlsIdentity)` - 証明書・秘密鍵・フィンガープリント
+/// * `Err(String)` - 読み込み・生成・保存に失敗した場合のエラーメッセージ
+pub fn load_or_create_identity() -> Result<TlsIdentity, String> {
+    let cert_path = get_certificate_file_path()?;
+    let key_path = get_private_key_file_path()?;
+
+    if cert_path.exists() && key_path.exists() {
+        let cert_pem = fs::read_to_string(&cert_path)
+            .map_err(|e| format!("Failed to read certificate file: {}", e))?;
+        let key_pem = fs::read_to_string(&key_path)
+            .map_err(|e| format!("Failed to read private key file: {}", e))?;
+        let fingerprint = certificate_fingerprint(&cert_pem)?;
+
+        return Ok(TlsIdentity {
+            cert_pem,
+            key_pem,
+            fingerprint,
+        });
+    }
+
+    let identity = generate_identity()?;
+
+    write_private_key(&key_path, &identity.key_pem)?;
+    fs::write(&cert_path, &identity.cert_pem)
+        .map_err(|e| format!("Failed to write certificate file: {}", e))?;
+
+    Ok(identity)
+}
+
+// 新しい自己署名証明書を生成する
+fn generate_identity() -> Result<TlsIdentity, String> {
+    let subject_alt_names = CERTIFICATE_SUBJECT_NAMES
+        .iter()
+        .map(|name| name.to_string())
+        .collect::<Vec<_>>();
+    let certified_key = rcgen::generate_simple_self_signed(subject_alt_names)
+        .map_err(|e| format!("Failed to generate certificate: {}", e))?;
+
+    Ok(TlsIdentity {
+        cert_pem: certified_key.cert.pem(),
+        key_pem: certified_key.key_pair.serialize_pem(),
+        fingerprint: sha256_hex(certified_key.cert.der()),
+    })
+}
+
+/// PEM形式の証明書からフィンガープリント（DERのSHA-256、16進文字列）を計算する関数
+pub fn certificate_fingerprint(cert_pem: &str) -> Result<String, String> {
+    let body: String = cert_pem
+        .lines()
+        .skip_while(|line| !line.starts_with("-----BEGIN CERTIFICATE-----"))
+        .skip(1)
+        .take_while(|line| !line.starts_with("-----END CERTIFICATE-----"))
+        .map(|line| line.trim())
+        .collect();
+    if body.is_empty() {
+        return Err("Certificate file does not contain a certificate".to_string());
+    }
+
+    let der = STANDARD
+        .decode(body)
+        .map_err(|e| format!("Failed to decode certificate: {}", e))?;
+    Ok(sha256_hex(&der))
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+// 秘密鍵を所有者のみ読み書き可能なファイルに保存する
+//
+// 作成時から権限を制限した一時ファイルに書き込んでから置き換えるため、
+// 他のユーザーが読める状態の秘密鍵ファイルが存在する瞬間はありません。
+fn write_private_key(path: &Path, key_pem: &str) -> Result<(), String> {
+    let temp_path = path.with_extension("pem.tmp");
+    // 前回中断した時の一時ファイルは権限が正しいとは限らないため作り直す
+    let _ = fs::remove_file(&temp_path);
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let mut file = options
+        .open(&temp_path)
+        .map_err(|e| format!("Failed to create private key file: {}", e))?;
+    file.write_all(key_pem.as_bytes())
+        .and_then(|_| file.sync_all())
+        .map_err(|e| format!("Failed to write private key file: {}", e))?;
+    fs::rename(&temp_path, path).map_err(|e| format!("Failed to write private key file: {}", e))
+}
+
+type TlsStream = <RustlsAcceptor as Accept<TcpStream, ()>>::Stream;
+
+/// TLSと平文HTTPを同じポートで受け付けるアクセプター
+///
+/// 接続の最初のバイトがTLSのハンドシェイクであればTLSで処理し、それ以外は設定の
+/// `allowPlainHttp` が有効な場合のみ平文HTTPとして処理します（無効な場合は接続を閉じます）。
+/// `allowPlainHttp` は初期状態では無効で、デスクトップでのみ有効にできます。
+/// HTTPSに対応する前のモバイルアプリが、QRコードと同じポートに `http://` で接続できるようにするためです。
+/// 設定は接続ごとに読み込むため、変更はサーバーを再起動せずに反映されます。
+#[derive(Clone)]
+pub struct PlainHttpFallbackAcceptor {
+    tls: RustlsAcceptor,
+}
+
+impl PlainHttpFallbackAcceptor {
+    pub fn new(config: RustlsConfig) -> Self {
+        Self {
+            tls: RustlsAcceptor::new(config),
+        }
+    }
+}
+
+impl<S: Send + 'static> Accept<TcpStream, S> for PlainHttpFallbackAcceptor {
+    type Stream = Either<TlsStream, TcpStream>;
+    type Service = S;
+    type Future = Pin<Box<dyn Future<Output = io::Result<(Self::Stream, S)>> + Send>>;
+
+    fn accept(&self, stream: TcpStream, service: S) -> Self::Future {
+        let tls = self.tls.clone();
+        Box::pin(async move {
+            let mut first_byte = [0u8; 1];
+            let read = tokio::time::timeout(FIRST_BYTE_TIMEOUT, stream.peek(&mut first_byte))
+                .await
+                .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "No data received"))??;
+
+            if read == 1 && first_byte[0] == TLS_HANDSHAKE_RECORD {
+                let (stream, service) = Accept::<TcpStream, S>::accept(&tls, stream, service).await?;
+                return Ok((Either::Left(stream), service));
+            }
+
+            if get_current_settings().allow_plain_http {
+                Ok((Either::Right(stream), service))
+            } else {
+                Err(io::Error::new(io::ErrorKind::InvalidData, "Plain HTTP is disabled"))
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncWriteExt;
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn plain_http_is_refused_on_a_fresh_install() {
+        assert!(!get_current_settings().allow_plain_http);
+
+        let identity = generate_identity().unwrap();
+        let config = RustlsConfig::from_pem(identity.cert_pem.into_bytes(), identity.key_pem.into_bytes())
+            .await
+            .unwrap();
+        let acceptor = PlainHttpFallbackAcceptor::new(config);
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).await.unwrap();
+        client.write_all(b"GET /health HTTP/1.1\r\nHost: localhost\r\n\r\n").await.unwrap();
+        let (stream, _) = listener.accept().await.unwrap();
+
+        let error = Accept::<TcpStream, ()>::accept(&acceptor, stream, ()).await.err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,316 @@
+use std::net::SocketAddr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        ConnectInfo, Query, State,
+    },
+    http::HeaderMap,
+    Extension,
+    response::Response,
+};
+use futures::{SinkExt, StreamExt};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tokio_util::sync::CancellationToken;
+
+use crate::auth::Credentials;
+use crate::clients::ClientMetadata;
+use crate::error::AppError;
+use crate::simulation::UndeliveredChar;
+use crate::events::ServerEvent;
+use crate::{execute_action, ActionType, AppState, ServerState};
+
+/// WebSocket接続中のクライアントの生存確認と認証の再確認を行う間隔（秒）
+///
+/// `settings::MIN_HEARTBEAT_TIMEOUT_SECS` より短くしてください。
+const PRESENCE_REFRESH_SECS: u64 = 5;
+
+/// 1つの接続で実行待ちにできるアクションの数（超えた分は `rate_limited` で失敗を返す）
+const MAX_PENDING_ACTIONS: usize = 32;
+
+/// WebSocket接続時のクエリパラメータ
+///
+/// ブラウザやReact NativeのWebSocketはヘッダーを付与できない場合があるため、
+/// クライアントID・認証情報（`client_id` / `token` / `password`）もクエリで受け付けます。
+/// これらは `auth::require_auth` がヘッダーを優先して検証し、接続にはその検証した値を使用します。
+/// 端末情報（`device_name` / `platform` / `app_version`）も同様にヘッダーの代わりに指定できます。
+#[derive(Deserialize)]
+pub struct WebSocketParams {
+    #[serde(flatten)]
+    metadata: ClientMetadata,
+}
+
+/// クライアントから送信されるメッセージ
+///
+/// `action` は `/input` の `InputRequest.action` と同じ形式です。
+/// `id` を指定すると、対応する `action_result` に同じ値が含まれます。
+#[derive(Deserialize, JsonSchema)]
+pub(crate) struct WebSocketRequest {
+    id: Option<u64>,
+    action: ActionType,
+}
+
+/// アクション実行結果のメッセージ（送信元のクライアントにのみ返す）
+#[derive(Serialize, JsonSchema)]
+pub(crate) struct ActionResultMessage {
+    #[serde(rename = "type")]
+    message_type: &'static str,
+    id: Option<u64>,
+    success: bool,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    code: Option<&'static str>, // 失敗時のエラーコード（HTTPの `code` と同じ値）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    undelivered: Option<Vec<UndeliveredChar>>,
+}
+
+impl ActionResultMessage {
+    fn new(id: Option<u64>, result: Result<String, AppError>) -> Self {
+        let (message, code, undelivered) = match result {
+            Ok(message) => (message, None, None),
+            Err(e) => {
+                let undelivered = match &e {
+                    AppError::InjectionFailed { undelivered, .. } => undelivered.clone(),
+                    _ => None,
+                };
+                (e.message(), Some(e.code()), undelivered)
+            }
+        };
+        Self {
+            message_type: "action_result",
+            id,
+            success: code.is_none(),
+            message,
+            code,
+            undelivered,
+        }
+    }
+}
+
+/// `/ws` エンドポイント
+///
+/// 接続後は `ActionType` メッセージの受信とサーバーイベント（`ServerEvent`）の
+/// プッシュを同じ接続で行います（認証はアップグレード前にミドルウェアで実施）。
+/// アクションは接続ごとのタスクで受信順に実行するため、実行中もイベントの送信や切断は遅れません。
+/// 接続中も `PRESENCE_REFRESH_SECS` ごとに認証を再確認し、デバイスの失効・BAN・
+/// ワンタイムパスワードの再生成や無効化があった場合は接続を閉じます。
+pub async fn handle_websocket(
+    ws: WebSocketUpgrade,
+    headers: HeaderMap,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    Query(params): Query<WebSocketParams>,
+    Extension(authenticated): Extension<Credentials>,
+    State(state): State<AppState>,
+) -> Result<Response, AppError> {
+    // ミドルウェアが認証したクライアントIDを使う（別のクライアントIDを名乗れないようにする）
+    let client_id = authenticated.client_id.ok_or(AppError::MissingClientId)?;
+    let ip = connect_info.map(|ConnectInfo(addr)| addr.ip());
+    let metadata = ClientMetadata::from_headers(&headers, ip).or(params.metadata);
+    let credentials = SessionCredentials {
+        token: authenticated.token,
+        password: authenticated.password,
+        ip,
+    };
+
+    Ok(ws.on_upgrade(move |socket| run_websocket(socket, state, client_id, metadata, credentials)))
+}
+
+// 接続時に使用した認証情報（接続中の再確認に使用）
+struct SessionCredentials {
+    token: Option<String>,
+    password: Option<String>,
+    ip: Option<std::net::IpAddr>,
+}
+
+// 1つのWebSocket接続を処理する
+async fn run_websocket(
+    socket: WebSocket,
+    state: AppState,
+    client_id: String,
+    metadata: ClientMetadata,
+    credentials: SessionCredentials,
+) {
+    let (mut sender, mut receiver) = socket.split();
+
+    // `session` はこの接続で登録したクライアントのセッション（既に `/health` などで登録済みの場合はNone）
+    let (mut events, shutdown, mut disconnect, mut session) = match state.lock() {
+        Ok(mut state_guard) => {
+            let session = register_client(&mut state_guard, &client_id, &metadata);
+            // サーバーを起動せずにルーターだけを使う場合は停止要求を受けない
+            let shutdown = state_guard
+                .server_handle
+                .as_ref()
+                .map(|handle| handle.shutdown_token())
+                .unwrap_or_default();
+            let disconnect = disconnect_token(&state_guard, &client_id);
+            (state_guard.events.subscribe(), shutdown, disconnect, session)
+        }
+        Err(_) => return,
+    };
+
+    // 接続中はヘルスチェックの代わりに定期的に最終アクセス時刻を更新する
+    let mut presence = tokio::time::interval(tokio::time::Duration::from_secs(PRESENCE_REFRESH_SECS));
+
+    let (actions, mut results) = spawn_action_runner(state.clone());
+
+    loop {
+        tokio::select! {
+            message = receiver.next() => {
+                let text = match message {
+                    Some(Ok(Message::Text(text))) => text,
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                    Some(Ok(_)) => continue, // Ping/Pongはaxumが自動で応答する
+                };
+
+                let reply = match parse_text_message(&state, &client_id, &text) {
+                    Ok(request) => match actions.try_send(request) {
+                        Ok(()) => continue, // 実行結果は `results` から送信する
+                        Err(TrySendError::Full(request)) => ActionResultMessage::new(request.id, Err(AppError::RateLimited)),
+                        Err(TrySendError::Closed(_)) => break,
+                    },
+                    Err(reply) => reply,
+                };
+                let json = match serde_json::to_string(&reply) {
+                    Ok(json) => json,
+                    Err(_) => continue,
+                };
+                if sender.send(Message::Text(json)).await.is_err() {
+                    break;
+                }
+            }
+            Some(reply) = results.recv() => {
+                let json = match serde_json::to_string(&reply) {
+                    Ok(json) => json,
+                    Err(_) => continue,
+                };
+                if sender.send(Message::Text(json)).await.is_err() {
+                    break;
+                }
+            }
+            event = events.recv() => {
+                let event = match event {
+                    Ok(event) => event,
+                    // 取りこぼしたイベントは破棄し、クライアントに取得し直すよう通知する
+                    Err(RecvError::Lagged(skipped)) => ServerEvent::Resync { skipped },
+                    Err(RecvError::Closed) => break,
+                };
+                if event.is_desktop_only() {
+                    continue;
+                }
+
+                let json = match serde_json::to_string(&event) {
+                    Ok(json) => json,
+                    Err(_) => continue,
+                };
+                if sender.send(Message::Text(json)).await.is_err() {
+                    break;
+                }
+            }
+            _ = shutdown.cancelled() => {
+                // サーバー停止時は接続を閉じ、グレースフルシャットダウンを待たせない
+                let _ = sender.send(Message::Close(None)).await;
+                break;
+            }
+            _ = disconnect.cancelled() => {
+                // デスクトップから切断された
+                let _ = sender.send(Message::Close(None)).await;
+                break;
+            }
+            _ = presence.tick() => {
+                let authorized = match state.lock() {
+                    Ok(mut state_guard) => {
+                        let authorized = state_guard.is_session_authorized(
+                            &client_id,
+                            credentials.token.as_deref(),
+                            credentials.password.as_deref(),
+                            credentials.ip,
+                        );
+                        if authorized {
+                            session = register_client(&mut state_guard, &client_id, &metadata).or(session);
+                            // 非アクティブとして削除された後に再登録された場合はトークンが変わる
+                            disconnect = disconnect_token(&state_guard, &client_id);
+                        }
+                        authorized
+                    }
+                    Err(_) => false,
+                };
+                if !authorized {
+                    // 接続後に失効・BAN・パスワードの再生成があった
+                    let _ = sender.send(Message::Close(None)).await;
+                    break;
+                }
+            }
+        }
+    }
+
+    // 同じクライアントIDで後から登録された接続（再接続など）は残す
+    if let (Ok(mut state_guard), Some(session)) = (state.lock(), session) {
+        state_guard.remove_client_session(&client_id, session);
+    }
+}
+
+// クライアントの最終アクセス時刻を更新し、新しく登録した場合はそのセッションIDを返す
+fn register_client(state: &mut ServerState, client_id: &str, metadata: &ClientMetadata) -> Option<u64> {
+    let is_new = !state.connected_clients.contains_key(client_id);
+    state.touch_client(client_id, now_secs(), metadata);
+    is_new
+        .then(|| state.connected_clients.get(client_id).map(|client| client.session_id))
+        .flatten()
+}
+
+// クライアントからのテキストメッセージを解析する（解析できない場合は返信するエラーを返す）
+fn parse_text_message(state: &AppState, client_id: &str, text: &str) -> Result<WebSocketRequest, ActionResultMessage> {
+    let request: WebSocketRequest = serde_json::from_str(text).map_err(|e| {
+        ActionResultMessage::new(None, Err(AppError::InvalidRequest(format!("Invalid message: {}", e))))
+    })?;
+
+    if let Ok(mut state_guard) = state.lock() {
+        state_guard.record_client_action(client_id, request.action.name(), now_secs());
+    }
+
+    Ok(request)
+}
+
+// 受信したアクションを順に実行するタスクを開始し、アクションの送信用と実行結果の受信用のチャネルを返す
+//
+// 接続が閉じると、実行中のアクションを最後まで実行してから終了します
+// （途中で止めると押したままのキーが離されないため）。
+fn spawn_action_runner(
+    state: AppState,
+) -> (mpsc::Sender<WebSocketRequest>, mpsc::UnboundedReceiver<ActionResultMessage>) {
+    let (actions, mut pending) = mpsc::channel::<WebSocketRequest>(MAX_PENDING_ACTIONS);
+    let (results, replies) = mpsc::unbounded_channel();
+
+    tokio::spawn(async move {
+        while let Some(request) = pending.recv().await {
+            // 接続が閉じた後は実行待ちのアクションを実行しない
+            if results.is_closed() {
+                break;
+            }
+            let result = execute_action(&state, &request.action).await;
+            let _ = results.send(ActionResultMessage::new(request.id, result));
+        }
+    });
+
+    (actions, replies)
+}
+
+// クライアントの切断用トークンを取得する（登録されていない場合は切断されないトークン）
+fn disconnect_token(state: &ServerState, client_id: &str) -> CancellationToken {
+    state
+        .connected_clients
+        .get(client_id)
+        .map(|client| client.disconnect.clone())
+        .unwrap_or_default()
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
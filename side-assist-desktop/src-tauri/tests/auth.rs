@@ -0,0 +1,59 @@
+mod common;
+
+use axum::body::{to_bytes, Body};
+use axum::http::{Request, StatusCode};
+use common::{test_app, TestApp, TestDataDir, TEST_PASSWORD};
+use serde_json::Value;
+use side_assist_desktop_lib::server;
+use tower::ServiceExt;
+
+// `:id` などのパスパラメータをテスト用の値に置き換える
+fn fill_path_params(path: &str) -> String {
+    path.split('/')
+        .map(|segment| if segment.starts_with(':') { "auth-test" } else { segment })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[tokio::test]
+async fn every_route_outside_the_allowlist_requires_credentials() {
+    // `/auth` はペアリングしたデバイスを保存する
+    let _data_dir = TestDataDir::create();
+    let TestApp { app, .. } = test_app();
+    let routes = server::api_route_list();
+    assert!(!routes.is_empty());
+
+    for (method, path) in routes {
+        let is_public = server::PUBLIC_PATHS.contains(&path);
+        let path = fill_path_params(path);
+        let prefixed_path = format!("{}{}", server::API_PREFIX, path);
+
+        for uri in [prefixed_path.as_str(), path.as_str()] {
+            // 公開パスのうち `/auth` はボディのパスワード自体を検証する
+            let body = if path == "/auth" {
+                format!(r#"{{"password":"{}"}}"#, TEST_PASSWORD)
+            } else {
+                String::new()
+            };
+            // クライアントIDは識別用で、認証情報（トークン・パスワード）ではない
+            let request = Request::builder()
+                .method(method.clone())
+                .uri(uri)
+                .header("x-client-id", "auth-test")
+                .header("content-type", "application/json")
+                .body(Body::from(body))
+                .unwrap();
+            let response = app.clone().oneshot(request).await.unwrap();
+            let status = response.status();
+
+            if is_public {
+                assert!(status.is_success(), "{} {} returned {}", method, uri, status);
+            } else {
+                assert_eq!(status, StatusCode::UNAUTHORIZED, "{} {} is not protected", method, uri);
+                let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+                let body: Value = serde_json::from_slice(&body).unwrap();
+                assert_eq!(body["code"], "unauthorized", "{} {}", method, uri);
+            }
+        }
+    }
+}
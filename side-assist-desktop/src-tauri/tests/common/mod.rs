@@ -0,0 +1,75 @@
+// 結合テストで共通のサーバー状態・ルーター・データディレクトリ
+#![allow(dead_code)] // テストファイルごとに使う関数が異なる
+
+use std::fs;
+use std::net::{IpAddr, Ipv4Addr};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use axum::Router;
+use side_assist_desktop_lib::backend::RecordingBackend;
+use side_assist_desktop_lib::{create_router, server, AppState, ServerState};
+
+/// テスト用のワンタイムパスワード（`x-password` ヘッダーに指定する）
+pub const TEST_PASSWORD: &str = "12345";
+
+/// テスト用のサーバー状態とルーター
+pub struct TestApp {
+    pub app: Router,
+    pub state: AppState,
+    pub recorder: Arc<RecordingBackend>, // 送信された入力イベントの記録
+}
+
+/// テスト用のサーバー状態を作る
+///
+/// 入力は記録用バックエンドに送り、ワンタイムパスワードは `TEST_PASSWORD` に固定します。
+/// サーバーを起動する場合は空いているポートに割り当て、ローカルホストのみで待ち受けます。
+pub fn test_state() -> (AppState, Arc<RecordingBackend>) {
+    let recorder = Arc::new(RecordingBackend::new());
+    let state = Arc::new(Mutex::new(ServerState {
+        port: 0,
+        bind_address: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+        input_backend: recorder.clone(),
+        ..ServerState::default()
+    }));
+    server::set_one_time_password(&state, TEST_PASSWORD, 300).unwrap();
+    (state, recorder)
+}
+
+/// テストごとに別のサーバー状態を持つルーターを作る
+pub fn test_app() -> TestApp {
+    let (state, recorder) = test_state();
+    TestApp {
+        app: create_router(Arc::clone(&state)),
+        state,
+        recorder,
+    }
+}
+
+/// テスト用の空のデータディレクトリ（破棄時に削除する）
+///
+/// 設定・証明書・カスタムアクションなどをユーザーのデータディレクトリに保存しないために使います。
+/// データディレクトリはプロセス全体で共有されるため、1つのテストファイルでは1つのテストだけが作成します。
+pub struct TestDataDir {
+    pub path: PathBuf,
+}
+
+impl TestDataDir {
+    pub fn create() -> Self {
+        let path = std::env::temp_dir().join(format!(
+            "side-assist-{}-test-{}",
+            env!("CARGO_CRATE_NAME"),
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        server::set_app_data_dir(path.clone());
+        Self { path }
+    }
+}
+
+impl Drop for TestDataDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
@@ -0,0 +1,77 @@
+mod common;
+
+use std::fs;
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use axum::Router;
+use common::{test_app, TestApp, TestDataDir, TEST_PASSWORD};
+use side_assist_desktop_lib::CustomAction;
+use tower::ServiceExt;
+
+fn test_action(id: &str) -> CustomAction {
+    CustomAction {
+        id: id.to_string(),
+        name: "Original".to_string(),
+        icon: None,
+        key_sequence: Vec::new(),
+        created_at: 0,
+        shortcut_type: Default::default(),
+        order: 0,
+        steps: Vec::new(),
+    }
+}
+
+async fn send(app: &Router, method: &str, uri: &str, body: &str) -> StatusCode {
+    let request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header("x-client-id", "custom-actions-test")
+        .header("x-password", TEST_PASSWORD)
+        .header("content-type", "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap();
+    app.clone().oneshot(request).await.unwrap().status()
+}
+
+#[tokio::test]
+async fn custom_actions_change_only_after_they_are_saved() {
+    let data_dir = TestDataDir::create();
+    let actions_file = data_dir.path.join("custom_actions.json");
+
+    // 保存先がディレクトリのため書き込みに失敗する
+    fs::create_dir_all(&actions_file).unwrap();
+    let TestApp { app, state, .. } = test_app();
+    state.lock().unwrap().custom_actions.insert("action-1".to_string(), test_action("action-1"));
+    let mut events = state.lock().unwrap().events.subscribe();
+
+    let status = send(&app, "PATCH", "/api/v1/custom_actions/action-1", r#"{"name":"Renamed"}"#).await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(state.lock().unwrap().custom_actions["action-1"].name, "Original");
+
+    let key_sequence = r#"{"key_sequence":[{"key":"KeyA","event_type":"press","timestamp":0},
+        {"key":"KeyA","event_type":"release","timestamp":10}]}"#;
+    let status = send(&app, "PUT", "/api/v1/custom_actions/action-1/key_sequence", key_sequence).await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+    let edits = r#"{"edits":[{"op":"insert","index":0,"step":{"key":"KeyA","event_type":"press","timestamp":0}},
+        {"op":"insert","index":1,"step":{"key":"KeyA","event_type":"release","timestamp":10}}]}"#;
+    let status = send(&app, "PATCH", "/api/v1/custom_actions/action-1/key_sequence", edits).await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+    assert!(state.lock().unwrap().custom_actions["action-1"].key_sequence.is_empty());
+
+    assert!(events.try_recv().is_err(), "a change that was not saved must not be published");
+
+    // 同時に複製しても、最後に保存した一覧に全てのアクションが含まれる
+    fs::remove_dir(&actions_file).unwrap();
+    let duplicates = (0..10).map(|_| {
+        let app = app.clone();
+        async move { send(&app, "POST", "/api/v1/custom_actions/action-1/duplicate", "").await }
+    });
+    for status in futures::future::join_all(duplicates).await {
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    let saved: serde_json::Value = serde_json::from_str(&fs::read_to_string(&actions_file).unwrap()).unwrap();
+    assert_eq!(saved["actions"].as_array().unwrap().len(), 11);
+    assert_eq!(state.lock().unwrap().custom_actions.len(), 11);
+}
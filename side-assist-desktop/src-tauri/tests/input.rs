@@ -0,0 +1,183 @@
+mod common;
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use axum::Router;
+use rdev::{EventType, Key};
+use serde_json::{json, Value};
+use common::{test_app, TestApp, TEST_PASSWORD};
+use side_assist_desktop_lib::CustomAction;
+use tower::ServiceExt;
+
+#[cfg(target_os = "macos")]
+const MODIFIER: Key = Key::MetaLeft;
+#[cfg(not(target_os = "macos"))]
+const MODIFIER: Key = Key::ControlLeft;
+
+async fn post_input(app: &Router, action: Value) -> StatusCode {
+    let request = Request::builder()
+        .method("POST")
+        .uri("/api/v1/input")
+        .header("content-type", "application/json")
+        .header("x-client-id", "input-test")
+        .header("x-password", TEST_PASSWORD)
+        .body(Body::from(json!({ "action": action }).to_string()))
+        .unwrap();
+    app.clone().oneshot(request).await.unwrap().status()
+}
+
+fn keystroke(key: Key) -> [EventType; 2] {
+    [EventType::KeyPress(key), EventType::KeyRelease(key)]
+}
+
+#[tokio::test]
+async fn text_is_typed_as_keystrokes() {
+    let TestApp { app, recorder, .. } = test_app();
+
+    assert_eq!(post_input(&app, json!({ "type": "text", "text": "aB1" })).await, StatusCode::OK);
+
+    let mut expected = Vec::new();
+    expected.extend(keystroke(Key::KeyA));
+    expected.push(EventType::KeyPress(Key::ShiftLeft));
+    expected.extend(keystroke(Key::KeyB));
+    expected.push(EventType::KeyRelease(Key::ShiftLeft));
+    expected.extend(keystroke(Key::Num1));
+    assert_eq!(recorder.take_events(), expected);
+}
+
+#[tokio::test]
+async fn copy_and_paste_send_the_platform_shortcut() {
+    let TestApp { app, recorder, .. } = test_app();
+
+    assert_eq!(post_input(&app, json!({ "type": "copy" })).await, StatusCode::OK);
+    assert_eq!(
+        recorder.take_events(),
+        vec![
+            EventType::KeyPress(MODIFIER),
+            EventType::KeyPress(Key::KeyC),
+            EventType::KeyRelease(Key::KeyC),
+            EventType::KeyRelease(MODIFIER),
+        ]
+    );
+
+    assert_eq!(post_input(&app, json!({ "type": "paste" })).await, StatusCode::OK);
+    assert_eq!(
+        recorder.take_events(),
+        vec![
+            EventType::KeyPress(MODIFIER),
+            EventType::KeyPress(Key::KeyV),
+            EventType::KeyRelease(Key::KeyV),
+            EventType::KeyRelease(MODIFIER),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn custom_action_steps_are_played_back_in_order() {
+    let TestApp { app, state, recorder } = test_app();
+    let action: CustomAction = serde_json::from_value(json!({
+        "id": "input-test",
+        "name": "Input test",
+        "key_sequence": [],
+        "created_at": 0,
+        "steps": [
+            { "type": "chord", "keys": ["ShiftLeft", "KeyT"] },
+            { "type": "key_press", "key": "AltLeft" },
+            { "type": "key_release", "key": "AltLeft" },
+        ],
+    }))
+    .unwrap();
+    state.lock().unwrap().custom_actions.insert(action.id.clone(), action);
+
+    let status = post_input(&app, json!({ "type": "custom", "action_id": "input-test" })).await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        recorder.take_events(),
+        vec![
+            EventType::KeyPress(Key::ShiftLeft),
+            EventType::KeyPress(Key::KeyT),
+            EventType::KeyRelease(Key::KeyT),
+            EventType::KeyRelease(Key::ShiftLeft),
+            EventType::KeyPress(Key::Alt),
+            EventType::KeyRelease(Key::Alt),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn keys_left_pressed_are_released_after_the_action() {
+    let TestApp { app, state, recorder } = test_app();
+    let action: CustomAction = serde_json::from_value(json!({
+        "id": "held-keys-test",
+        "name": "Held keys test",
+        "key_sequence": [],
+        "created_at": 0,
+        "steps": [
+            { "type": "key_press", "key": "ShiftLeft" },
+            { "type": "key_press", "key": "ControlLeft" },
+            { "type": "key_press", "key": "ShiftLeft" },
+            { "type": "key_release", "key": "ControlLeft" },
+        ],
+    }))
+    .unwrap();
+    state.lock().unwrap().custom_actions.insert(action.id.clone(), action);
+
+    let status = post_input(&app, json!({ "type": "custom", "action_id": "held-keys-test" })).await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        recorder.take_events(),
+        vec![
+            EventType::KeyPress(Key::ShiftLeft),
+            EventType::KeyPress(Key::ControlLeft),
+            EventType::KeyPress(Key::ShiftLeft),
+            EventType::KeyRelease(Key::ControlLeft),
+            EventType::KeyRelease(Key::ShiftLeft),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn custom_actions_with_steps_cannot_be_recorded_again() {
+    let TestApp { app, state, .. } = test_app();
+    let action: CustomAction = serde_json::from_value(json!({
+        "id": "macro-test",
+        "name": "Macro test",
+        "key_sequence": [],
+        "created_at": 0,
+        "steps": [{ "type": "text", "text": "hi" }],
+    }))
+    .unwrap();
+    state.lock().unwrap().custom_actions.insert(action.id.clone(), action);
+
+    let prepare = json!({ "type": "prepare_recording", "action_id": "macro-test", "name": "Macro test" });
+    assert_eq!(post_input(&app, prepare).await, StatusCode::BAD_REQUEST);
+
+    let state = state.lock().unwrap();
+    assert!(state.recording_modal_info.is_none());
+    assert_eq!(state.custom_actions["macro-test"].steps.len(), 1);
+}
+
+#[tokio::test]
+async fn unknown_custom_action_sends_nothing() {
+    let TestApp { app, recorder, .. } = test_app();
+
+    let status = post_input(&app, json!({ "type": "custom", "action_id": "missing" })).await;
+
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert!(recorder.events().is_empty());
+}
+
+#[tokio::test]
+async fn relative_mouse_move_starts_from_the_real_cursor_position() {
+    let TestApp { app, recorder, .. } = test_app();
+
+    assert_eq!(post_input(&app, json!({ "type": "mouse_move_to", "x": 100.0, "y": 100.0 })).await, StatusCode::OK);
+    // 移動後にユーザーが物理マウスでカーソルを動かした場合
+    recorder.set_cursor_position(500.0, 300.0);
+    recorder.take_events();
+
+    assert_eq!(post_input(&app, json!({ "type": "mouse_move", "dx": 10.0, "dy": -20.0 })).await, StatusCode::OK);
+    assert_eq!(recorder.take_events(), vec![EventType::MouseMove { x: 510.0, y: 280.0 }]);
+}
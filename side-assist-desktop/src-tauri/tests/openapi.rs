@@ -0,0 +1,119 @@
+mod common;
+
+use std::fs;
+use std::path::PathBuf;
+
+use axum::body::Body;
+use axum::http::{Method, Request, StatusCode};
+use common::{test_app, TestApp, TestDataDir, TEST_PASSWORD};
+use side_assist_desktop_lib::{openapi::openapi_spec, server, CustomAction};
+use tower::ServiceExt;
+
+// コミット済みのドキュメント（モバイルアプリのクライアント生成に使用する）
+fn spec_path() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("openapi.json")
+}
+
+// パスパラメータ（`{id}`）に指定するカスタムアクションのID
+const TEST_ACTION_ID: &str = "openapi-test";
+
+fn test_action() -> CustomAction {
+    CustomAction {
+        id: TEST_ACTION_ID.to_string(),
+        name: "OpenAPI test".to_string(),
+        icon: None,
+        key_sequence: Vec::new(),
+        created_at: 0,
+        shortcut_type: Default::default(),
+        order: 0,
+        steps: Vec::new(),
+    }
+}
+
+// `{id}` などのパスパラメータをテスト用の値に置き換える
+fn fill_path_params(path: &str) -> String {
+    path.split('/')
+        .map(|segment| if segment.starts_with('{') { TEST_ACTION_ID } else { segment })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[test]
+fn openapi_json_is_up_to_date() {
+    let generated = serde_json::to_string_pretty(&openapi_spec()).unwrap() + "\n";
+
+    // `UPDATE_OPENAPI=1 cargo test --test openapi` で再生成する
+    if std::env::var_os("UPDATE_OPENAPI").is_some() {
+        fs::write(spec_path(), &generated).unwrap();
+        return;
+    }
+
+    let committed = fs::read_to_string(spec_path()).unwrap_or_default();
+    assert!(
+        committed == generated,
+        "openapi.json is out of date. Run `UPDATE_OPENAPI=1 cargo test --test openapi` and commit the result"
+    );
+}
+
+#[tokio::test]
+async fn documented_paths_are_routed() {
+    // 削除・複製はカスタムアクションを保存するため、ユーザーのデータディレクトリを使わない
+    let _data_dir = TestDataDir::create();
+    let TestApp { app, state, .. } = test_app();
+
+    let spec = openapi_spec();
+    let paths = spec["paths"].as_object().unwrap();
+    assert!(!paths.is_empty());
+
+    for (path, operations) in paths {
+        let path = fill_path_params(path);
+        let legacy_path = path.strip_prefix(server::API_PREFIX).unwrap();
+        for method in operations.as_object().unwrap().keys() {
+            for uri in [path.as_str(), legacy_path] {
+                // 削除などで消えたアクションを戻し、ハンドラーの404（action_not_found）と区別する
+                state.lock().unwrap().custom_actions.insert(TEST_ACTION_ID.to_string(), test_action());
+
+                // ボディなしで送るため、入力が必要なアクションは実行されずに400・409・415などで終わる
+                let request = Request::builder()
+                    .method(Method::from_bytes(method.to_uppercase().as_bytes()).unwrap())
+                    .uri(uri)
+                    .header("x-client-id", "openapi-test")
+                    .header("x-password", TEST_PASSWORD)
+                    .body(Body::empty())
+                    .unwrap();
+                let status = app.clone().oneshot(request).await.unwrap().status();
+
+                assert_ne!(status, StatusCode::NOT_FOUND, "{} {} is not routed", method, uri);
+                assert_ne!(status, StatusCode::METHOD_NOT_ALLOWED, "{} {} is not routed", method, uri);
+            }
+        }
+    }
+}
+
+#[test]
+fn routed_paths_are_documented() {
+    let spec = openapi_spec();
+    let routes = server::api_route_list();
+    assert!(!routes.is_empty());
+
+    for (method, path) in routes {
+        // axumの `:id` をOpenAPIの `{id}` に置き換える
+        let documented_path = path
+            .split('/')
+            .map(|segment| match segment.strip_prefix(':') {
+                Some(name) => format!("{{{}}}", name),
+                None => segment.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join("/");
+        let documented_path = format!("{}{}", server::API_PREFIX, documented_path);
+        let method = method.as_str().to_lowercase();
+
+        assert!(
+            spec["paths"][&documented_path][&method].is_object(),
+            "{} {} is routed but not documented in openapi.json",
+            method,
+            documented_path
+        );
+    }
+}
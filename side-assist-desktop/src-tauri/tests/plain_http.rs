@@ -0,0 +1,60 @@
+mod common;
+
+use std::net::SocketAddr;
+
+use common::{test_state, TestDataDir, TEST_PASSWORD};
+use side_assist_desktop_lib::server;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+// 平文HTTPでリクエストを送り、レスポンス全体を返す（接続を閉じられた場合は空文字列）
+async fn plain_request(addr: SocketAddr, method: &str, path: &str, body: &str) -> String {
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    let request = format!(
+        "{} {} HTTP/1.1\r\nHost: localhost\r\nx-client-id: plain-http-test\r\nx-password: {}\r\n\
+         content-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+        method,
+        path,
+        TEST_PASSWORD,
+        body.len(),
+        body
+    );
+    stream.write_all(request.as_bytes()).await.unwrap();
+
+    let mut response = Vec::new();
+    let _ = stream.read_to_end(&mut response).await;
+    String::from_utf8_lossy(&response).into_owned()
+}
+
+#[tokio::test]
+async fn plain_http_is_served_on_the_tls_port_only_when_enabled_on_the_desktop() {
+    // 初回起動と同じく、設定ファイルのないデータディレクトリから読み込む
+    let data_dir = TestDataDir::create();
+    let (state, _recorder) = test_state();
+    server::load_persistent_state(&state).await;
+    let addr = server::start_server(&state).await.unwrap();
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let key_mode = std::fs::metadata(data_dir.path.join("tls_key.pem")).unwrap().permissions().mode();
+        assert_eq!(key_mode & 0o777, 0o600);
+    }
+
+    // 初期状態では平文の接続をレスポンスなしで閉じる
+    assert_eq!(plain_request(addr, "GET", "/health", "").await, "");
+
+    server::update_settings(&state, serde_json::json!({ "allowPlainHttp": true })).unwrap();
+    let response = plain_request(addr, "GET", "/health", "").await;
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+
+    // モバイルアプリからは変更できない
+    let response = plain_request(addr, "POST", "/settings", r#"{"settings":{"allowPlainHttp":true}}"#).await;
+    assert!(response.starts_with("HTTP/1.1 403"), "{}", response);
+    assert!(response.contains("desktop_only_setting"), "{}", response);
+
+    server::update_settings(&state, serde_json::json!({ "allowPlainHttp": false })).unwrap();
+    assert_eq!(plain_request(addr, "GET", "/health", "").await, "");
+
+    server::stop_server(&state).await.unwrap();
+}
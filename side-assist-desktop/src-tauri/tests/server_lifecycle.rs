@@ -0,0 +1,42 @@
+mod common;
+
+use std::net::{SocketAddr, TcpListener};
+
+use common::{test_state, TestDataDir};
+use side_assist_desktop_lib::server;
+use tokio::net::TcpStream;
+
+#[tokio::test]
+async fn server_can_be_restarted_on_the_same_port() {
+    let _data_dir = TestDataDir::create();
+
+    // 空いているポートを割り当ててもらい、以降は同じポートで再起動する
+    let (state, _recorder) = test_state();
+    let addr = server::start_server(&state).await.unwrap();
+    state.lock().unwrap().port = addr.port();
+    server::stop_server(&state).await.unwrap();
+
+    for _ in 0..3 {
+        let addr = server::start_server(&state).await.unwrap();
+        assert!(state.lock().unwrap().running);
+        TcpStream::connect(addr).await.unwrap();
+
+        server::stop_server(&state).await.unwrap();
+        {
+            let state = state.lock().unwrap();
+            assert!(!state.running);
+            assert!(state.server_handle.is_none());
+        }
+
+        // 停止後はポートが解放されている
+        drop(TcpListener::bind(SocketAddr::new(addr.ip(), addr.port())).unwrap());
+    }
+}
+
+#[tokio::test]
+async fn stopping_a_stopped_server_succeeds() {
+    let (state, _recorder) = test_state();
+
+    assert_eq!(server::stop_server(&state).await.unwrap(), "Server is not running");
+    assert!(!state.lock().unwrap().running);
+}
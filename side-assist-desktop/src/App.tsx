@@ -68,6 +68,9 @@ function App() {
   // サーバー管理フック
   const {
     serverStatus,
+    connectedClients,
+    disconnectClient,
+    banClient,
     isLoading: isServerLoading,
     oneTimePassword,
     isGeneratingPassword,
@@ -75,6 +78,7 @@ function App() {
     passwordExpired,
     generateOneTimePassword,
     handlePortChange,
+    handleBindAddressChange,
   } = useServer(addLog);
 
   // キーボードテストフック
@@ -200,6 +204,9 @@ function App() {
                   oneTimePassword={oneTimePassword}
                   qrCodeImage={qrCodeImage}
                   passwordExpired={passwordExpired}
+                  clients={connectedClients}
+                  onDisconnectClient={disconnectClient}
+                  onBanClient={banClient}
                 />
               </div>
             </div>
@@ -237,6 +244,8 @@ function App() {
                 <PortSettings
                   currentPort={serverStatus.port}
                   onPortChange={handlePortChange}
+                  currentBindAddress={serverStatus.bind_address}
+                  onBindAddressChange={handleBindAddressChange}
                   isLoading={isServerLoading}
                 />
               </div>
@@ -1,16 +1,31 @@
 import React, { useState } from 'react';
-import { Card, CardHeader, CardTitle, CardContent, Badge, Icon } from './ui';
+import {
+  Card,
+  CardHeader,
+  CardTitle,
+  CardContent,
+  Badge,
+  Button,
+  Icon,
+} from './ui';
+import { ConnectedClientType } from '../services/serverService';
 
 interface ConnectionPanelProps {
   oneTimePassword: string | null;
   qrCodeImage: string | null;
   passwordExpired?: boolean;
+  clients?: ConnectedClientType[];
+  onDisconnectClient?: (clientId: string) => void;
+  onBanClient?: (clientId: string) => void;
 }
 
 export const ConnectionPanel: React.FC<ConnectionPanelProps> = ({
   oneTimePassword,
   qrCodeImage,
   passwordExpired = false,
+  clients = [],
+  onDisconnectClient,
+  onBanClient,
 }) => {
   const [isQRModalOpen, setIsQRModalOpen] = useState(false);
   return (
@@ -121,6 +136,60 @@ export const ConnectionPanel: React.FC<ConnectionPanelProps> = ({
             </div>
           </div>
         </div>
+
+        {/* Connected Devices Section */}
+        <div className='space-y-1 mt-2 shrink-0'>
+          <div className='flex items-center gap-2'>
+            <Icon name='connect' className='text-stone-400' />
+            <h4 className='text-sm font-medium text-stone-200'>
+              Connected Devices
+            </h4>
+          </div>
+          {clients.length > 0 ? (
+            <ul className='space-y-1 max-h-32 overflow-y-auto'>
+              {clients.map(client => (
+                <li
+                  key={client.clientID}
+                  className='flex items-center justify-between gap-2 bg-gray-950/50 border border-gray-700/30 rounded-lg px-2 py-1'
+                >
+                  <div className='min-w-0'>
+                    <p className='text-sm text-stone-200 truncate'>
+                      {client.deviceName ?? client.clientID}
+                    </p>
+                    <p className='text-xs text-stone-400 truncate'>
+                      {[client.platform, client.appVersion, client.ip]
+                        .filter(Boolean)
+                        .join(' · ')}
+                      {client.lastAction && ` · ${client.lastAction}`}
+                    </p>
+                  </div>
+                  <div className='flex gap-1 shrink-0'>
+                    {onDisconnectClient && (
+                      <Button
+                        size='sm'
+                        variant='secondary'
+                        onClick={() => onDisconnectClient(client.clientID)}
+                      >
+                        Disconnect
+                      </Button>
+                    )}
+                    {onBanClient && (
+                      <Button
+                        size='sm'
+                        variant='danger'
+                        onClick={() => onBanClient(client.clientID)}
+                      >
+                        Block
+                      </Button>
+                    )}
+                  </div>
+                </li>
+              ))}
+            </ul>
+          ) : (
+            <p className='text-xs text-stone-400'>No devices connected</p>
+          )}
+        </div>
       </CardContent>
 
       {/* QR Code Overlay */}
@@ -6,6 +6,7 @@ import {
   useImperativeHandle,
 } from 'react';
 import { invoke } from '@tauri-apps/api/core';
+import { errorMessage } from '../utils/errors';
 import { Card } from './ui/Card';
 import { Button } from './ui/Button';
 import { Heading, Text } from './ui/Typography';
@@ -17,6 +18,7 @@ interface CustomAction {
   name: string;
   icon?: string;
   created_at: number;
+  order: number;
   key_sequence: Array<{
     key: string;
     event_type: string;
@@ -29,6 +31,7 @@ interface CustomAction {
     };
   }>;
   shortcut_type: string;
+  steps?: Array<{ type: string; [field: string]: unknown }>; // マクロステップ（ある場合はkey_sequenceの代わりに実行）
 }
 
 interface CustomActionsProps {
@@ -60,7 +63,7 @@ export const CustomActions = forwardRef<CustomActionsRef, CustomActionsProps>(
         onLog(`Loaded ${actions.length} custom actions`, 'success');
       } catch (error) {
         console.error('Failed to load custom actions:', error);
-        onLog(`Failed to load custom actions: ${error}`, 'error');
+        onLog(`Failed to load custom actions: ${errorMessage(error)}`, 'error');
       }
       setIsLoading(false);
     }, [onLog]);
@@ -86,7 +89,46 @@ export const CustomActions = forwardRef<CustomActionsRef, CustomActionsProps>(
         await loadCustomActions(); // Reload the list
       } catch (error) {
         console.error('Failed to update custom action:', error);
-        onLog(`Failed to update custom action: ${error}`, 'error');
+        onLog(`Failed to update custom action: ${errorMessage(error)}`, 'error');
+      }
+      setIsUpdating(false);
+    };
+
+    const handleDuplicate = async () => {
+      if (!editingAction) return;
+
+      setIsUpdating(true);
+      try {
+        const duplicate = await invoke<CustomAction>('duplicate_custom_action', {
+          actionId: editingAction.id,
+        });
+
+        onLog(`Custom action duplicated: ${duplicate.name}`, 'success');
+        setEditingAction(null);
+        setNewName('');
+        await loadCustomActions();
+      } catch (error) {
+        console.error('Failed to duplicate custom action:', error);
+        onLog(`Failed to duplicate custom action: ${errorMessage(error)}`, 'error');
+      }
+      setIsUpdating(false);
+    };
+
+    const handleDelete = async () => {
+      if (!editingAction) return;
+      if (!window.confirm(`「${editingAction.name}」を削除しますか？`)) return;
+
+      setIsUpdating(true);
+      try {
+        await invoke('delete_custom_action', { actionId: editingAction.id });
+
+        onLog(`Custom action deleted: ${editingAction.name}`, 'success');
+        setEditingAction(null);
+        setNewName('');
+        await loadCustomActions();
+      } catch (error) {
+        console.error('Failed to delete custom action:', error);
+        onLog(`Failed to delete custom action: ${errorMessage(error)}`, 'error');
       }
       setIsUpdating(false);
     };
@@ -172,14 +214,20 @@ export const CustomActions = forwardRef<CustomActionsRef, CustomActionsProps>(
                           </Text>
                           <div className='flex items-center gap-2 mt-1'>
                             <Text variant='small' className='text-gray-400'>
-                              {action.key_sequence.length}個のキー
-                            </Text>
-                            <Text variant='small' className='text-gray-500'>
-                              •
-                            </Text>
-                            <Text variant='small' className='text-gray-400'>
-                              {formatShortcutType(action.shortcut_type)}
+                              {action.steps?.length
+                                ? `${action.steps.length}個のステップ`
+                                : `${action.key_sequence.length}個のキー`}
                             </Text>
+                            {!action.steps?.length && (
+                              <>
+                                <Text variant='small' className='text-gray-500'>
+                                  •
+                                </Text>
+                                <Text variant='small' className='text-gray-400'>
+                                  {formatShortcutType(action.shortcut_type)}
+                                </Text>
+                              </>
+                            )}
                             <Text variant='small' className='text-gray-500'>
                               •
                             </Text>
@@ -255,6 +303,23 @@ export const CustomActions = forwardRef<CustomActionsRef, CustomActionsProps>(
             )}
 
             <div className='flex gap-2 pt-4'>
+              <Button
+                variant='danger'
+                onClick={handleDelete}
+                disabled={isUpdating}
+              >
+                削除
+              </Button>
+              <Button
+                variant='secondary'
+                onClick={handleDuplicate}
+                disabled={isUpdating}
+              >
+                複製
+              </Button>
+            </div>
+
+            <div className='flex gap-2'>
               <Button
                 variant='secondary'
                 className='flex-1'
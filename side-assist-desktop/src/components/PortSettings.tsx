@@ -1,19 +1,43 @@
 import React, { useState, useEffect } from 'react';
 import { Card, CardHeader, CardTitle, CardContent, Button, Icon } from './ui';
+import {
+  serverService,
+  NetworkInterfaceType,
+} from '../services/serverService';
 
 interface PortSettingsProps {
   currentPort: number;
   onPortChange: (port: number) => void;
+  currentBindAddress: string | null;
+  onBindAddressChange: (address: string | null) => void;
   isLoading?: boolean;
 }
 
+// 全インターフェースで待ち受ける場合のselectの値
+const ALL_INTERFACES = '';
+
 export const PortSettings: React.FC<PortSettingsProps> = ({
   currentPort,
   onPortChange,
+  currentBindAddress,
+  onBindAddressChange,
   isLoading = false,
 }) => {
   const [portInput, setPortInput] = useState(currentPort.toString());
   const [isEditingPort, setIsEditingPort] = useState(false);
+  const [interfaces, setInterfaces] = useState<NetworkInterfaceType[]>([]);
+
+  const refreshInterfaces = async () => {
+    try {
+      setInterfaces(await serverService.getNetworkInterfaces());
+    } catch (error) {
+      console.error('Failed to get network interfaces:', error);
+    }
+  };
+
+  useEffect(() => {
+    refreshInterfaces();
+  }, []);
 
   useEffect(() => {
     setPortInput(currentPort.toString());
@@ -108,6 +132,37 @@ export const PortSettings: React.FC<PortSettingsProps> = ({
               Port must be between 1024 and 65535
             </p>
           )}
+
+          <div className='pt-2 border-t border-stone-700/30 space-y-2'>
+            <p className='text-sm text-stone-400'>
+              Listen address (also used in the QR code)
+            </p>
+            <div className='flex items-center gap-2'>
+              <select
+                value={currentBindAddress ?? ALL_INTERFACES}
+                onChange={e =>
+                  onBindAddressChange(
+                    e.target.value === ALL_INTERFACES ? null : e.target.value
+                  )
+                }
+                onFocus={refreshInterfaces}
+                disabled={isLoading}
+                className='bg-stone-800/50 border border-stone-600/50 rounded-lg px-3 py-2 text-stone-200 text-sm flex-1 min-w-0 focus:outline-none focus:border-stone-500 focus:ring-1 focus:ring-stone-500'
+              >
+                <option value={ALL_INTERFACES}>All interfaces (IPv4)</option>
+                {interfaces
+                  .filter(networkInterface => !networkInterface.isLinkLocal)
+                  .map(networkInterface => (
+                    <option
+                      key={`${networkInterface.name}-${networkInterface.address}`}
+                      value={networkInterface.address}
+                    >
+                      {networkInterface.name}: {networkInterface.address}
+                    </option>
+                  ))}
+              </select>
+            </div>
+          </div>
         </div>
       </CardContent>
     </Card>
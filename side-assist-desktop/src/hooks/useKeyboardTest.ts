@@ -2,6 +2,7 @@ import { useState, useCallback } from 'react';
 import { useTranslation } from 'react-i18next';
 import { keyboardService } from '../services/keyboardService';
 import { permissionConfig } from '../config/permissions';
+import { errorMessage } from '../utils/errors';
 
 interface LogEntry {
   time: string;
@@ -40,8 +41,8 @@ export const useKeyboardTest = (
         onLog(`${t('activity.keyboardTestPrefix')}: "${text}"`, 'success');
       } catch (error) {
         console.error('Failed to test typing:', error);
-        setTestResult(`${t('messages.failed')}: ${error}`);
-        onLog(`${t('activity.keyboardTestFailed')}: ${error}`, 'error');
+        setTestResult(`${t('messages.failed')}: ${errorMessage(error)}`);
+        onLog(`${t('activity.keyboardTestFailed')}: ${errorMessage(error)}`, 'error');
       } finally {
         setIsLoading(false);
       }
@@ -5,6 +5,7 @@ import {
 } from 'tauri-plugin-macos-permissions-api';
 import { invoke } from '@tauri-apps/api/core';
 import { PermissionConfig } from '../config/permissions';
+import { errorMessage } from '../utils/errors';
 
 interface UsePermissionsReturn {
   hasAccessibilityPermission: boolean | null;
@@ -121,7 +122,7 @@ export const usePermissions = (
     } catch (error) {
       console.error('Failed to request accessibility permission:', error);
       if (onLog) {
-        onLog(`権限要求エラー: ${error}`, 'error');
+        onLog(`権限要求エラー: ${errorMessage(error)}`, 'error');
       }
       return false;
     } finally {
@@ -147,7 +148,7 @@ export const usePermissions = (
     } catch (error) {
       console.error('Failed to open system preferences:', error);
       if (onLog) {
-        onLog(`システム環境設定を開けませんでした: ${error}`, 'error');
+        onLog(`システム環境設定を開けませんでした: ${errorMessage(error)}`, 'error');
       }
     }
   }, [onLog]);
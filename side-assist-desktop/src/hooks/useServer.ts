@@ -1,6 +1,13 @@
 import { useState, useEffect, useCallback, useRef } from 'react';
-import { serverService, ServerStatusType } from '../services/serverService';
+import { listen } from '@tauri-apps/api/event';
+import {
+  serverService,
+  ServerStatusType,
+  ConnectedClientType,
+  ClientPresenceEvent,
+} from '../services/serverService';
 import { passwordService } from '../services/passwordService';
+import { errorMessage } from '../utils/errors';
 
 interface LogEntry {
   time: string;
@@ -19,13 +26,20 @@ export const useServer = (
     running: false,
     connected_clients: 0,
     port: 8080,
+    bind_address: null,
   });
+  const [connectedClients, setConnectedClients] = useState<
+    ConnectedClientType[]
+  >([]);
   const [isLoading, setIsLoading] = useState(false);
   const [oneTimePassword, setOneTimePassword] = useState<string | null>(null);
   const [isGeneratingPassword, setIsGeneratingPassword] = useState(false);
   const [qrCodeImage, setQrCodeImage] = useState<string | null>(null);
   const [passwordExpired, setPasswordExpired] = useState(false);
   const passwordTimerRef = useRef<number | null>(null);
+  // イベントリスナーを登録し直さないよう最新のonLogを参照する
+  const onLogRef = useRef(onLog);
+  onLogRef.current = onLog;
 
   const refreshServerStatus = useCallback(async () => {
     try {
@@ -37,6 +51,38 @@ export const useServer = (
     }
   }, [onLog]);
 
+  const refreshConnectedClients = useCallback(async () => {
+    try {
+      setConnectedClients(await serverService.getConnectedClients());
+    } catch (error) {
+      console.error('Failed to get connected clients:', error);
+    }
+  }, []);
+
+  const disconnectClient = useCallback(
+    async (clientId: string) => {
+      try {
+        onLog(await serverService.disconnectClient(clientId), 'success');
+      } catch (error) {
+        console.error('Failed to disconnect client:', error);
+        onLog(`クライアントの切断に失敗しました: ${errorMessage(error)}`, 'error');
+      }
+    },
+    [onLog]
+  );
+
+  const banClient = useCallback(
+    async (clientId: string) => {
+      try {
+        onLog(await serverService.banClient(clientId), 'success');
+      } catch (error) {
+        console.error('Failed to ban client:', error);
+        onLog(`クライアントの接続禁止に失敗しました: ${errorMessage(error)}`, 'error');
+      }
+    },
+    [onLog]
+  );
+
   const generateQRCode = useCallback(async () => {
     try {
       const qrCode = await passwordService.generateQR();
@@ -44,7 +90,7 @@ export const useServer = (
       onLog('QRコードを生成しました', 'success');
     } catch (error) {
       console.error('Failed to generate QR code:', error);
-      onLog(`QRコード生成に失敗しました: ${error}`, 'error');
+      onLog(`QRコード生成に失敗しました: ${errorMessage(error)}`, 'error');
       setQrCodeImage(null);
     }
   }, [onLog]);
@@ -82,7 +128,7 @@ export const useServer = (
       startPasswordTimer();
     } catch (error) {
       console.error('Failed to generate password:', error);
-      onLog(`パスワード生成に失敗しました: ${error}`, 'error');
+      onLog(`パスワード生成に失敗しました: ${errorMessage(error)}`, 'error');
     } finally {
       setIsGeneratingPassword(false);
     }
@@ -96,7 +142,7 @@ export const useServer = (
       await refreshServerStatus();
     } catch (error) {
       console.error('Failed to start server:', error);
-      onLog(`サーバー開始に失敗しました: ${error}`, 'error');
+      onLog(`サーバー開始に失敗しました: ${errorMessage(error)}`, 'error');
     } finally {
       setIsLoading(false);
     }
@@ -156,7 +202,7 @@ export const useServer = (
         await refreshServerStatus();
       } catch (error) {
         console.error('Failed to change port:', error);
-        onLog(`ポート変更に失敗しました: ${error}`, 'error');
+        onLog(`ポート変更に失敗しました: ${errorMessage(error)}`, 'error');
 
         // エラー時はステータスを再取得して正しい状態に戻す
         await refreshServerStatus();
@@ -167,6 +213,40 @@ export const useServer = (
     [isLoading, onLog, refreshServerStatus]
   );
 
+  const handleBindAddressChange = useCallback(
+    async (address: string | null) => {
+      // 既に処理中の場合は拒否
+      if (isLoading) {
+        onLog('サーバー操作が実行中です。しばらくお待ちください。', 'warning');
+        return;
+      }
+
+      try {
+        setIsLoading(true);
+        onLog(`待ち受けアドレスを${address ?? '全てのインターフェース'}に変更中...`, 'info');
+
+        const result = await serverService.changeBindAddress(address);
+        onLog(result, 'success');
+
+        // 少し待機してからステータスを確認
+        await new Promise(resolve => setTimeout(resolve, 1000));
+        await refreshServerStatus();
+
+        // QRコードの接続先アドレスを更新
+        await generateQRCode();
+      } catch (error) {
+        console.error('Failed to change bind address:', error);
+        onLog(`待ち受けアドレスの変更に失敗しました: ${errorMessage(error)}`, 'error');
+
+        // エラー時はステータスを再取得して正しい状態に戻す
+        await refreshServerStatus();
+      } finally {
+        setIsLoading(false);
+      }
+    },
+    [isLoading, onLog, refreshServerStatus, generateQRCode]
+  );
+
   // クリーンアップ
   useEffect(() => {
     return () => {
@@ -174,6 +254,41 @@ export const useServer = (
     };
   }, [clearPasswordTimer]);
 
+  // クライアントの接続状況をサーバーからのイベントで更新（ポーリングしない）
+  useEffect(() => {
+    const presenceMessages: Record<
+      ClientPresenceEvent['type'],
+      [string, LogEntry['type']]
+    > = {
+      client_connected: ['クライアントが接続しました', 'success'],
+      client_disconnected: ['クライアントが切断しました', 'info'],
+      client_timed_out: ['クライアントの応答がなくなりました', 'warning'],
+    };
+    const onPresence = (event: { payload: ClientPresenceEvent }) => {
+      const [message, type] = presenceMessages[event.payload.type];
+      onLogRef.current(`${message}: ${event.payload.clientID}`, type);
+    };
+
+    const unlisteners = Promise.all([
+      listen<ClientPresenceEvent>('client-connected', onPresence),
+      listen<ClientPresenceEvent>('client-disconnected', onPresence),
+      listen<ClientPresenceEvent>('client-timed-out', onPresence),
+      listen<ConnectedClientType[]>('clients-changed', event => {
+        setConnectedClients(event.payload);
+        setServerStatus(prev => ({
+          ...prev,
+          connected_clients: event.payload.length,
+        }));
+      }),
+    ]);
+
+    refreshConnectedClients();
+
+    return () => {
+      unlisteners.then(fns => fns.forEach(unlisten => unlisten()));
+    };
+  }, [refreshConnectedClients]);
+
   // 初期化
   useEffect(() => {
     const initializeServer = async () => {
@@ -196,15 +311,14 @@ export const useServer = (
         await checkCurrentPassword();
       } catch (error) {
         console.error('Server initialization failed:', error);
-        onLog(`サーバー初期化に失敗しました: ${error}`, 'error');
+        onLog(`サーバー初期化に失敗しました: ${errorMessage(error)}`, 'error');
       }
     };
 
     initializeServer();
 
-    // 定期的にステータスとパスワードを更新
+    // 定期的にパスワードを更新（接続状況はイベントで更新）
     const interval = setInterval(() => {
-      refreshServerStatus();
       checkCurrentPassword();
     }, 5000);
 
@@ -218,15 +332,19 @@ export const useServer = (
 
   return {
     serverStatus,
+    connectedClients,
     isLoading,
     oneTimePassword,
     isGeneratingPassword,
     qrCodeImage,
     passwordExpired,
     refreshServerStatus,
+    disconnectClient,
+    banClient,
     startServer,
     generateOneTimePassword,
     generateQRCode,
     handlePortChange,
+    handleBindAddressChange,
   };
 };
@@ -12,4 +12,18 @@ export const passwordService = {
   async generateQR(): Promise<string> {
     return await invoke<string>('generate_qr_code');
   },
+
+  // data:image/png;base64,... 形式
+  async generateQRPng(): Promise<string> {
+    return await invoke<string>('generate_qr_code_png');
+  },
+
+  // ターミナル・ログ向けのUnicodeブロック文字
+  async generateQRText(): Promise<string> {
+    return await invoke<string>('generate_qr_code_text');
+  },
+
+  async getConnectionUrl(): Promise<string> {
+    return await invoke<string>('get_connection_url');
+  },
 };
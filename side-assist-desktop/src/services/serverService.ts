@@ -1,9 +1,37 @@
 import { invoke } from '@tauri-apps/api/core';
+import { errorMessage } from '../utils/errors';
 
 export interface ServerStatusType {
   running: boolean;
   connected_clients: number;
   port: number;
+  bind_address: string | null;
+}
+
+export interface ConnectedClientType {
+  clientID: string;
+  deviceName: string | null;
+  platform: string | null;
+  appVersion: string | null;
+  ip: string | null;
+  connectedAt: number;
+  lastHealthCheck: number;
+  lastAction: string | null;
+  lastActionAt: number | null;
+}
+
+// client-connected / client-disconnected / client-timed-out イベントのペイロード
+export interface ClientPresenceEvent {
+  type: 'client_connected' | 'client_disconnected' | 'client_timed_out';
+  clientID: string;
+}
+
+export interface NetworkInterfaceType {
+  name: string;
+  address: string;
+  isIpv6: boolean;
+  isLoopback: boolean;
+  isLinkLocal: boolean;
 }
 
 export const serverService = {
@@ -11,6 +39,18 @@ export const serverService = {
     return await invoke<ServerStatusType>('get_server_status');
   },
 
+  async getConnectedClients(): Promise<ConnectedClientType[]> {
+    return await invoke<ConnectedClientType[]>('get_connected_clients');
+  },
+
+  async disconnectClient(clientId: string): Promise<string> {
+    return await invoke<string>('disconnect_client', { clientId });
+  },
+
+  async banClient(clientId: string): Promise<string> {
+    return await invoke<string>('ban_client', { clientId });
+  },
+
   async start(): Promise<string> {
     return await invoke<string>('start_server');
   },
@@ -23,34 +63,55 @@ export const serverService = {
     return await invoke<string>('set_port', { port });
   },
 
-  async changePort(newPort: number): Promise<string> {
-    try {
-      // サーバーを停止
-      await this.stop();
-
-      // 新しいポートを設定
-      await this.setPort(newPort);
-
-      // サーバーを再開（リトライ付き）
-      let retries = 3;
-      let lastError: unknown;
-
-      for (let i = 0; i < retries; i++) {
-        try {
-          return await this.start();
-        } catch (error) {
-          lastError = error;
-
-          if (i < retries - 1) {
-            // 少し待機してからリトライ
-            await new Promise(resolve => setTimeout(resolve, 1000));
-          }
+  async getNetworkInterfaces(): Promise<NetworkInterfaceType[]> {
+    return await invoke<NetworkInterfaceType[]>('get_network_interfaces');
+  },
+
+  async setBindAddress(address: string | null): Promise<string> {
+    return await invoke<string>('set_bind_address', { address });
+  },
+
+  // サーバーを停止して設定を変更し、再開する（リトライ付き）
+  async restartWith(apply: () => Promise<string>): Promise<string> {
+    // サーバーを停止
+    await this.stop();
+
+    // 新しい設定を反映
+    await apply();
+
+    // サーバーを再開（リトライ付き）
+    let retries = 3;
+    let lastError: unknown;
+
+    for (let i = 0; i < retries; i++) {
+      try {
+        return await this.start();
+      } catch (error) {
+        lastError = error;
+
+        if (i < retries - 1) {
+          // 少し待機してからリトライ
+          await new Promise(resolve => setTimeout(resolve, 1000));
         }
       }
+    }
+
+    throw lastError;
+  },
+
+  async changePort(newPort: number): Promise<string> {
+    try {
+      return await this.restartWith(() => this.setPort(newPort));
+    } catch (error) {
+      throw new Error(`ポート変更に失敗しました: ${errorMessage(error)}`);
+    }
+  },
 
-      throw lastError;
+  async changeBindAddress(address: string | null): Promise<string> {
+    try {
+      return await this.restartWith(() => this.setBindAddress(address));
     } catch (error) {
-      throw new Error(`ポート変更に失敗しました: ${error}`);
+      throw new Error(`待ち受けアドレスの変更に失敗しました: ${errorMessage(error)}`);
     }
   },
 };
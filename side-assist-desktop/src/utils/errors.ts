@@ -0,0 +1,27 @@
+// Tauriコマンドのエラー（Rust側の AppError と同じ形式）
+export interface AppErrorType {
+  success: false;
+  code: string;
+  message: string;
+  retryAfterSecs?: number;
+}
+
+export function isAppError(error: unknown): error is AppErrorType {
+  return (
+    typeof error === 'object' &&
+    error !== null &&
+    typeof (error as AppErrorType).code === 'string' &&
+    typeof (error as AppErrorType).message === 'string'
+  );
+}
+
+// ログ表示用のメッセージ（文字列のエラーを返すコマンドにも対応）
+export function errorMessage(error: unknown): string {
+  if (isAppError(error)) {
+    return error.message;
+  }
+  if (error instanceof Error) {
+    return error.message;
+  }
+  return String(error);
+}